@@ -6,6 +6,7 @@ async fn main() -> anyhow::Result<()> {
         kill_id: 12345,
         zkb: krusty::zkb::Zkb {
             href: "https://esi.evetech.net/v1/killmails/130678514/145c457c34ce9c9e8d67e942e764d8f439b22271/".to_string(),
+            ..Default::default()
         },
         killmail: None,
     };
@@ -18,11 +19,21 @@ async fn main() -> anyhow::Result<()> {
                 guild_id: 100,
                 channel_id: 1,
                 filters: vec!["corp:98190062".to_string()],
+                webhook: None,
+                discord_webhook: None,
+                template: None,
+                disabled: None,
+                config_filters: vec![],
             },
             filters::FilterSet {
                 guild_id: 100,
                 channel_id: 3,
                 filters: vec!["corp:98190062".to_string()],
+                webhook: None,
+                discord_webhook: None,
+                template: None,
+                disabled: None,
+                config_filters: vec![],
             },
         ],
         ..Default::default()
@@ -11,15 +11,28 @@ pub enum CommandsEnabled {
     Some(Vec<String>),
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, Clone)]
 pub struct GuildConfig {
     pub commands: CommandsEnabled,
+    // Role ids allowed to use the commands, anyone with the command's Discord
+    // permissions may use them when empty
+    #[serde(default)]
+    pub roles: Vec<u64>,
+    // Role ids allowed to use a specific command, replacing `roles` for it
+    #[serde(default)]
+    pub command_roles: HashMap<String, Vec<u64>>,
+    // Channel filter changes made with commands are posted to
+    #[serde(default)]
+    pub log_channel: Option<u64>,
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, Clone)]
 pub struct Config {
     pub queue_id: Option<String>,
     pub redis_url: Option<String>,
+    pub redisq_url: Option<String>,
+    pub zkillboard_url: Option<String>,
+    pub esi_url: Option<String>,
     pub filters: Option<filters::Config>,
     pub guilds: Option<HashMap<u64, GuildConfig>>,
 }
@@ -45,6 +58,24 @@ impl Config {
             .unwrap_or_else(|| "redis://localhost:6379".to_string())
     }
 
+    pub fn redisq_url(&self) -> String {
+        self.redisq_url
+            .clone()
+            .unwrap_or_else(|| "https://zkillredisq.stream".to_string())
+    }
+
+    pub fn zkillboard_url(&self) -> String {
+        self.zkillboard_url
+            .clone()
+            .unwrap_or_else(|| "https://zkillboard.com".to_string())
+    }
+
+    pub fn esi_url(&self) -> String {
+        self.esi_url
+            .clone()
+            .unwrap_or_else(|| "https://esi.evetech.net/latest".to_string())
+    }
+
     pub fn guild_commands(&self, guild_id: u64) -> CommandsEnabled {
         if let Some(guilds) = &self.guilds
             && let Some(guild_config) = guilds.get(&guild_id)
@@ -54,6 +85,29 @@ impl Config {
 
         CommandsEnabled::None
     }
+
+    // Roles allowed to use the command in the guild, empty when unrestricted
+    pub fn command_roles(&self, guild_id: u64, command: &str) -> Vec<u64> {
+        match self
+            .guilds
+            .as_ref()
+            .and_then(|guilds| guilds.get(&guild_id))
+        {
+            Some(guild_config) => guild_config
+                .command_roles
+                .get(command)
+                .unwrap_or(&guild_config.roles)
+                .clone(),
+            None => vec![],
+        }
+    }
+
+    pub fn log_channel(&self, guild_id: u64) -> Option<u64> {
+        self.guilds
+            .as_ref()
+            .and_then(|guilds| guilds.get(&guild_id))
+            .and_then(|guild_config| guild_config.log_channel)
+    }
 }
 
 #[cfg(test)]
@@ -71,6 +125,10 @@ mod tests {
     commands: !Some
         - ping
         - stats
+    roles: [201, 202]
+    command_roles:
+        stats: []
+    log_channel: 301
 "#;
 
         let guild_configs: HashMap<u64, GuildConfig> =
@@ -96,5 +154,25 @@ mod tests {
             }
             _ => panic!("Expected CommandsEnabled::Some for guild 103"),
         }
+
+        assert!(guild_configs.get(&101).unwrap().roles.is_empty());
+        assert_eq!(guild_configs.get(&103).unwrap().roles, vec![201, 202]);
+
+        let config = Config {
+            queue_id: None,
+            redis_url: None,
+            redisq_url: None,
+            zkillboard_url: None,
+            esi_url: None,
+            filters: None,
+            guilds: Some(guild_configs),
+        };
+
+        assert_eq!(config.command_roles(103, "ping"), vec![201, 202]);
+        assert!(config.command_roles(103, "stats").is_empty());
+        assert!(config.command_roles(101, "ping").is_empty());
+        assert!(config.command_roles(104, "ping").is_empty());
+        assert_eq!(config.log_channel(103), Some(301));
+        assert_eq!(config.log_channel(101), None);
     }
 }
@@ -0,0 +1,237 @@
+use twilight_model::application::command::{CommandOptionChoice, CommandOptionChoiceValue};
+
+use crate::{filters::FilterPreset, static_data};
+
+// Discord shows at most 25 choices with at most 100 characters each
+const MAX_CHOICES: usize = 25;
+const MAX_CHOICE_LENGTH: usize = 100;
+
+const KINDS: &[&str] = &[
+    "region",
+    "system",
+    "ship",
+    "character",
+    "corporation",
+    "alliance",
+];
+const PROPERTIES: &[&str] = &["loss", "kill", "exclude", "with_npc"];
+
+// Suggestions for a filter being typed, completing the kind, system and region
+// names, and the properties depending on how far the filter got
+pub fn filter_choices(input: &str) -> Vec<CommandOptionChoice> {
+    let input = input.trim();
+    let parts = input.splitn(3, ':').collect::<Vec<&str>>();
+
+    let choices = match parts.as_slice() {
+        [kind] => kind_choices(kind),
+        [kind, ids] => id_choices(kind, ids),
+        [kind, ids, properties] => property_choices(&format!("{kind}:{ids}"), properties),
+        _ => vec![],
+    };
+
+    choices
+        .into_iter()
+        .filter(|choice| match &choice.value {
+            CommandOptionChoiceValue::String(value) => fits(value),
+            _ => true,
+        })
+        .take(MAX_CHOICES)
+        .collect()
+}
+
+// Filters of a channel matching what was typed so far
+pub fn existing_filter_choices(filters: &[String], input: &str) -> Vec<CommandOptionChoice> {
+    let input = input.trim().to_lowercase();
+
+    filters
+        .iter()
+        .enumerate()
+        .filter(|(_, filter)| fits(filter) && filter.to_lowercase().contains(&input))
+        .take(MAX_CHOICES)
+        .map(|(index, filter)| choice(format!("{}. {filter}", index + 1), filter.clone()))
+        .collect()
+}
+
+// Presets of a guild whose name starts with what was typed so far
+pub fn preset_choices(presets: &[FilterPreset], input: &str) -> Vec<CommandOptionChoice> {
+    let input = input.trim().to_lowercase();
+
+    presets
+        .iter()
+        .filter(|preset| preset.name.starts_with(&input))
+        .take(MAX_CHOICES)
+        .map(|preset| {
+            choice(
+                format!("{} ({} filters)", preset.name, preset.filters.len()),
+                preset.name.clone(),
+            )
+        })
+        .collect()
+}
+
+fn kind_choices(kind: &str) -> Vec<CommandOptionChoice> {
+    let kind = kind.to_lowercase();
+
+    KINDS
+        .iter()
+        .filter(|k| k.starts_with(&kind))
+        .map(|k| choice(format!("{k}:"), format!("{k}:")))
+        .collect()
+}
+
+fn id_choices(kind: &str, ids: &str) -> Vec<CommandOptionChoice> {
+    // Only the id after the last comma is being typed
+    let (done, current) = match ids.rsplit_once(',') {
+        Some((done, current)) => (format!("{done},"), current.trim()),
+        None => (String::new(), ids.trim()),
+    };
+    let prefix = format!("{kind}:{done}");
+
+    if current.is_empty() {
+        return vec![];
+    }
+
+    if current.parse::<u64>().is_ok() {
+        let value = format!("{prefix}{current}");
+        let mut choices = vec![choice(value.clone(), value.clone())];
+        choices.extend(
+            PROPERTIES
+                .iter()
+                .map(|p| choice(format!("{value}:{p}"), format!("{value}:{p}"))),
+        );
+        return choices;
+    }
+
+    match kind {
+        "system" => static_data::search_systems(current, MAX_CHOICES)
+            .into_iter()
+            .map(|system| {
+                let region = static_data::get_region(system.region_id)
+                    .map(|r| format!(" ({})", r.name))
+                    .unwrap_or_default();
+                choice(
+                    format!("{}{region}", system.name),
+                    format!("{prefix}{}", system.system_id),
+                )
+            })
+            .collect(),
+        "region" => static_data::search_regions(current, MAX_CHOICES)
+            .into_iter()
+            .map(|region| choice(region.name.clone(), format!("{prefix}{}", region.region_id)))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn property_choices(filter: &str, properties: &str) -> Vec<CommandOptionChoice> {
+    let (done, current) = match properties.rsplit_once(',') {
+        Some((done, current)) => (format!("{done},"), current.trim()),
+        None => (String::new(), properties.trim()),
+    };
+    let used = done.split(',').collect::<Vec<&str>>();
+
+    PROPERTIES
+        .iter()
+        .filter(|p| !used.contains(p) && p.starts_with(current))
+        .map(|p| {
+            let value = format!("{filter}:{done}{p}");
+            choice(value.clone(), value)
+        })
+        .collect()
+}
+
+// Values come back exactly as offered and a cut one would be another filter,
+// so longer values aren't offered. Names are only shown and can be cut.
+fn fits(value: &str) -> bool {
+    value.chars().count() <= MAX_CHOICE_LENGTH
+}
+
+fn choice(name: String, value: String) -> CommandOptionChoice {
+    CommandOptionChoice {
+        name: name.chars().take(MAX_CHOICE_LENGTH).collect(),
+        name_localizations: None,
+        value: CommandOptionChoiceValue::String(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(choices: Vec<CommandOptionChoice>) -> Vec<String> {
+        choices
+            .into_iter()
+            .map(|c| match c.value {
+                CommandOptionChoiceValue::String(s) => s,
+                _ => panic!("expected string choice"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_kind_choices() {
+        assert_eq!(
+            values(filter_choices("c")),
+            vec!["character:", "corporation:"]
+        );
+        assert_eq!(values(filter_choices("")).len(), KINDS.len());
+        assert!(filter_choices("planet").is_empty());
+    }
+
+    #[test]
+    fn test_system_and_region_choices() {
+        let choices = filter_choices("system:jita");
+        assert_eq!(choices[0].name, "Jita (The Forge)");
+        assert_eq!(values(choices), vec!["system:30000142"]);
+
+        let choices = filter_choices("region:10000043,the for");
+        assert_eq!(choices[0].name, "The Forge");
+        assert_eq!(values(choices), vec!["region:10000043,10000002"]);
+
+        assert!(filter_choices("corp:goons").is_empty());
+    }
+
+    #[test]
+    fn test_id_and_property_choices() {
+        assert_eq!(
+            values(filter_choices("corp:98000001")),
+            vec![
+                "corp:98000001",
+                "corp:98000001:loss",
+                "corp:98000001:kill",
+                "corp:98000001:exclude",
+                "corp:98000001:with_npc",
+            ]
+        );
+        assert_eq!(
+            values(filter_choices("corp:98000001:loss,e")),
+            vec!["corp:98000001:loss,exclude"]
+        );
+        assert_eq!(
+            values(filter_choices("corp:98000001:loss,")),
+            vec![
+                "corp:98000001:loss,kill",
+                "corp:98000001:loss,exclude",
+                "corp:98000001:loss,with_npc",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_existing_filter_choices() {
+        let filters = vec![
+            "region:10000002".to_string(),
+            "corp:98000001:loss".to_string(),
+            "corp:98000002".to_string(),
+        ];
+
+        let choices = existing_filter_choices(&filters, "CORP");
+        assert_eq!(choices[0].name, "2. corp:98000001:loss");
+        assert_eq!(values(choices), vec!["corp:98000001:loss", "corp:98000002"]);
+
+        // Too long to send back whole, it's removed by its number instead
+        let long = format!("corp:{}", ["98000001"; 12].join(","));
+        let choices = existing_filter_choices(&[long], "corp");
+        assert!(choices.is_empty());
+    }
+}
@@ -1,10 +1,10 @@
 use twilight_model::{
-    application::command::{CommandOption, CommandType},
+    application::command::{CommandOption, CommandOptionChoice, CommandType},
     channel::ChannelType,
 };
 use twilight_util::builder::command::{ChannelBuilder, StringBuilder};
 
-use super::{CommandParams, CommandTrait};
+use super::{CommandParams, CommandTrait, Context, autocomplete};
 
 pub struct FilterAddCmd {}
 
@@ -14,6 +14,7 @@ impl FilterAddCmd {
     }
 }
 
+#[async_trait::async_trait]
 impl CommandTrait for FilterAddCmd {
     fn name(&self) -> String {
         "filter-add".to_string()
@@ -30,14 +31,16 @@ impl CommandTrait for FilterAddCmd {
     fn options(&self) -> Option<Vec<CommandOption>> {
         let filter = StringBuilder::new("filter", "Filter to add")
             .required(true)
+            .autocomplete(true)
             .build();
 
+        // Defaults to the channel the command is used in
         let channel = ChannelBuilder::new("channel", "Channel to add filter to")
             .channel_types(vec![ChannelType::GuildText])
-            .required(true)
+            .required(false)
             .build();
 
-        Some(vec![channel, filter])
+        Some(vec![filter, channel])
     }
 
     fn permissions(&self) -> Option<twilight_model::guild::Permissions> {
@@ -48,13 +51,16 @@ impl CommandTrait for FilterAddCmd {
         )
     }
 
-    fn callback(
+    async fn callback(
         &self,
-        store: &dyn crate::persistence::Store,
+        ctx: &Context,
         interaction: &CommandParams,
     ) -> Result<String, anyhow::Error> {
         let channel_id = match interaction.get_option_channel_id("channel") {
-            None => interaction.channel.id,
+            None => match &interaction.channel {
+                Some(channel) => channel.id,
+                None => return Ok("Missing required option channel".to_string()),
+            },
             Some(id) => id,
         };
         let filter = match interaction.get_option_string("filter") {
@@ -64,10 +70,23 @@ impl CommandTrait for FilterAddCmd {
 
         tracing::info!(channel_id, filter, "adding filter to channel");
 
-        store.add_filter_to_set(interaction.guild_id.get(), channel_id, &filter)?;
+        ctx.store
+            .add_filter_to_set(interaction.guild_id.get(), channel_id, &filter)
+            .await?;
 
         Ok(format!(
             "Filter `{filter}` added successfully to channel <#{channel_id}>"
         ))
     }
+
+    async fn autocomplete(
+        &self,
+        _ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Vec<CommandOptionChoice> {
+        match interaction.get_focused_option() {
+            Some((name, value)) if name == "filter" => autocomplete::filter_choices(&value),
+            _ => vec![],
+        }
+    }
 }
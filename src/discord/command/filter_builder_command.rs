@@ -0,0 +1,573 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use twilight_model::{
+    application::command::{CommandOption, CommandType},
+    channel::{
+        ChannelType,
+        message::{
+            Component, MessageFlags,
+            component::{
+                ActionRow, Button, ButtonStyle, Label, SelectMenu, SelectMenuOption,
+                SelectMenuType, TextInput, TextInputStyle,
+            },
+        },
+    },
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+};
+use twilight_util::builder::command::ChannelBuilder;
+
+use crate::{
+    filters::{Filter, FilterKind},
+    static_data,
+};
+
+use super::{CommandParams, CommandTrait, ComponentParams, Context, update};
+
+const NAME: &str = "filter-builder";
+
+// Drafts nobody touched for this long are dropped
+const DRAFT_TTL: Duration = Duration::from_secs(15 * 60);
+
+const KINDS: &[(&str, &str)] = &[
+    ("region", "Region"),
+    ("system", "System"),
+    ("ship", "Ship type"),
+    ("character", "Character"),
+    ("corporation", "Corporation"),
+    ("alliance", "Alliance"),
+];
+
+const PROPERTIES: &[(&str, &str)] = &[
+    ("loss", "Only losses"),
+    ("kill", "Only kills"),
+    ("exclude", "Exclude matching kills"),
+    ("with_npc", "Include NPC kills"),
+];
+
+// Draft is a filter being built for a channel, one per /filter-builder use
+#[derive(Clone, Debug)]
+struct Draft {
+    guild_id: u64,
+    channel_id: u64,
+    kind: String,
+    ids: Vec<u64>,
+    properties: Vec<String>,
+    updated: Instant,
+}
+
+impl Draft {
+    fn filter(&self) -> String {
+        let ids = self
+            .ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+
+        match self.properties.is_empty() {
+            true => format!("{}:{ids}", self.kind),
+            false => format!("{}:{ids}:{}", self.kind, self.properties.join(",")),
+        }
+    }
+}
+
+pub struct FilterBuilderCmd {
+    drafts: Arc<Mutex<HashMap<String, Draft>>>,
+    // seeded with the start time so sessions of a previous run never match
+    next_session: AtomicU64,
+}
+
+impl FilterBuilderCmd {
+    pub fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+
+        Self {
+            drafts: Arc::new(Mutex::new(HashMap::new())),
+            next_session: AtomicU64::new(seed),
+        }
+    }
+
+    fn start(&self, draft: Draft) -> String {
+        let session = format!("{:x}", self.next_session.fetch_add(1, Ordering::Relaxed));
+
+        if let Ok(mut drafts) = self.drafts.lock() {
+            drafts.retain(|_, d| d.updated.elapsed() < DRAFT_TTL);
+            drafts.insert(session.clone(), draft);
+        }
+
+        session
+    }
+
+    // Applies the change to the draft of the session, None when it expired
+    fn update(&self, session: &str, change: impl FnOnce(&mut Draft)) -> Option<Draft> {
+        let mut drafts = self.drafts.lock().ok()?;
+        let draft = drafts
+            .get_mut(session)
+            .filter(|d| d.updated.elapsed() < DRAFT_TTL)?;
+
+        change(draft);
+        draft.updated = Instant::now();
+
+        Some(draft.clone())
+    }
+
+    fn finish(&self, session: &str) -> Option<Draft> {
+        self.drafts.lock().ok()?.remove(session)
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandTrait for FilterBuilderCmd {
+    fn name(&self) -> String {
+        NAME.to_string()
+    }
+
+    fn description(&self) -> String {
+        "Build a filter step by step".to_string()
+    }
+
+    fn kind(&self) -> CommandType {
+        CommandType::ChatInput
+    }
+
+    fn options(&self) -> Option<Vec<CommandOption>> {
+        // Defaults to the channel the command is used in
+        let channel = ChannelBuilder::new("channel", "Channel to add the filter to")
+            .channel_types(vec![ChannelType::GuildText])
+            .required(false)
+            .build();
+
+        Some(vec![channel])
+    }
+
+    fn permissions(&self) -> Option<twilight_model::guild::Permissions> {
+        Some(
+            twilight_model::guild::Permissions::ADMINISTRATOR
+                | twilight_model::guild::Permissions::MANAGE_GUILD
+                | twilight_model::guild::Permissions::MANAGE_CHANNELS,
+        )
+    }
+
+    async fn callback(
+        &self,
+        _ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<String, anyhow::Error> {
+        let channel_id = channel_id(interaction)?;
+
+        Ok(format!(
+            "Building a filter for <#{channel_id}>, pick what it should match:"
+        ))
+    }
+
+    async fn response(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<InteractionResponse, anyhow::Error> {
+        let content = self.callback(ctx, interaction).await?;
+        let channel_id = channel_id(interaction)?;
+
+        let session = self.start(Draft {
+            guild_id: interaction.guild_id.get(),
+            channel_id,
+            kind: String::new(),
+            ids: vec![],
+            properties: vec![],
+            updated: Instant::now(),
+        });
+
+        tracing::info!(channel_id, session, "starting filter builder");
+
+        Ok(InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(InteractionResponseData {
+                content: Some(content),
+                components: Some(kind_components(&session)),
+                flags: Some(MessageFlags::EPHEMERAL),
+                ..Default::default()
+            }),
+        })
+    }
+
+    async fn component(
+        &self,
+        ctx: &Context,
+        interaction: &ComponentParams,
+    ) -> Result<InteractionResponse, anyhow::Error> {
+        let session = interaction.state.as_str();
+
+        let draft = match interaction.action.as_str() {
+            "kind" => self.update(session, |d| {
+                d.kind = interaction.values.first().cloned().unwrap_or_default();
+                d.ids.clear();
+            }),
+            "enter" => {
+                return match self.update(session, |_| {}) {
+                    Some(draft) => Ok(ids_modal(session, &draft)),
+                    None => Ok(expired()),
+                };
+            }
+            "ids" => {
+                let input = interaction.get_field("ids").unwrap_or_default();
+                let Some(draft) = self.update(session, |_| {}) else {
+                    return Ok(expired());
+                };
+
+                match parse_ids(&draft.kind, &input) {
+                    Ok(ids) => self.update(session, |d| d.ids = ids),
+                    Err(e) => {
+                        return Ok(update(
+                            format!("{e}\n{}", ids_prompt(&draft)),
+                            ids_components(session),
+                        ));
+                    }
+                }
+            }
+            "properties" => self.update(session, |d| d.properties = interaction.values.clone()),
+            "confirm" => {
+                let Some(draft) = self.finish(session) else {
+                    return Ok(expired());
+                };
+                if draft.guild_id != interaction.guild_id.get() {
+                    return Err(anyhow::anyhow!("filter draft belongs to another guild"));
+                }
+
+                let filter = draft.filter();
+                Filter::parse(filter.clone())?;
+
+                tracing::info!(channel_id = draft.channel_id, filter, "adding built filter");
+
+                ctx.store
+                    .add_filter_to_set(draft.guild_id, draft.channel_id, &filter)
+                    .await?;
+
+                return Ok(update(
+                    format!(
+                        "Filter `{filter}` added successfully to channel <#{}>",
+                        draft.channel_id
+                    ),
+                    vec![],
+                ));
+            }
+            "cancel" => {
+                self.finish(session);
+                return Ok(update("Filter builder cancelled".to_string(), vec![]));
+            }
+            action => {
+                return Err(anyhow::anyhow!("unknown filter builder action: {action}"));
+            }
+        };
+
+        let Some(draft) = draft else {
+            return Ok(expired());
+        };
+
+        match draft.ids.is_empty() {
+            true => Ok(update(ids_prompt(&draft), ids_components(session))),
+            false => Ok(update(
+                summary(&draft),
+                properties_components(session, &draft.properties),
+            )),
+        }
+    }
+}
+
+fn channel_id(interaction: &CommandParams) -> Result<u64, anyhow::Error> {
+    match interaction.get_option_channel_id("channel") {
+        Some(id) => Ok(id),
+        None => match &interaction.channel {
+            Some(channel) => Ok(channel.id),
+            None => Err(anyhow::anyhow!("Missing required option channel")),
+        },
+    }
+}
+
+// Ids entered in the modal, separated by commas or new lines. Systems and
+// regions can also be entered by name.
+fn parse_ids(kind: &str, input: &str) -> Result<Vec<u64>, anyhow::Error> {
+    let mut ids = vec![];
+
+    for part in input
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+    {
+        if let Ok(id) = part.parse::<u64>() {
+            ids.push(id);
+            continue;
+        }
+
+        let id = match kind {
+            "system" => static_data::search_systems(part, 25)
+                .into_iter()
+                .find(|s| s.name.eq_ignore_ascii_case(part))
+                .map(|s| s.system_id),
+            "region" => static_data::search_regions(part, 25)
+                .into_iter()
+                .find(|r| r.name.eq_ignore_ascii_case(part))
+                .map(|r| r.region_id),
+            _ => None,
+        };
+
+        match id {
+            Some(id) => ids.push(id),
+            None => return Err(anyhow::anyhow!("`{part}` is not a known {kind} or id")),
+        }
+    }
+
+    if ids.is_empty() {
+        return Err(anyhow::anyhow!("Enter at least one id"));
+    }
+
+    Ok(ids)
+}
+
+fn ids_prompt(draft: &Draft) -> String {
+    let hint = match draft.kind.as_str() {
+        "system" | "region" => "names or ids",
+        _ => "ids",
+    };
+
+    format!(
+        "Building a `{}` filter for <#{}>, enter the {hint} to match:",
+        draft.kind, draft.channel_id
+    )
+}
+
+fn summary(draft: &Draft) -> String {
+    let filter = draft.filter();
+    let description = match Filter::parse(filter.clone()) {
+        Ok(parsed) => parsed.describe(|kind, id| {
+            match kind {
+                FilterKind::System => static_data::get_system(id).map(|s| s.name.clone()),
+                FilterKind::Region => static_data::get_region(id).map(|r| r.name.clone()),
+                _ => None,
+            }
+            .unwrap_or_else(|| id.to_string())
+        }),
+        Err(e) => format!("Invalid filter: {e}"),
+    };
+
+    format!(
+        "Filter for <#{}>: {description}\n-# `{filter}`\nPick any options, then confirm to add it.",
+        draft.channel_id
+    )
+}
+
+fn expired() -> InteractionResponse {
+    update(
+        "This filter builder expired, run /filter-builder again".to_string(),
+        vec![],
+    )
+}
+
+fn custom_id(action: &str, session: &str) -> String {
+    format!("{NAME}:{action}:{session}")
+}
+
+fn row(components: Vec<Component>) -> Component {
+    Component::ActionRow(ActionRow {
+        id: None,
+        components,
+    })
+}
+
+fn button(action: &str, session: &str, label: &str, style: ButtonStyle) -> Component {
+    Component::Button(Button {
+        id: None,
+        custom_id: Some(custom_id(action, session)),
+        disabled: false,
+        emoji: None,
+        label: Some(label.to_string()),
+        style,
+        url: None,
+        sku_id: None,
+    })
+}
+
+fn select(
+    action: &str,
+    session: &str,
+    placeholder: &str,
+    options: &[(&str, &str)],
+    selected: &[String],
+    max_values: u8,
+) -> Component {
+    Component::SelectMenu(SelectMenu {
+        id: None,
+        channel_types: None,
+        custom_id: custom_id(action, session),
+        default_values: None,
+        disabled: false,
+        kind: SelectMenuType::Text,
+        max_values: Some(max_values),
+        min_values: Some(if max_values > 1 { 0 } else { 1 }),
+        options: Some(
+            options
+                .iter()
+                .map(|(value, label)| SelectMenuOption {
+                    default: selected.iter().any(|s| s == value),
+                    description: None,
+                    emoji: None,
+                    label: label.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+        ),
+        placeholder: Some(placeholder.to_string()),
+        required: None,
+    })
+}
+
+fn kind_components(session: &str) -> Vec<Component> {
+    vec![
+        row(vec![select(
+            "kind",
+            session,
+            "What should the filter match?",
+            KINDS,
+            &[],
+            1,
+        )]),
+        row(vec![button(
+            "cancel",
+            session,
+            "Cancel",
+            ButtonStyle::Secondary,
+        )]),
+    ]
+}
+
+fn ids_components(session: &str) -> Vec<Component> {
+    vec![row(vec![
+        button("enter", session, "Enter ids", ButtonStyle::Primary),
+        button("cancel", session, "Cancel", ButtonStyle::Secondary),
+    ])]
+}
+
+fn properties_components(session: &str, selected: &[String]) -> Vec<Component> {
+    vec![
+        row(vec![select(
+            "properties",
+            session,
+            "Options",
+            PROPERTIES,
+            selected,
+            PROPERTIES.len() as u8,
+        )]),
+        row(vec![
+            button("confirm", session, "Add filter", ButtonStyle::Success),
+            button("enter", session, "Change ids", ButtonStyle::Primary),
+            button("cancel", session, "Cancel", ButtonStyle::Secondary),
+        ]),
+    ]
+}
+
+#[allow(deprecated)]
+fn ids_modal(session: &str, draft: &Draft) -> InteractionResponse {
+    let value = draft
+        .ids
+        .iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    let input = Component::TextInput(TextInput {
+        id: None,
+        custom_id: "ids".to_string(),
+        label: None,
+        max_length: Some(4000),
+        min_length: Some(1),
+        placeholder: Some("Comma separated, e.g. 30000142, 30002187".to_string()),
+        required: Some(true),
+        style: TextInputStyle::Paragraph,
+        value: (!value.is_empty()).then_some(value),
+    });
+
+    InteractionResponse {
+        kind: InteractionResponseType::Modal,
+        data: Some(InteractionResponseData {
+            custom_id: Some(custom_id("ids", session)),
+            title: Some("Filter ids".to_string()),
+            components: Some(vec![Component::Label(Label {
+                id: None,
+                label: format!("{} ids", draft.kind),
+                description: None,
+                component: Box::new(input),
+            })]),
+            ..Default::default()
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draft(kind: &str, ids: Vec<u64>, properties: Vec<&str>) -> Draft {
+        Draft {
+            guild_id: 1,
+            channel_id: 2,
+            kind: kind.to_string(),
+            ids,
+            properties: properties.into_iter().map(String::from).collect(),
+            updated: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_draft_filter() {
+        assert_eq!(
+            draft("system", vec![30000142], vec![]).filter(),
+            "system:30000142"
+        );
+        assert_eq!(
+            draft(
+                "corporation",
+                vec![98000001, 98000002],
+                vec!["loss", "exclude"]
+            )
+            .filter(),
+            "corporation:98000001,98000002:loss,exclude"
+        );
+    }
+
+    #[test]
+    fn test_parse_ids() {
+        assert_eq!(
+            parse_ids("system", "jita, 30002187\n").unwrap(),
+            vec![30000142, 30002187]
+        );
+        assert_eq!(parse_ids("region", "The Forge").unwrap(), vec![10000002]);
+        assert!(parse_ids("system", "jit").is_err());
+        assert!(parse_ids("corporation", "Krusty Industries").is_err());
+        assert!(parse_ids("alliance", " , ").is_err());
+    }
+
+    #[test]
+    fn test_drafts_expire() {
+        let cmd = FilterBuilderCmd::new();
+        let session = cmd.start(draft("ship", vec![], vec![]));
+
+        assert!(cmd.update(&session, |d| d.ids = vec![670]).is_some());
+        assert_eq!(cmd.finish(&session).unwrap().filter(), "ship:670");
+        assert!(cmd.update(&session, |_| {}).is_none());
+
+        let stale = cmd.start(draft("ship", vec![], vec![]));
+        if let Ok(mut drafts) = cmd.drafts.lock() {
+            drafts.get_mut(&stale).unwrap().updated -= DRAFT_TTL;
+        }
+        assert!(cmd.update(&stale, |_| {}).is_none());
+    }
+}
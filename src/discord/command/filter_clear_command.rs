@@ -1,10 +1,21 @@
 use twilight_model::{
     application::command::{CommandOption, CommandType},
-    channel::ChannelType,
+    channel::{
+        ChannelType,
+        message::{
+            Component, MessageFlags,
+            component::{ActionRow, Button, ButtonStyle},
+        },
+    },
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
 };
 use twilight_util::builder::command::ChannelBuilder;
 
-use super::{CommandParams, CommandTrait};
+use crate::filters::{FilterSet, FilterSnapshot};
+
+use super::{CommandParams, CommandTrait, ComponentParams, Context, update};
+
+const NAME: &str = "filter-clear";
 
 pub struct FilterClearCmd {}
 
@@ -12,11 +23,45 @@ impl FilterClearCmd {
     pub fn new() -> Self {
         Self {}
     }
+
+    // The question asked before clearing, with the channel to clear when
+    // there's anything to remove
+    async fn prompt(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<(String, Option<u64>), anyhow::Error> {
+        let channel_id = match interaction.get_option_channel_id("channel") {
+            None => return Ok(("Missing required option channel".to_string(), None)),
+            Some(id) => id,
+        };
+
+        let removable = match ctx.store.get_channel_filter_set(channel_id).await {
+            Ok(filter_set) if filter_set.guild_id == interaction.guild_id.get() => filter_set
+                .filters
+                .len()
+                .saturating_sub(filter_set.config_filters.len()),
+            _ => 0,
+        };
+
+        if removable == 0 {
+            return Ok((format!("No filters to remove from <#{channel_id}>"), None));
+        }
+
+        Ok((
+            format!(
+                "Remove the {removable} filters of <#{channel_id}>? They can be restored with /filter-undo for {} hours",
+                FilterSnapshot::RETENTION.num_hours()
+            ),
+            Some(channel_id),
+        ))
+    }
 }
 
+#[async_trait::async_trait]
 impl CommandTrait for FilterClearCmd {
     fn name(&self) -> String {
-        "filter-clear".to_string()
+        NAME.to_string()
     }
 
     fn description(&self) -> String {
@@ -44,22 +89,126 @@ impl CommandTrait for FilterClearCmd {
         )
     }
 
-    fn callback(
+    async fn callback(
         &self,
-        store: &dyn crate::persistence::Store,
+        ctx: &Context,
         interaction: &CommandParams,
     ) -> Result<String, anyhow::Error> {
-        let channel_id = match interaction.get_option_channel_id("channel") {
-            None => return Ok("Missing required option channel".to_string()),
-            Some(id) => id,
-        };
+        Ok(self.prompt(ctx, interaction).await?.0)
+    }
 
-        tracing::info!(channel_id, "removing all filters from channel");
+    // Nothing is removed until the user confirms
+    async fn response(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<InteractionResponse, anyhow::Error> {
+        let (content, channel_id) = self.prompt(ctx, interaction).await?;
 
-        store.clear_filter_set(channel_id)?;
+        Ok(InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(InteractionResponseData {
+                content: Some(content),
+                components: channel_id.map(confirm_components),
+                flags: Some(MessageFlags::EPHEMERAL),
+                ..Default::default()
+            }),
+        })
+    }
 
-        Ok(format!(
-            "All filters in <#{channel_id}> removed successfully"
-        ))
+    async fn component(
+        &self,
+        ctx: &Context,
+        interaction: &ComponentParams,
+    ) -> Result<InteractionResponse, anyhow::Error> {
+        match interaction.action.as_str() {
+            "confirm" => {
+                let channel_id = interaction.state.parse::<u64>()?;
+                let content = clear(ctx, interaction.guild_id.get(), channel_id).await?;
+                Ok(update(content, vec![]))
+            }
+            "cancel" => Ok(update("Nothing was removed".to_string(), vec![])),
+            action => Err(anyhow::anyhow!("unknown filter clear action: {action}")),
+        }
+    }
+}
+
+// Removes the channel's filters, keeping a snapshot for /filter-undo
+pub(super) async fn clear(
+    ctx: &Context,
+    guild_id: u64,
+    channel_id: u64,
+) -> Result<String, anyhow::Error> {
+    tracing::info!(channel_id, "removing all filters from channel");
+
+    // Cleared under the store's lock, filters from the bot config stay
+    let updates = ctx
+        .store
+        .update_filter_sets(
+            &[channel_id],
+            Box::new(move |current| {
+                let Some(filter_set) = current[0].take_if(|set| set.guild_id == guild_id) else {
+                    return Ok(());
+                };
+                if !filter_set.config_filters.is_empty() {
+                    current[0] = Some(FilterSet {
+                        filters: filter_set.config_filters.clone(),
+                        ..filter_set
+                    });
+                }
+                Ok(())
+            }),
+        )
+        .await?;
+
+    // The snapshot has exactly the filters the clear removed
+    let Some((Some(before), _)) = updates.into_iter().next() else {
+        return Ok(format!("No filters to remove from <#{channel_id}>"));
+    };
+    if before.guild_id != guild_id {
+        return Ok(format!("No filters to remove from <#{channel_id}>"));
+    }
+
+    let kept = before.config_filters.len();
+    ctx.store
+        .set_filter_snapshot(FilterSnapshot::new(before))
+        .await?;
+
+    let undo = format!(
+        "use /filter-undo within {} hours to restore them",
+        FilterSnapshot::RETENTION.num_hours()
+    );
+
+    if kept == 0 {
+        return Ok(format!(
+            "All filters in <#{channel_id}> removed successfully, {undo}"
+        ));
     }
+
+    Ok(format!(
+        "All filters in <#{channel_id}> removed successfully, {kept} filters from the bot config were kept, {undo}"
+    ))
+}
+
+fn confirm_components(channel_id: u64) -> Vec<Component> {
+    let button = |action: &str, label: &str, style| {
+        Component::Button(Button {
+            id: None,
+            custom_id: Some(format!("{NAME}:{action}:{channel_id}")),
+            disabled: false,
+            emoji: None,
+            label: Some(label.to_string()),
+            style,
+            url: None,
+            sku_id: None,
+        })
+    };
+
+    vec![Component::ActionRow(ActionRow {
+        id: None,
+        components: vec![
+            button("confirm", "Remove filters", ButtonStyle::Danger),
+            button("cancel", "Cancel", ButtonStyle::Secondary),
+        ],
+    })]
 }
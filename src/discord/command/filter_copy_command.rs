@@ -0,0 +1,105 @@
+use twilight_model::{
+    application::command::{CommandOption, CommandType},
+    channel::ChannelType,
+};
+use twilight_util::builder::command::{BooleanBuilder, ChannelBuilder};
+
+use crate::persistence;
+
+use super::{CommandParams, CommandTrait, Context};
+
+pub struct FilterCopyCmd {}
+
+impl FilterCopyCmd {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandTrait for FilterCopyCmd {
+    fn name(&self) -> String {
+        "filter-copy".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Copy the filters of a channel to another channel".to_string()
+    }
+
+    fn kind(&self) -> CommandType {
+        CommandType::ChatInput
+    }
+
+    fn options(&self) -> Option<Vec<CommandOption>> {
+        let from = ChannelBuilder::new("from", "Channel to copy filters from")
+            .channel_types(vec![ChannelType::GuildText])
+            .required(true)
+            .build();
+
+        // Defaults to the channel the command is used in
+        let to = ChannelBuilder::new("to", "Channel to copy filters to")
+            .channel_types(vec![ChannelType::GuildText])
+            .required(false)
+            .build();
+
+        let replace = BooleanBuilder::new(
+            "replace",
+            "Replace the filters of the channel instead of adding to them",
+        )
+        .required(false)
+        .build();
+
+        Some(vec![from, to, replace])
+    }
+
+    fn permissions(&self) -> Option<twilight_model::guild::Permissions> {
+        Some(
+            twilight_model::guild::Permissions::ADMINISTRATOR
+                | twilight_model::guild::Permissions::MANAGE_GUILD
+                | twilight_model::guild::Permissions::MANAGE_CHANNELS,
+        )
+    }
+
+    async fn callback(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<String, anyhow::Error> {
+        let from = match interaction.get_option_channel_id("from") {
+            None => return Ok("Missing required option from".to_string()),
+            Some(id) => id,
+        };
+        let to = match interaction.get_option_channel_id("to") {
+            None => match &interaction.channel {
+                Some(channel) => channel.id,
+                None => return Ok("Missing required option to".to_string()),
+            },
+            Some(id) => id,
+        };
+        let replace = interaction.get_option_boolean("replace").unwrap_or(false);
+
+        if from == to {
+            return Ok("Pick two different channels to copy filters between".to_string());
+        }
+
+        let guild_id = interaction.guild_id.get();
+        let source = match ctx.store.get_channel_filter_set(from).await {
+            Ok(source) if source.guild_id == guild_id && !source.filters.is_empty() => source,
+            _ => return Ok(format!("No filters configured for <#{from}>")),
+        };
+
+        tracing::info!(from, to, replace, "copying filters between channels");
+
+        let added =
+            persistence::apply_filters(ctx.store.as_ref(), guild_id, to, &source.filters, replace)
+                .await?;
+
+        Ok(match (replace, added) {
+            (true, _) => {
+                format!("Filters of <#{to}> replaced with the {added} filters of <#{from}>")
+            }
+            (false, 0) => format!("<#{to}> already has every filter of <#{from}>"),
+            (false, _) => format!("Copied {added} filters from <#{from}> to <#{to}>"),
+        })
+    }
+}
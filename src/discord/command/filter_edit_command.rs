@@ -0,0 +1,100 @@
+use twilight_model::{
+    application::command::{CommandOption, CommandType},
+    channel::ChannelType,
+};
+use twilight_util::builder::command::{ChannelBuilder, IntegerBuilder, StringBuilder};
+
+use crate::filters::Filter;
+
+use super::{CommandParams, CommandTrait, Context, position};
+
+pub struct FilterEditCmd {}
+
+impl FilterEditCmd {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandTrait for FilterEditCmd {
+    fn name(&self) -> String {
+        "filter-edit".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Replace a filter of the channel".to_string()
+    }
+
+    fn kind(&self) -> CommandType {
+        CommandType::ChatInput
+    }
+
+    fn options(&self) -> Option<Vec<CommandOption>> {
+        let channel = ChannelBuilder::new("channel", "Channel to edit the filter of")
+            .channel_types(vec![ChannelType::GuildText])
+            .required(true)
+            .build();
+
+        let index = IntegerBuilder::new("index", "Number of the filter in /filter-list")
+            .required(true)
+            .min_value(1)
+            .build();
+
+        let filter = StringBuilder::new("filter", "Filter to use instead")
+            .required(true)
+            .build();
+
+        Some(vec![channel, index, filter])
+    }
+
+    fn permissions(&self) -> Option<twilight_model::guild::Permissions> {
+        Some(
+            twilight_model::guild::Permissions::ADMINISTRATOR
+                | twilight_model::guild::Permissions::MANAGE_GUILD
+                | twilight_model::guild::Permissions::MANAGE_CHANNELS,
+        )
+    }
+
+    async fn callback(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<String, anyhow::Error> {
+        let channel_id = match interaction.get_option_channel_id("channel") {
+            None => return Ok("Missing required option channel".to_string()),
+            Some(id) => id,
+        };
+        let index = match interaction.get_option_integer("index") {
+            None => return Ok("Missing required option index".to_string()),
+            Some(i) => i,
+        };
+        let filter = match interaction.get_option_string("filter") {
+            None => return Ok("Missing required option filter".to_string()),
+            Some(f) => f,
+        };
+
+        if let Err(e) = Filter::parse(filter.clone()) {
+            return Ok(format!("Invalid filter `{filter}`: {e}"));
+        }
+
+        let Some(position) = position(index) else {
+            return Ok(format!("Invalid filter number {index}, filters start at 1"));
+        };
+
+        tracing::info!(channel_id, index, filter, "replacing filter of channel");
+
+        let replaced = match ctx
+            .store
+            .replace_filter_at(channel_id, position, &filter)
+            .await
+        {
+            Ok(f) => f,
+            Err(e) => return Ok(format!("Failed to edit filter {index}: {e}")),
+        };
+
+        Ok(format!(
+            "Filter {index} of channel <#{channel_id}> changed from `{replaced}` to `{filter}`"
+        ))
+    }
+}
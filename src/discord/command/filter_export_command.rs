@@ -0,0 +1,122 @@
+use twilight_model::{
+    application::command::{CommandOption, CommandType},
+    channel::message::MessageFlags,
+    http::{
+        attachment::Attachment,
+        interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+    },
+};
+use twilight_util::builder::command::StringBuilder;
+
+use crate::filters::export::{Document, Format};
+
+use super::{CommandParams, CommandTrait, Context};
+
+pub struct FilterExportCmd {}
+
+impl FilterExportCmd {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    // The exported file with the message sent along with it
+    async fn export(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<(String, Option<Attachment>), anyhow::Error> {
+        let guild_id = interaction.guild_id.get();
+        let format = match interaction.get_option_string("format") {
+            Some(format) => Format::parse(&format)?,
+            None => Format::Yaml,
+        };
+
+        let filter_sets = ctx.store.list_guild_filter_sets(guild_id).await?;
+        if filter_sets.is_empty() {
+            return Ok(("No filters configured in this server".to_string(), None));
+        }
+
+        tracing::info!(
+            guild_id,
+            channels = filter_sets.len(),
+            "exporting filters for guild"
+        );
+
+        let has_webhooks = filter_sets.iter().any(|set| set.discord_webhook.is_some());
+        let document = Document::new(filter_sets);
+        let mut content = format!(
+            "Filters of {} channels, restore them with /filter-import",
+            document.filter_sets.len()
+        );
+        if has_webhooks {
+            content.push_str(
+                "\n-# Webhooks aren't in the file, channels keep theirs when it's imported",
+            );
+        }
+
+        let document = document.to_string(format)?;
+        let filename = format!("krusty-filters-{guild_id}.{}", format.extension());
+
+        Ok((
+            content,
+            Some(Attachment::from_bytes(filename, document.into_bytes(), 0)),
+        ))
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandTrait for FilterExportCmd {
+    fn name(&self) -> String {
+        "filter-export".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Download the filters of every channel in this server".to_string()
+    }
+
+    fn kind(&self) -> CommandType {
+        CommandType::ChatInput
+    }
+
+    fn options(&self) -> Option<Vec<CommandOption>> {
+        let format = StringBuilder::new("format", "File format, YAML by default")
+            .choices(vec![("YAML", "yaml"), ("JSON", "json")])
+            .required(false)
+            .build();
+
+        Some(vec![format])
+    }
+
+    fn permissions(&self) -> Option<twilight_model::guild::Permissions> {
+        Some(
+            twilight_model::guild::Permissions::ADMINISTRATOR
+                | twilight_model::guild::Permissions::MANAGE_GUILD,
+        )
+    }
+
+    async fn callback(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<String, anyhow::Error> {
+        Ok(self.export(ctx, interaction).await?.0)
+    }
+
+    async fn response(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<InteractionResponse, anyhow::Error> {
+        let (content, attachment) = self.export(ctx, interaction).await?;
+
+        Ok(InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(InteractionResponseData {
+                content: Some(content),
+                attachments: attachment.map(|attachment| vec![attachment]),
+                flags: Some(MessageFlags::EPHEMERAL),
+                ..Default::default()
+            }),
+        })
+    }
+}
@@ -0,0 +1,100 @@
+use twilight_model::{
+    application::command::{CommandOption, CommandType},
+    channel::ChannelType,
+};
+use twilight_util::builder::command::ChannelBuilder;
+
+use crate::persistence::audit::Change;
+
+use super::{CommandParams, CommandTrait, Context, truncate_list};
+
+// Changes shown, the store keeps more than fit in a message anyway
+const MAX_SHOWN: usize = 10;
+
+pub struct FilterHistoryCmd {}
+
+impl FilterHistoryCmd {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandTrait for FilterHistoryCmd {
+    fn name(&self) -> String {
+        "filter-history".to_string()
+    }
+
+    fn description(&self) -> String {
+        "See who changed the filters of a channel and when".to_string()
+    }
+
+    fn kind(&self) -> CommandType {
+        CommandType::ChatInput
+    }
+
+    fn options(&self) -> Option<Vec<CommandOption>> {
+        let channel = ChannelBuilder::new("channel", "Channel to see the changes for")
+            .channel_types(vec![ChannelType::GuildText])
+            .required(true)
+            .build();
+
+        Some(vec![channel])
+    }
+
+    fn permissions(&self) -> Option<twilight_model::guild::Permissions> {
+        None
+    }
+
+    async fn callback(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<String, anyhow::Error> {
+        let channel_id = match interaction.get_option_channel_id("channel") {
+            Some(id) => id,
+            None => return Ok("Missing required option channel".to_string()),
+        };
+        let guild_id = interaction.guild_id.get();
+
+        tracing::info!(guild_id, channel_id, "listing filter changes for channel");
+
+        // Channels can't move between guilds, but don't trust the store on it
+        let changes = ctx
+            .store
+            .list_changes(channel_id, MAX_SHOWN)
+            .await?
+            .into_iter()
+            .filter(|change| change.guild_id == guild_id)
+            .collect::<Vec<Change>>();
+
+        if changes.is_empty() {
+            return Ok(format!("No filter changes recorded for <#{channel_id}>"));
+        }
+
+        Ok(render(channel_id, &changes))
+    }
+}
+
+// Newest change first, the oldest are left out when they don't fit
+fn render(channel_id: u64, changes: &[Change]) -> String {
+    let entries = changes
+        .iter()
+        .map(|change| {
+            format!(
+                "<t:{}:f> <@{}> used /{}\n{}\n",
+                change.at.timestamp(),
+                change.user_id,
+                change.command,
+                change.describe()
+            )
+        })
+        .collect::<Vec<String>>();
+
+    truncate_list(
+        format!("Latest filter changes of <#{channel_id}>:\n"),
+        &entries,
+        "",
+        |count| format!("-# …and {count} older changes\n"),
+    )
+}
@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+
+use twilight_model::{
+    application::command::{CommandOption, CommandType},
+    id::Id,
+};
+use twilight_util::builder::command::AttachmentBuilder;
+
+use crate::filters::export::{Document, Format};
+
+use super::{CommandParams, CommandTrait, Context, truncate};
+
+// Exports are a few KiB, anything much larger isn't one
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+pub struct FilterImportCmd {}
+
+impl FilterImportCmd {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandTrait for FilterImportCmd {
+    fn name(&self) -> String {
+        "filter-import".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Restore filters from a file made with /filter-export".to_string()
+    }
+
+    fn kind(&self) -> CommandType {
+        CommandType::ChatInput
+    }
+
+    fn options(&self) -> Option<Vec<CommandOption>> {
+        let file = AttachmentBuilder::new("file", "YAML or JSON file made with /filter-export")
+            .required(true)
+            .build();
+
+        Some(vec![file])
+    }
+
+    fn permissions(&self) -> Option<twilight_model::guild::Permissions> {
+        Some(
+            twilight_model::guild::Permissions::ADMINISTRATOR
+                | twilight_model::guild::Permissions::MANAGE_GUILD,
+        )
+    }
+
+    async fn callback(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<String, anyhow::Error> {
+        let attachment = match interaction.get_option_attachment("file") {
+            None => return Ok("Missing required option file".to_string()),
+            Some(attachment) => attachment,
+        };
+
+        if attachment.size > MAX_FILE_SIZE {
+            return Ok(format!(
+                "`{}` is too large to be an export",
+                attachment.filename
+            ));
+        }
+
+        let guild_id = interaction.guild_id.get();
+
+        tracing::info!(
+            guild_id,
+            filename = attachment.filename,
+            "importing filters for guild"
+        );
+
+        let data = ctx
+            .http
+            .get(&attachment.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        // Only channels of this guild can be imported to
+        let channel_ids = ctx
+            .discord
+            .guild_channels(Id::new(guild_id))
+            .await?
+            .models()
+            .await?
+            .iter()
+            .map(|channel| channel.id.get())
+            .collect::<HashSet<u64>>();
+
+        import(
+            ctx,
+            guild_id,
+            &data,
+            Format::from_filename(&attachment.filename),
+            &channel_ids,
+        )
+        .await
+    }
+}
+
+// Validates the whole file before storing anything, so a file with mistakes
+// leaves the filters as they were
+pub(super) async fn import(
+    ctx: &Context,
+    guild_id: u64,
+    data: &[u8],
+    format: Format,
+    channel_ids: &HashSet<u64>,
+) -> Result<String, anyhow::Error> {
+    let document = match Document::parse(data, format) {
+        Ok(document) => document,
+        Err(e) => return Ok(format!("Nothing was imported, {e}")),
+    };
+
+    if document.filter_sets.is_empty() {
+        return Ok("Nothing was imported, the file has no filter sets".to_string());
+    }
+
+    let has_webhooks = document.has_webhooks();
+    let filter_sets = match document.validate(guild_id, channel_ids) {
+        Ok(filter_sets) => filter_sets,
+        Err(e) => {
+            return Ok(truncate(format!(
+                "Nothing was imported, fix these problems first:\n{e}"
+            )));
+        }
+    };
+
+    let imported = filter_sets
+        .iter()
+        .map(|filter_set| filter_set.channel_id)
+        .collect::<Vec<u64>>();
+    let channels = imported
+        .iter()
+        .map(|channel_id| format!("<#{channel_id}>"))
+        .collect::<Vec<String>>();
+
+    // Filters from the bot config and the channel's webhooks stay, merged
+    // under the store's lock so changes made meanwhile aren't lost
+    ctx.store
+        .update_filter_sets(
+            &imported,
+            Box::new(move |current| {
+                for (current, mut filter_set) in current.iter_mut().zip(filter_sets) {
+                    if let Some(current) = current.as_mut() {
+                        filter_set.keep_config_filters(current);
+                        filter_set.webhook = current.webhook.take();
+                        filter_set.discord_webhook = current.discord_webhook.take();
+                    }
+                    *current = Some(filter_set);
+                }
+                Ok(())
+            }),
+        )
+        .await?;
+
+    let mut output = format!(
+        "Imported the filters of {} channels: {}",
+        channels.len(),
+        channels.join(", ")
+    );
+    if has_webhooks {
+        output.push_str("\n-# Webhooks in the file were ignored, set them with /filter-webhook");
+    }
+
+    Ok(truncate(output))
+}
@@ -4,7 +4,12 @@ use twilight_model::{
 };
 use twilight_util::builder::command::ChannelBuilder;
 
-use super::{CommandParams, CommandTrait};
+use crate::{
+    filters::{Filter, FilterKind},
+    static_data,
+};
+
+use super::{CommandParams, CommandTrait, Context, truncate_list};
 
 pub struct FilterListCmd {}
 
@@ -14,6 +19,7 @@ impl FilterListCmd {
     }
 }
 
+#[async_trait::async_trait]
 impl CommandTrait for FilterListCmd {
     fn name(&self) -> String {
         "filter-list".to_string()
@@ -40,9 +46,9 @@ impl CommandTrait for FilterListCmd {
         None
     }
 
-    fn callback(
+    async fn callback(
         &self,
-        store: &dyn crate::persistence::Store,
+        ctx: &Context,
         interaction: &CommandParams,
     ) -> Result<String, anyhow::Error> {
         let channel_id = interaction.get_option_channel_id("channel");
@@ -58,18 +64,81 @@ impl CommandTrait for FilterListCmd {
 
         tracing::info!(channel_id, "listing filters for channel");
 
-        let filters = store.get_channel_filter_set(channel_id)?;
+        let filters = ctx.store.get_channel_filter_set(channel_id).await?;
 
         if filters.filters.is_empty() {
             return Ok(format!("No filters configured for <#{channel_id}>"));
         }
 
-        let mut output = format!("Filters for <#{channel_id}>:\n");
+        let descriptions = describe_filters(ctx, &filters.filters).await;
 
-        filters.filters.iter().for_each(|filter| {
-            output.push_str(&format!("- `{}`\n", filter));
-        });
+        let mut header = format!("Filters for <#{channel_id}>:\n");
+
+        if let Some(disabled) = &filters.disabled {
+            header.push_str(&format!(
+                "Deliveries were paused on {}: {}\n-# Changing the filters resumes them\n",
+                disabled.at.format("%Y-%m-%d %H:%M UTC"),
+                disabled.reason
+            ));
+        }
 
-        Ok(output)
+        let entries = filters
+            .filters
+            .iter()
+            .zip(descriptions)
+            .enumerate()
+            .map(|(index, (raw, description))| {
+                let source = match filters.config_filters.contains(raw) {
+                    true => " · from the bot config",
+                    false => "",
+                };
+                format!("**{}.** {description}\n-# `{raw}`{source}\n", index + 1)
+            })
+            .collect::<Vec<String>>();
+
+        Ok(truncate_list(
+            header,
+            &entries,
+            "-# Use the numbers with /filter-remove, /filter-edit and /filter-move",
+            |count| format!("-# …and {count} more filters\n"),
+        ))
     }
 }
+
+// Describes each filter with the names of the ids it matches, invalid filters
+// are described by their error
+pub(super) async fn describe_filters(ctx: &Context, filters: &[String]) -> Vec<String> {
+    let parsed = filters
+        .iter()
+        .map(|filter| Filter::parse(filter.clone()))
+        .collect::<Vec<_>>();
+
+    // Systems are named from static data, everything else through ESI
+    let ids = parsed
+        .iter()
+        .flatten()
+        .filter(|filter| *filter.kind() != FilterKind::System)
+        .flat_map(|filter| filter.ids().to_vec())
+        .collect::<Vec<u64>>();
+
+    let names = ctx.names.resolve(&ids).await;
+
+    let name = |kind: &FilterKind, id: u64| {
+        match kind {
+            FilterKind::System => static_data::get_system(id).map(|s| s.name.clone()),
+            FilterKind::Region => static_data::get_region(id)
+                .map(|r| r.name.clone())
+                .or_else(|| names.get(&id).map(|n| n.name.clone())),
+            _ => names.get(&id).map(|n| n.name.clone()),
+        }
+        .unwrap_or_else(|| id.to_string())
+    };
+
+    parsed
+        .iter()
+        .map(|filter| match filter {
+            Ok(filter) => filter.describe(name),
+            Err(e) => format!("Invalid filter: {e}"),
+        })
+        .collect()
+}
@@ -0,0 +1,96 @@
+use twilight_model::{
+    application::command::{CommandOption, CommandType},
+    channel::ChannelType,
+};
+use twilight_util::builder::command::{ChannelBuilder, IntegerBuilder};
+
+use super::{CommandParams, CommandTrait, Context, position};
+
+pub struct FilterMoveCmd {}
+
+impl FilterMoveCmd {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandTrait for FilterMoveCmd {
+    fn name(&self) -> String {
+        "filter-move".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Move a filter of the channel to another position".to_string()
+    }
+
+    fn kind(&self) -> CommandType {
+        CommandType::ChatInput
+    }
+
+    fn options(&self) -> Option<Vec<CommandOption>> {
+        let channel = ChannelBuilder::new("channel", "Channel to reorder the filters of")
+            .channel_types(vec![ChannelType::GuildText])
+            .required(true)
+            .build();
+
+        let from = IntegerBuilder::new("from", "Number of the filter in /filter-list")
+            .required(true)
+            .min_value(1)
+            .build();
+
+        let to = IntegerBuilder::new("to", "Number the filter should have afterwards")
+            .required(true)
+            .min_value(1)
+            .build();
+
+        Some(vec![channel, from, to])
+    }
+
+    fn permissions(&self) -> Option<twilight_model::guild::Permissions> {
+        Some(
+            twilight_model::guild::Permissions::ADMINISTRATOR
+                | twilight_model::guild::Permissions::MANAGE_GUILD
+                | twilight_model::guild::Permissions::MANAGE_CHANNELS,
+        )
+    }
+
+    async fn callback(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<String, anyhow::Error> {
+        let channel_id = match interaction.get_option_channel_id("channel") {
+            None => return Ok("Missing required option channel".to_string()),
+            Some(id) => id,
+        };
+        let from = match interaction.get_option_integer("from") {
+            None => return Ok("Missing required option from".to_string()),
+            Some(i) => i,
+        };
+        let to = match interaction.get_option_integer("to") {
+            None => return Ok("Missing required option to".to_string()),
+            Some(i) => i,
+        };
+
+        let (Some(from_position), Some(to_position)) = (position(from), position(to)) else {
+            return Ok(format!(
+                "Invalid filter numbers {from} and {to}, filters start at 1"
+            ));
+        };
+
+        tracing::info!(channel_id, from, to, "moving filter of channel");
+
+        if let Err(e) = ctx
+            .store
+            .move_filter(channel_id, from_position, to_position)
+            .await
+        {
+            return Ok(format!("Failed to move filter {from}: {e}"));
+        }
+
+        Ok(format!(
+            "Filter {from} of channel <#{channel_id}> moved to position {to}"
+        ))
+    }
+}
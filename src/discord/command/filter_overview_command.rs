@@ -0,0 +1,124 @@
+use twilight_model::application::command::{CommandOption, CommandType};
+
+use super::{
+    CommandParams, CommandTrait, Context, filter_list_command::describe_filters, truncate_list,
+};
+
+pub struct FilterOverviewCmd {}
+
+impl FilterOverviewCmd {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandTrait for FilterOverviewCmd {
+    fn name(&self) -> String {
+        "filter-overview".to_string()
+    }
+
+    fn description(&self) -> String {
+        "List every channel with filters in this server".to_string()
+    }
+
+    fn kind(&self) -> CommandType {
+        CommandType::ChatInput
+    }
+
+    fn options(&self) -> Option<Vec<CommandOption>> {
+        None
+    }
+
+    fn permissions(&self) -> Option<twilight_model::guild::Permissions> {
+        None
+    }
+
+    async fn callback(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<String, anyhow::Error> {
+        let guild_id = interaction.guild_id.get();
+
+        tracing::info!(guild_id, "listing filters for guild");
+
+        let filter_sets = ctx
+            .store
+            .list_guild_filter_sets(guild_id)
+            .await?
+            .into_iter()
+            .filter(|filter_set| !filter_set.filters.is_empty())
+            .collect::<Vec<_>>();
+
+        if filter_sets.is_empty() {
+            return Ok("No filters configured in this server".to_string());
+        }
+
+        // Described in one go so names are resolved in a single batch
+        let all_filters = filter_sets
+            .iter()
+            .flat_map(|filter_set| filter_set.filters.clone())
+            .collect::<Vec<String>>();
+        let mut descriptions = describe_filters(ctx, &all_filters).await.into_iter();
+
+        let channels = filter_sets
+            .iter()
+            .map(|filter_set| {
+                let mut section = format!("**<#{}>**", filter_set.channel_id);
+                if filter_set.webhook.is_some() {
+                    section.push_str(" (webhook)");
+                }
+                if filter_set.disabled.is_some() {
+                    section.push_str(" (paused)");
+                }
+                section.push('\n');
+
+                for (index, description) in descriptions
+                    .by_ref()
+                    .take(filter_set.filters.len())
+                    .enumerate()
+                {
+                    section.push_str(&format!("{}. {description}\n", index + 1));
+                }
+
+                section
+            })
+            .collect::<Vec<String>>();
+
+        Ok(render(&channels))
+    }
+}
+
+// Joins the channel sections, leaving out the ones that don't fit in a
+// message
+fn render(channels: &[String]) -> String {
+    truncate_list(
+        format!("Filters in this server ({} channels):\n", channels.len()),
+        channels,
+        "-# Use /filter-list on a channel for the raw filters",
+        |count| format!("-# …and {count} more channels, use /filter-list to see them\n"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::MAX_CONTENT_LENGTH, *};
+
+    #[test]
+    fn test_render_fits_in_a_message() {
+        let channels = (1..=3)
+            .map(|id| format!("**<#{id}>**\n1. In Jita\n"))
+            .collect::<Vec<_>>();
+        let output = render(&channels);
+        assert!(output.starts_with("Filters in this server (3 channels):\n**<#1>**"));
+        assert!(output.contains("**<#3>**\n1. In Jita\n-# Use /filter-list"));
+
+        let channels = (1..=100)
+            .map(|id| format!("**<#{id}>**\n1. {}\n", "x".repeat(100)))
+            .collect::<Vec<_>>();
+        let output = render(&channels);
+        assert!(output.len() <= MAX_CONTENT_LENGTH);
+        assert!(output.contains("more channels, use /filter-list to see them"));
+    }
+}
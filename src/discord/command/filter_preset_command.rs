@@ -0,0 +1,223 @@
+use twilight_model::{
+    application::command::{CommandOption, CommandOptionChoice, CommandType},
+    channel::ChannelType,
+};
+use twilight_util::builder::command::{BooleanBuilder, ChannelBuilder, StringBuilder};
+
+use crate::{filters::FilterPreset, persistence};
+
+use super::{CommandParams, CommandTrait, Context, autocomplete, truncate_list};
+
+pub struct FilterPresetCmd {}
+
+impl FilterPresetCmd {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandTrait for FilterPresetCmd {
+    fn name(&self) -> String {
+        "filter-preset".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Save the filters of a channel under a name and apply them to other channels".to_string()
+    }
+
+    fn kind(&self) -> CommandType {
+        CommandType::ChatInput
+    }
+
+    fn options(&self) -> Option<Vec<CommandOption>> {
+        let action = StringBuilder::new("action", "What to do with the preset")
+            .choices(vec![
+                ("Save a channel's filters", "save"),
+                ("Apply to a channel", "apply"),
+                ("Delete", "delete"),
+                ("List", "list"),
+            ])
+            .required(true)
+            .build();
+
+        let name = StringBuilder::new("name", "Name of the preset, e.g. home-defence")
+            .required(false)
+            .autocomplete(true)
+            .max_length(FilterPreset::MAX_NAME_LENGTH as u16)
+            .build();
+
+        // Defaults to the channel the command is used in
+        let channel = ChannelBuilder::new("channel", "Channel to save filters from or apply to")
+            .channel_types(vec![ChannelType::GuildText])
+            .required(false)
+            .build();
+
+        let replace = BooleanBuilder::new(
+            "replace",
+            "Replace the filters of the channel instead of adding to them",
+        )
+        .required(false)
+        .build();
+
+        Some(vec![action, name, channel, replace])
+    }
+
+    fn permissions(&self) -> Option<twilight_model::guild::Permissions> {
+        Some(
+            twilight_model::guild::Permissions::ADMINISTRATOR
+                | twilight_model::guild::Permissions::MANAGE_GUILD
+                | twilight_model::guild::Permissions::MANAGE_CHANNELS,
+        )
+    }
+
+    async fn callback(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<String, anyhow::Error> {
+        let guild_id = interaction.guild_id.get();
+
+        let action = match interaction.get_option_string("action") {
+            None => return Ok("Missing required option action".to_string()),
+            Some(action) => action,
+        };
+
+        if action == "list" {
+            return list(ctx, guild_id).await;
+        }
+
+        let name = match interaction.get_option_string("name") {
+            None => return Ok(format!("A name is needed to {action} a preset")),
+            Some(name) => name.trim().to_lowercase(),
+        };
+
+        if action == "delete" {
+            tracing::info!(guild_id, name, "deleting filter preset");
+
+            return Ok(
+                match ctx.store.remove_filter_preset(guild_id, &name).await {
+                    Ok(_) => format!("Preset `{name}` deleted"),
+                    Err(_) => format!("No preset named `{name}`"),
+                },
+            );
+        }
+
+        let channel_id = match interaction.get_option_channel_id("channel") {
+            None => match &interaction.channel {
+                Some(channel) => channel.id,
+                None => return Ok("Missing required option channel".to_string()),
+            },
+            Some(id) => id,
+        };
+
+        match action.as_str() {
+            "save" => {
+                if let Err(e) = FilterPreset::validate_name(&name) {
+                    return Ok(format!("Invalid preset name: {e}"));
+                }
+
+                let filters = match ctx.store.get_channel_filter_set(channel_id).await {
+                    Ok(set) if set.guild_id == guild_id && !set.filters.is_empty() => set.filters,
+                    _ => return Ok(format!("No filters configured for <#{channel_id}>")),
+                };
+
+                tracing::info!(guild_id, channel_id, name, "saving filter preset");
+
+                let count = filters.len();
+                ctx.store
+                    .set_filter_preset(FilterPreset {
+                        guild_id,
+                        name: name.clone(),
+                        filters,
+                    })
+                    .await?;
+
+                Ok(format!(
+                    "Saved the {count} filters of <#{channel_id}> as `{name}`, apply them with /filter-preset"
+                ))
+            }
+            "apply" => {
+                let preset = match ctx.store.get_filter_preset(guild_id, &name).await {
+                    Ok(preset) => preset,
+                    Err(_) => return Ok(format!("No preset named `{name}`")),
+                };
+                let replace = interaction.get_option_boolean("replace").unwrap_or(false);
+
+                tracing::info!(
+                    guild_id,
+                    channel_id,
+                    name,
+                    replace,
+                    "applying filter preset"
+                );
+
+                let added = persistence::apply_filters(
+                    ctx.store.as_ref(),
+                    guild_id,
+                    channel_id,
+                    &preset.filters,
+                    replace,
+                )
+                .await?;
+
+                Ok(match (replace, added) {
+                    (true, _) => format!(
+                        "Filters of <#{channel_id}> replaced with the {added} filters of `{name}`"
+                    ),
+                    (false, 0) => format!("<#{channel_id}> already has every filter of `{name}`"),
+                    (false, _) => format!("Added {added} filters of `{name}` to <#{channel_id}>"),
+                })
+            }
+            action => Ok(format!("Unknown action {action}")),
+        }
+    }
+
+    async fn autocomplete(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Vec<CommandOptionChoice> {
+        match interaction.get_focused_option() {
+            Some((name, value)) if name == "name" => {
+                let presets = ctx
+                    .store
+                    .list_filter_presets(interaction.guild_id.get())
+                    .await
+                    .unwrap_or_default();
+                autocomplete::preset_choices(&presets, &value)
+            }
+            _ => vec![],
+        }
+    }
+}
+
+async fn list(ctx: &Context, guild_id: u64) -> Result<String, anyhow::Error> {
+    let presets = ctx.store.list_filter_presets(guild_id).await?;
+
+    if presets.is_empty() {
+        return Ok(
+            "No presets saved in this server, save a channel's filters with /filter-preset"
+                .to_string(),
+        );
+    }
+
+    let entries = presets
+        .iter()
+        .map(|preset| {
+            format!(
+                "**{}** ({} filters)\n-# `{}`\n",
+                preset.name,
+                preset.filters.len(),
+                preset.filters.join("`, `")
+            )
+        })
+        .collect::<Vec<String>>();
+
+    Ok(truncate_list(
+        "Presets in this server:\n".to_string(),
+        &entries,
+        "",
+        |count| format!("-# …and {count} more presets\n"),
+    ))
+}
@@ -1,10 +1,10 @@
 use twilight_model::{
-    application::command::{CommandOption, CommandType},
+    application::command::{CommandOption, CommandOptionChoice, CommandType},
     channel::ChannelType,
 };
-use twilight_util::builder::command::{ChannelBuilder, StringBuilder};
+use twilight_util::builder::command::{ChannelBuilder, IntegerBuilder, StringBuilder};
 
-use super::{CommandParams, CommandTrait};
+use super::{CommandParams, CommandTrait, Context, autocomplete, position};
 
 pub struct FilterRemoveCmd {}
 
@@ -14,6 +14,7 @@ impl FilterRemoveCmd {
     }
 }
 
+#[async_trait::async_trait]
 impl CommandTrait for FilterRemoveCmd {
     fn name(&self) -> String {
         "filter-remove".to_string()
@@ -28,8 +29,14 @@ impl CommandTrait for FilterRemoveCmd {
     }
 
     fn options(&self) -> Option<Vec<CommandOption>> {
-        let filter = StringBuilder::new("filter", "Filter to add")
-            .required(true)
+        let filter = StringBuilder::new("filter", "Filter to remove")
+            .required(false)
+            .autocomplete(true)
+            .build();
+
+        let index = IntegerBuilder::new("index", "Number of the filter in /filter-list")
+            .required(false)
+            .min_value(1)
             .build();
 
         let channel = ChannelBuilder::new("channel", "Channel to add filter to")
@@ -37,7 +44,7 @@ impl CommandTrait for FilterRemoveCmd {
             .required(true)
             .build();
 
-        Some(vec![channel, filter])
+        Some(vec![channel, filter, index])
     }
 
     fn permissions(&self) -> Option<twilight_model::guild::Permissions> {
@@ -48,26 +55,69 @@ impl CommandTrait for FilterRemoveCmd {
         )
     }
 
-    fn callback(
+    async fn callback(
         &self,
-        store: &dyn crate::persistence::Store,
+        ctx: &Context,
         interaction: &CommandParams,
     ) -> Result<String, anyhow::Error> {
         let channel_id = match interaction.get_option_channel_id("channel") {
             None => return Ok("Missing required option channel".to_string()),
             Some(id) => id,
         };
+
+        if let Some(index) = interaction.get_option_integer("index") {
+            let Some(position) = position(index) else {
+                return Ok(format!("Invalid filter number {index}, filters start at 1"));
+            };
+
+            tracing::info!(channel_id, index, "removing filter at index from channel");
+
+            let filter = match ctx.store.remove_filter_at(channel_id, position).await {
+                Ok(f) => f,
+                Err(e) => return Ok(format!("Failed to remove filter {index}: {e}")),
+            };
+
+            return Ok(format!(
+                "Filter {index} `{filter}` removed successfully from channel <#{channel_id}>"
+            ));
+        }
+
         let filter = match interaction.get_option_string("filter") {
-            None => return Ok("Missing required option filter".to_string()),
+            None => return Ok("Either filter or index is required".to_string()),
             Some(f) => f,
         };
 
         tracing::info!(channel_id, filter, "removing filter from channel");
 
-        store.remove_filter_from_set(channel_id, &filter)?;
+        if let Err(e) = ctx.store.remove_filter_from_set(channel_id, &filter).await {
+            return Ok(format!("Failed to remove filter `{filter}`: {e}"));
+        }
 
         Ok(format!(
             "Filter `{filter}` removed successfully from channel <#{channel_id}>"
         ))
     }
+
+    async fn autocomplete(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Vec<CommandOptionChoice> {
+        let Some((name, value)) = interaction.get_focused_option() else {
+            return vec![];
+        };
+        if name != "filter" {
+            return vec![];
+        }
+
+        // The channel has to be picked first to know which filters to offer
+        let Some(channel_id) = interaction.get_option_channel_id("channel") else {
+            return vec![];
+        };
+
+        match ctx.store.get_channel_filter_set(channel_id).await {
+            Ok(filter_set) => autocomplete::existing_filter_choices(&filter_set.filters, &value),
+            Err(_) => vec![],
+        }
+    }
 }
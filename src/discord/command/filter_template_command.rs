@@ -0,0 +1,161 @@
+use twilight_model::{
+    application::command::{CommandOption, CommandType},
+    channel::ChannelType,
+};
+use twilight_util::builder::command::{ChannelBuilder, StringBuilder};
+
+use crate::{
+    filters::FilterSet,
+    template::{self, Template},
+};
+
+use super::{CommandParams, CommandTrait, Context};
+
+pub struct FilterTemplateCmd {}
+
+impl FilterTemplateCmd {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandTrait for FilterTemplateCmd {
+    fn name(&self) -> String {
+        "filter-template".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Choose how kills are laid out in a channel".to_string()
+    }
+
+    fn kind(&self) -> CommandType {
+        CommandType::ChatInput
+    }
+
+    fn options(&self) -> Option<Vec<CommandOption>> {
+        let channel = ChannelBuilder::new("channel", "Channel to set the template for")
+            .channel_types(vec![ChannelType::GuildText])
+            .required(true)
+            .build();
+
+        let preset = StringBuilder::new("preset", "Template to use")
+            .choices(vec![
+                ("Full", "full"),
+                ("Compact", "compact"),
+                ("Custom", "custom"),
+            ])
+            .required(true)
+            .build();
+
+        let title = StringBuilder::new(
+            "title",
+            "Custom title, e.g. {victim.ship} destroyed in {system}",
+        )
+        .required(false)
+        .max_length(template::MAX_TITLE_LENGTH as u16)
+        .build();
+
+        let description = StringBuilder::new(
+            "description",
+            "Custom description, e.g. {victim.name} lost {value} ISK",
+        )
+        .required(false)
+        .build();
+
+        Some(vec![channel, preset, title, description])
+    }
+
+    fn permissions(&self) -> Option<twilight_model::guild::Permissions> {
+        Some(
+            twilight_model::guild::Permissions::ADMINISTRATOR
+                | twilight_model::guild::Permissions::MANAGE_GUILD,
+        )
+    }
+
+    async fn callback(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<String, anyhow::Error> {
+        let channel_id = match interaction.get_option_channel_id("channel") {
+            None => return Ok("Missing required option channel".to_string()),
+            Some(id) => id,
+        };
+
+        let preset = match interaction.get_option_string("preset") {
+            None => return Ok("Missing required option preset".to_string()),
+            Some(preset) => preset,
+        };
+
+        let template = match preset.as_str() {
+            "custom" => {
+                let Some(title) = interaction.get_option_string("title") else {
+                    return Ok(format!(
+                        "A custom template needs a title, available placeholders: {}",
+                        placeholders()
+                    ));
+                };
+
+                match Template::custom(title, interaction.get_option_string("description")) {
+                    Ok(t) => t,
+                    Err(e) => {
+                        return Ok(format!(
+                            "Invalid template: {e}, available placeholders: {}",
+                            placeholders()
+                        ));
+                    }
+                }
+            }
+            preset => match Template::preset(preset) {
+                Ok(t) => t,
+                Err(e) => return Ok(format!("Invalid template: {e}")),
+            },
+        };
+
+        tracing::info!(
+            channel_id,
+            template = template.name(),
+            "setting template for channel"
+        );
+
+        let response = format!(
+            "Kills for <#{channel_id}> will use the {} template",
+            template.name()
+        );
+
+        let guild_id = interaction.guild_id.get();
+        ctx.store
+            .update_filter_sets(
+                &[channel_id],
+                Box::new(move |current| {
+                    let filter_set = current[0].get_or_insert_with(|| FilterSet {
+                        guild_id,
+                        channel_id,
+                        filters: vec![],
+                        webhook: None,
+                        discord_webhook: None,
+                        template: None,
+                        disabled: None,
+                        config_filters: vec![],
+                    });
+                    filter_set.template = match template {
+                        Template::Full => None,
+                        template => Some(template),
+                    };
+                    Ok(())
+                }),
+            )
+            .await?;
+
+        Ok(response)
+    }
+}
+
+fn placeholders() -> String {
+    template::PLACEHOLDERS
+        .iter()
+        .map(|p| format!("`{{{p}}}`"))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
@@ -0,0 +1,127 @@
+use twilight_model::{
+    application::command::{CommandOption, CommandType},
+    channel::ChannelType,
+};
+use twilight_util::builder::command::ChannelBuilder;
+
+use crate::filters::{FilterSet, FilterSnapshot};
+
+use super::{CommandParams, CommandTrait, Context};
+
+pub struct FilterUndoCmd {}
+
+impl FilterUndoCmd {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandTrait for FilterUndoCmd {
+    fn name(&self) -> String {
+        "filter-undo".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Restore the filters of a channel removed with /filter-clear".to_string()
+    }
+
+    fn kind(&self) -> CommandType {
+        CommandType::ChatInput
+    }
+
+    fn options(&self) -> Option<Vec<CommandOption>> {
+        let channel = ChannelBuilder::new("channel", "Channel to restore filters of")
+            .channel_types(vec![ChannelType::GuildText])
+            .required(true)
+            .build();
+
+        Some(vec![channel])
+    }
+
+    fn permissions(&self) -> Option<twilight_model::guild::Permissions> {
+        Some(
+            twilight_model::guild::Permissions::ADMINISTRATOR
+                | twilight_model::guild::Permissions::MANAGE_GUILD
+                | twilight_model::guild::Permissions::MANAGE_CHANNELS,
+        )
+    }
+
+    async fn callback(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<String, anyhow::Error> {
+        let channel_id = match interaction.get_option_channel_id("channel") {
+            None => return Ok("Missing required option channel".to_string()),
+            Some(id) => id,
+        };
+        let guild_id = interaction.guild_id.get();
+
+        let snapshot = match ctx.store.get_filter_snapshot(channel_id).await {
+            Ok(snapshot) if snapshot.filter_set.guild_id == guild_id => snapshot.filter_set,
+            _ => {
+                return Ok(format!(
+                    "Nothing to restore for <#{channel_id}>, cleared filters are kept for {} hours",
+                    FilterSnapshot::RETENTION.num_hours()
+                ));
+            }
+        };
+
+        tracing::info!(guild_id, channel_id, "restoring cleared filters of channel");
+
+        // Only the filters are restored, merged with the channel's current
+        // ones under the store's lock. Webhooks, template and paused state
+        // may have changed since the clear and stay as they are now.
+        let mut count = 0;
+        let mut added = 0;
+        ctx.store
+            .update_filter_sets(
+                &[channel_id],
+                Box::new(|current| {
+                    let Some(existing) = current[0].as_mut() else {
+                        count = snapshot.filters.len();
+                        current[0] = Some(FilterSet {
+                            disabled: None,
+                            ..snapshot
+                        });
+                        return Ok(());
+                    };
+
+                    // The bot config may have changed since the clear, its
+                    // filters win
+                    let mut filters = existing.config_filters.clone();
+                    let restored = snapshot
+                        .filters
+                        .iter()
+                        .filter(|filter| !snapshot.config_filters.contains(filter));
+                    for filter in restored.chain(&existing.filters) {
+                        if !filters.contains(filter) {
+                            filters.push(filter.clone());
+                        }
+                    }
+
+                    added = existing
+                        .filters
+                        .iter()
+                        .filter(|filter| {
+                            !existing.config_filters.contains(filter)
+                                && !snapshot.filters.contains(filter)
+                        })
+                        .count();
+                    count = filters.len();
+                    existing.filters = filters;
+                    Ok(())
+                }),
+            )
+            .await?;
+        ctx.store.remove_filter_snapshot(channel_id).await?;
+
+        Ok(match added {
+            0 => format!("Restored the {count} filters of <#{channel_id}>"),
+            _ => format!(
+                "Restored the {count} filters of <#{channel_id}>, {added} filters added since the clear were kept"
+            ),
+        })
+    }
+}
@@ -0,0 +1,121 @@
+use twilight_model::{
+    application::command::{CommandOption, CommandType},
+    channel::ChannelType,
+};
+use twilight_util::builder::command::{ChannelBuilder, StringBuilder};
+
+use crate::filters::{DiscordWebhook, FilterSet};
+
+use super::{CommandParams, CommandTrait, Context};
+
+pub struct FilterWebhookCmd {}
+
+impl FilterWebhookCmd {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandTrait for FilterWebhookCmd {
+    fn name(&self) -> String {
+        "filter-webhook".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Post kills for a channel through a Discord webhook, or back as the bot if no URL is given"
+            .to_string()
+    }
+
+    fn kind(&self) -> CommandType {
+        CommandType::ChatInput
+    }
+
+    fn options(&self) -> Option<Vec<CommandOption>> {
+        let channel = ChannelBuilder::new("channel", "Channel the webhook posts to")
+            .channel_types(vec![ChannelType::GuildText])
+            .required(true)
+            .build();
+
+        let url = StringBuilder::new("url", "Webhook URL from the channel's integration settings")
+            .required(false)
+            .build();
+
+        let name = StringBuilder::new("name", "Name kills are posted under")
+            .required(false)
+            .max_length(80)
+            .build();
+
+        let avatar = StringBuilder::new("avatar", "URL of the avatar kills are posted with")
+            .required(false)
+            .build();
+
+        Some(vec![channel, url, name, avatar])
+    }
+
+    fn permissions(&self) -> Option<twilight_model::guild::Permissions> {
+        Some(
+            twilight_model::guild::Permissions::ADMINISTRATOR
+                | twilight_model::guild::Permissions::MANAGE_GUILD
+                | twilight_model::guild::Permissions::MANAGE_WEBHOOKS,
+        )
+    }
+
+    async fn callback(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<String, anyhow::Error> {
+        let channel_id = match interaction.get_option_channel_id("channel") {
+            None => return Ok("Missing required option channel".to_string()),
+            Some(id) => id,
+        };
+
+        let webhook = match interaction.get_option_string("url") {
+            None => None,
+            Some(url) => {
+                let mut webhook = match DiscordWebhook::from_url(&url) {
+                    Ok(w) => w,
+                    Err(e) => return Ok(format!("Invalid webhook URL: {e}")),
+                };
+                webhook.username = interaction.get_option_string("name");
+                webhook.avatar_url = interaction.get_option_string("avatar");
+                Some(webhook)
+            }
+        };
+
+        tracing::info!(
+            channel_id,
+            webhook_id = webhook.as_ref().map(|w| w.id),
+            "setting discord webhook for channel"
+        );
+
+        let response = match &webhook {
+            Some(_) => format!("Kills for <#{channel_id}> will be posted through the webhook"),
+            None => format!("Kills for <#{channel_id}> will be posted by the bot"),
+        };
+
+        let guild_id = interaction.guild_id.get();
+        ctx.store
+            .update_filter_sets(
+                &[channel_id],
+                Box::new(move |current| {
+                    let filter_set = current[0].get_or_insert_with(|| FilterSet {
+                        guild_id,
+                        channel_id,
+                        filters: vec![],
+                        webhook: None,
+                        discord_webhook: None,
+                        template: None,
+                        disabled: None,
+                        config_filters: vec![],
+                    });
+                    filter_set.discord_webhook = webhook;
+                    Ok(())
+                }),
+            )
+            .await?;
+
+        Ok(response)
+    }
+}
@@ -1,48 +1,215 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
 use twilight_http::Client;
 use twilight_model::{
     application::{
-        command::{Command, CommandOption, CommandType},
-        interaction::application_command::{CommandData, CommandDataOption},
+        command::{Command, CommandOption, CommandOptionChoice, CommandType},
+        interaction::{
+            InteractionData,
+            application_command::{CommandData, CommandDataOption},
+            modal::ModalInteractionComponent,
+        },
     },
+    channel::message::{AllowedMentions, Component, MessageFlags},
     gateway::payload::incoming::InteractionCreate,
     guild::Permissions,
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
     id::{Id, marker::GuildMarker},
 };
 use twilight_util::builder::command::CommandBuilder;
 
-use crate::config;
+use crate::{
+    config, names,
+    persistence::audit::{self, Audited},
+    template,
+};
 
+mod autocomplete;
 mod filter_add_command;
+mod filter_builder_command;
 mod filter_clear_command;
+mod filter_copy_command;
+mod filter_edit_command;
+mod filter_export_command;
+mod filter_history_command;
+mod filter_import_command;
 mod filter_list_command;
+mod filter_move_command;
+mod filter_overview_command;
+mod filter_preset_command;
 mod filter_remove_command;
+mod filter_template_command;
+mod filter_undo_command;
+mod filter_webhook_command;
+mod registration;
+
+pub use registration::{register_commands, register_guild_commands};
+
+#[cfg(test)]
+mod tests;
 
+// Context gives commands access to everything they may need to answer
 #[derive(Clone)]
-pub struct Handler {
+pub struct Context {
     // store is the persistence store used by commands
-    store: Arc<dyn crate::persistence::Store>,
+    pub store: Arc<dyn crate::persistence::Store>,
+
+    // http is the client for ESI, zKillboard and other HTTP APIs
+    pub http: reqwest::Client,
+
+    // discord is the client for the Discord API
+    pub discord: Arc<Client>,
+
+    // names resolves ids for command output
+    pub names: names::Resolver,
+}
+
+// Role ids allowed to use a command, by guild id
+type GuildRoles = HashMap<u64, Vec<u64>>;
+
+// Discord refuses message content longer than this
+pub(super) const MAX_CONTENT_LENGTH: usize = 2000;
+
+// Turns a filter number shown by /filter-list into its position, None for
+// numbers no filter can have
+pub(super) fn position(index: i64) -> Option<usize> {
+    usize::try_from(index).ok().and_then(|i| i.checked_sub(1))
+}
+
+// Cuts content that doesn't fit in a message
+pub(super) fn truncate(content: String) -> String {
+    template::truncate(content, MAX_CONTENT_LENGTH)
+}
+
+// Lists as many entries as fit in a message between the header and the
+// footer, the ones left out are summed up by the omitted line for their count
+pub(super) fn truncate_list(
+    header: String,
+    entries: &[String],
+    footer: &str,
+    omitted: impl Fn(usize) -> String,
+) -> String {
+    let mut output = header;
+
+    for (index, entry) in entries.iter().enumerate() {
+        let more = omitted(entries.len() - index);
+        if output.len() + entry.len() + more.len() + footer.len() > MAX_CONTENT_LENGTH {
+            output.push_str(&more);
+            break;
+        }
+        output.push_str(entry);
+    }
+
+    output.push_str(footer);
+    truncate(output)
+}
+
+#[derive(Clone)]
+pub struct Handler {
+    // context is handed to every command
+    context: Context,
+
+    // config decides which commands are enabled in guilds joined later
+    config: Arc<config::Config>,
 
     // commands is a map of command name to command implementation
     commands: Arc<HashMap<String, Arc<dyn CommandTrait>>>,
 
-    // validator is a map of guild ID to list of valid commands
-    validator: HashMap<String, Vec<u64>>,
+    // validator is a map of command name to the guilds it is enabled in,
+    // updated as guilds are joined and left
+    validator: Arc<RwLock<HashMap<String, Vec<u64>>>>,
+
+    // roles is a map of command name to the roles allowed to use it per guild,
+    // guilds without restrictions are missing
+    roles: Arc<RwLock<HashMap<String, GuildRoles>>>,
 }
 
+// Channel the interaction was used in
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct Channel {
+    pub id: u64,
+    pub name: Option<String>,
+}
+
+// File uploaded through an attachment option, downloaded from its url
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub filename: String,
+    pub url: String,
+    pub size: u64,
+}
+
+// User who used the interaction, with their roles and permissions in the guild
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct User {
     pub id: u64,
     pub name: String,
+    pub roles: Vec<u64>,
+    pub permissions: Option<Permissions>,
+}
+
+impl User {
+    // Guild interactions carry the member, others only the user
+    pub fn parse_interaction(event: &InteractionCreate) -> Option<User> {
+        match &event.member {
+            Some(member) => member.user.as_ref().map(|user| User {
+                id: user.id.get(),
+                name: member.nick.clone().unwrap_or_else(|| user.name.clone()),
+                roles: member.roles.iter().map(|role| role.get()).collect(),
+                permissions: member.permissions,
+            }),
+            None => event.user.as_ref().map(|user| User {
+                id: user.id.get(),
+                name: user.name.clone(),
+                roles: vec![],
+                permissions: None,
+            }),
+        }
+    }
+
+    // Administrators may use every command, everyone else needs one of the
+    // allowed roles when the command is restricted
+    pub fn check_roles(user: Option<&User>, allowed: &[u64]) -> Result<(), anyhow::Error> {
+        if allowed.is_empty() {
+            return Ok(());
+        }
+
+        let Some(user) = user else {
+            return Err(anyhow::format_err!("unknown user"));
+        };
+
+        if user
+            .permissions
+            .is_some_and(|p| p.contains(Permissions::ADMINISTRATOR))
+            || user.roles.iter().any(|role| allowed.contains(role))
+        {
+            return Ok(());
+        }
+
+        let roles = allowed
+            .iter()
+            .map(|role| format!("<@&{role}>"))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        Err(anyhow::format_err!(
+            "you need one of these roles to use this command: {roles}"
+        ))
+    }
 }
 
 #[derive(Debug)]
 pub struct CommandParams {
     guild_id: Id<GuildMarker>,
-    channel: Channel,
+    channel: Option<Channel>,
+    user: Option<User>,
     name: String,
     options: HashMap<String, CommandDataOption>,
+    attachments: HashMap<u64, Attachment>,
 }
 
 impl CommandParams {
@@ -66,233 +233,415 @@ impl CommandParams {
         }
     }
 
-    pub fn parse_interaction(event: &CommandData) -> Result<CommandParams, anyhow::Error> {
-        let guild_id = match event.guild_id {
+    pub fn get_option_integer(&self, name: &str) -> Option<i64> {
+        match self.options.get(name) {
+            Some(val) => match &val.value {
+                twilight_model::application::interaction::application_command::CommandOptionValue::Integer(i) => Some(*i),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
+    pub fn get_option_boolean(&self, name: &str) -> Option<bool> {
+        match self.options.get(name) {
+            Some(val) => match &val.value {
+                twilight_model::application::interaction::application_command::CommandOptionValue::Boolean(b) => Some(*b),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
+    pub fn get_option_attachment(&self, name: &str) -> Option<&Attachment> {
+        match self.options.get(name) {
+            Some(val) => match &val.value {
+                twilight_model::application::interaction::application_command::CommandOptionValue::Attachment(id) => self.attachments.get(&id.get()),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
+    // The option the user is typing in during autocomplete, with its value so far
+    pub fn get_focused_option(&self) -> Option<(String, String)> {
+        self.options.values().find_map(|opt| match &opt.value {
+            twilight_model::application::interaction::application_command::CommandOptionValue::Focused(value, _) => Some((opt.name.clone(), value.clone())),
+            _ => None,
+        })
+    }
+
+    pub fn parse_interaction(event: &InteractionCreate) -> Result<CommandParams, anyhow::Error> {
+        let data: &CommandData = match &event.data {
+            Some(InteractionData::ApplicationCommand(data)) => data,
+            Some(_) => return Err(anyhow::format_err!("unexpected interaction data")),
+            None => return Err(anyhow::format_err!("missing interaction data")),
+        };
+
+        let guild_id = match event.guild_id.or(data.guild_id) {
             Some(gid) => gid,
             None => {
                 return Err(anyhow::format_err!("command not issued in a guild"));
             }
         };
 
-        let resolutions = match &event.resolved {
-            Some(res) => res,
-            None => {
-                tracing::error!(?event, "missing resolved data in interaction");
-                return Err(anyhow::format_err!("missing resolved data in interaction"));
-            }
-        };
+        // The channel the command was used in, channel options are read with
+        // get_option_channel_id
+        let channel = event.channel.as_ref().map(|channel| Channel {
+            id: channel.id.get(),
+            name: channel.name.clone(),
+        });
 
-        let channel = resolutions.channels.iter().next().ok_or_else(|| {
-            anyhow::format_err!("missing channel data in resolved interaction data")
-        })?;
+        let user = User::parse_interaction(event);
 
-        let options = event
+        let options = data
             .options
             .iter()
             .map(|opt| (opt.name.clone(), opt.clone()))
             .collect::<HashMap<String, CommandDataOption>>();
 
+        // Attachment options only carry an id, the file is in the resolved data
+        let attachments = data
+            .resolved
+            .iter()
+            .flat_map(|resolved| resolved.attachments.values())
+            .map(|attachment| {
+                (
+                    attachment.id.get(),
+                    Attachment {
+                        filename: attachment.filename.clone(),
+                        url: attachment.url.clone(),
+                        size: attachment.size,
+                    },
+                )
+            })
+            .collect::<HashMap<u64, Attachment>>();
+
         Ok(CommandParams {
             guild_id,
-            channel: Channel {
-                id: channel.0.get(),
-                name: channel.1.name.clone(),
-            },
-            name: event.name.clone(),
+            channel,
+            user,
+            name: data.name.clone(),
             options,
+            attachments,
+        })
+    }
+}
+
+// ComponentParams are the parameters of a message component or modal submit
+// interaction. Custom ids look like `command:action:state`, so the interaction
+// can be routed back to the command that sent the components.
+#[derive(Debug)]
+pub struct ComponentParams {
+    guild_id: Id<GuildMarker>,
+    user: Option<User>,
+    name: String,
+    action: String,
+    state: String,
+    // selected values of a select menu
+    values: Vec<String>,
+    // text inputs of a modal by custom id
+    fields: HashMap<String, String>,
+}
+
+impl ComponentParams {
+    pub fn get_field(&self, custom_id: &str) -> Option<String> {
+        self.fields.get(custom_id).cloned()
+    }
+
+    pub fn parse_interaction(event: &InteractionCreate) -> Result<ComponentParams, anyhow::Error> {
+        let guild_id = match event.guild_id {
+            Some(gid) => gid,
+            None => {
+                return Err(anyhow::format_err!("component not used in a guild"));
+            }
+        };
+
+        let (custom_id, values, fields) = match &event.data {
+            Some(InteractionData::MessageComponent(data)) => {
+                (data.custom_id.clone(), data.values.clone(), HashMap::new())
+            }
+            Some(InteractionData::ModalSubmit(data)) => {
+                let mut fields = HashMap::new();
+                collect_fields(&data.components, &mut fields);
+                (data.custom_id.clone(), vec![], fields)
+            }
+            _ => return Err(anyhow::format_err!("unexpected interaction data")),
+        };
+
+        let mut parts = custom_id.splitn(3, ':');
+        let name = parts.next().unwrap_or_default().to_string();
+        let action = parts.next().unwrap_or_default().to_string();
+        let state = parts.next().unwrap_or_default().to_string();
+
+        Ok(ComponentParams {
+            guild_id,
+            user: User::parse_interaction(event),
+            name,
+            action,
+            state,
+            values,
+            fields,
         })
     }
 }
 
+// Text inputs are nested in labels or action rows depending on the client
+fn collect_fields(components: &[ModalInteractionComponent], fields: &mut HashMap<String, String>) {
+    for component in components {
+        match component {
+            ModalInteractionComponent::Label(label) => {
+                collect_fields(std::slice::from_ref(label.component.as_ref()), fields)
+            }
+            ModalInteractionComponent::ActionRow(row) => collect_fields(&row.components, fields),
+            ModalInteractionComponent::TextInput(input) => {
+                fields.insert(input.custom_id.clone(), input.value.clone());
+            }
+            _ => {}
+        }
+    }
+}
+
+// Ephemeral reply to a command
+pub fn message(content: String) -> InteractionResponse {
+    InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(InteractionResponseData {
+            content: Some(content),
+            flags: Some(MessageFlags::EPHEMERAL),
+            ..Default::default()
+        }),
+    }
+}
+
+// Replaces the message the components were sent with
+pub fn update(content: String, components: Vec<Component>) -> InteractionResponse {
+    InteractionResponse {
+        kind: InteractionResponseType::UpdateMessage,
+        data: Some(InteractionResponseData {
+            content: Some(content),
+            components: Some(components),
+            ..Default::default()
+        }),
+    }
+}
+
 impl Handler {
     pub fn build(
         config: &config::Config,
-        store: Arc<dyn crate::persistence::Store>,
+        context: Context,
         guild_ids: Vec<Id<GuildMarker>>,
     ) -> Result<Self, anyhow::Error> {
-        let mut handler = Self {
-            store,
-            commands: Arc::new(HashMap::new()),
-            validator: HashMap::new(),
+        let handler = Self {
+            context,
+            config: Arc::new(config.clone()),
+            commands: Arc::new(command_list()),
+            validator: Arc::new(RwLock::new(HashMap::new())),
+            roles: Arc::new(RwLock::new(HashMap::new())),
         };
 
-        build_commands(config, &mut handler, guild_ids)?;
+        for guild_id in guild_ids {
+            handler.add_guild(guild_id);
+        }
 
         Ok(handler)
     }
 
-    pub async fn handle(&self, event: &InteractionCreate) -> Result<String, anyhow::Error> {
-        let params: CommandParams = match &event.data {
-            Some(data) => match data {
-                twilight_model::application::interaction::InteractionData::ApplicationCommand(
-                    cmd,
-                ) => CommandParams::parse_interaction(cmd)?,
-                _ => {
-                    return Err(anyhow::format_err!("unexpected interaction data"));
-                }
-            },
-            None => {
-                return Err(anyhow::format_err!("missing interaction data"));
-            }
-        };
+    pub async fn handle(
+        &self,
+        event: &InteractionCreate,
+    ) -> Result<InteractionResponse, anyhow::Error> {
+        let (command, params) = self.command(event)?;
 
-        match self.validator.get(&params.name) {
-            Some(guilds) => {
-                if !guilds.contains(&params.guild_id.get()) {
-                    return Err(anyhow::format_err!(
-                        "command not enabled for guild {}",
-                        params.guild_id.get()
-                    ));
-                }
-            }
-            None => {
-                return Err(anyhow::format_err!(
-                    "command not found in validator: {}",
-                    params.name
-                ));
-            }
+        let (ctx, audited) = self.audited(&command.name(), params.user.as_ref());
+        let response = command.response(&ctx, &params).await;
+        self.log_changes(params.guild_id, audited.changes()).await;
+
+        response
+    }
+
+    // Handles select menus, buttons and modals sent by a command
+    pub async fn component(
+        &self,
+        event: &InteractionCreate,
+    ) -> Result<InteractionResponse, anyhow::Error> {
+        let params = ComponentParams::parse_interaction(event)?;
+
+        if !self.enabled(&params.name, params.guild_id) {
+            return Err(anyhow::format_err!(
+                "command {} not enabled for guild {}",
+                params.name,
+                params.guild_id.get()
+            ));
         }
 
+        User::check_roles(
+            params.user.as_ref(),
+            &self.allowed_roles(&params.name, params.guild_id),
+        )?;
+
         tracing::trace!(
             guild_id = params.guild_id.get(),
             command_name = params.name.as_str(),
-            command_names = ?self.commands.keys().collect::<Vec<&String>>(),
-            "handling command"
+            action = params.action.as_str(),
+            "handling component"
         );
 
         let command = match self.commands.get(&params.name) {
-            Some(cmd) => cmd,
+            Some(cmd) => cmd.clone(),
             None => {
                 return Err(anyhow::format_err!("command not found: {}", params.name));
             }
         };
 
-        command.callback(self.store.as_ref(), &params)
-    }
+        let (ctx, audited) = self.audited(&params.name, params.user.as_ref());
+        let response = command.component(&ctx, &params).await;
+        self.log_changes(params.guild_id, audited.changes()).await;
 
-    pub async fn shutdown(&self, client: &Client) -> Result<(), anyhow::Error> {
-        deregister_commands(self, client).await?;
-        tracing::info!("shutting down command handler");
-        Ok(())
+        response
     }
-}
 
-pub async fn deregister_commands(handler: &Handler, client: &Client) -> Result<(), anyhow::Error> {
-    let current_application = client.current_user_application().await?.model().await?;
-    let mut output: Vec<(String, Vec<u64>)> = Vec::new();
-    for command in handler.commands.values() {
-        let guilds = handler
-            .validator
-            .get(&command.name())
-            .cloned()
-            .unwrap_or_default();
-        if guilds.is_empty() {
-            tracing::debug!(
-                command_name = command.name().as_str(),
-                "no guilds to register command in, skipping"
-            );
-            continue;
-        }
+    // Context for a single interaction, with a store that records the
+    // changes the user makes
+    fn audited(&self, command: &str, user: Option<&User>) -> (Context, Arc<Audited>) {
+        let actor = audit::Actor {
+            user_id: user.map(|u| u.id).unwrap_or_default(),
+            user_name: user
+                .map(|u| u.name.clone())
+                .unwrap_or_else(|| "unknown".to_string()),
+            command: command.to_string(),
+        };
+        let audited = Arc::new(Audited::new(self.context.store.clone(), actor));
 
-        for guild_id in &guilds {
-            let id = Id::<GuildMarker>::new(*guild_id);
-            let commands = client
-                .interaction(current_application.id)
-                .guild_commands(id)
-                .await?
-                .models()
-                .await?;
-
-            for cmd in commands {
-                if cmd.name == command.name() {
-                    tracing::trace!(
-                        guild_id = guild_id,
-                        command_name = cmd.name.as_str(),
-                        "deleting command from guild"
-                    );
+        let ctx = Context {
+            store: audited.clone(),
+            ..self.context.clone()
+        };
 
-                    let cmd_id = match cmd.id {
-                        Some(cid) => cid,
-                        None => {
-                            tracing::warn!(
-                                guild_id = guild_id,
-                                command_name = cmd.name.as_str(),
-                                "command has no ID, skipping deletion"
-                            );
-                            continue;
-                        }
-                    };
-
-                    client
-                        .interaction(current_application.id)
-                        .delete_guild_command(id, cmd_id)
-                        .await?;
-
-                    tracing::trace!(
-                        guild_id = guild_id,
-                        command_name = cmd.name.as_str(),
-                        "deleted command from guild"
-                    );
-                }
+        (ctx, audited)
+    }
+
+    // Mirrors the changes to the guild's log channel, when it has one
+    async fn log_changes(&self, guild_id: Id<GuildMarker>, changes: Vec<audit::Change>) {
+        let Some(log_channel) = self.config.log_channel(guild_id.get()) else {
+            return;
+        };
+
+        for change in changes {
+            let content = truncate(format!(
+                "<@{}> used /{} in <#{}>\n{}",
+                change.user_id,
+                change.command,
+                change.channel_id,
+                change.describe()
+            ));
+
+            // Posted without pinging the user
+            if let Err(e) = self
+                .context
+                .discord
+                .create_message(Id::new(log_channel))
+                .content(&content)
+                .allowed_mentions(Some(&AllowedMentions::default()))
+                .await
+            {
+                tracing::error!(
+                    guild_id = guild_id.get(),
+                    log_channel,
+                    error = e.to_string(),
+                    "failed to post change to log channel"
+                );
             }
         }
+    }
+
+    pub async fn autocomplete(
+        &self,
+        event: &InteractionCreate,
+    ) -> Result<Vec<CommandOptionChoice>, anyhow::Error> {
+        let (command, params) = self.command(event)?;
 
-        output.push((command.name(), guilds));
+        Ok(command.autocomplete(&self.context, &params).await)
     }
 
-    tracing::debug!(?output, "deregistered commands");
+    // Parses the interaction and looks up the command it is for, if it is
+    // enabled in the guild
+    fn command(
+        &self,
+        event: &InteractionCreate,
+    ) -> Result<(Arc<dyn CommandTrait>, CommandParams), anyhow::Error> {
+        let params = CommandParams::parse_interaction(event)?;
+
+        if !self.enabled(&params.name, params.guild_id) {
+            return Err(anyhow::format_err!(
+                "command {} not enabled for guild {}",
+                params.name,
+                params.guild_id.get()
+            ));
+        }
 
-    Ok(())
-}
+        User::check_roles(
+            params.user.as_ref(),
+            &self.allowed_roles(&params.name, params.guild_id),
+        )?;
 
-pub async fn register_commands(handler: &Handler, client: &Client) -> Result<(), anyhow::Error> {
-    let current_application = client.current_user_application().await?.model().await?;
+        tracing::trace!(
+            guild_id = params.guild_id.get(),
+            command_name = params.name.as_str(),
+            user_id = params.user.as_ref().map(|u| u.id),
+            command_names = ?self.commands.keys().collect::<Vec<&String>>(),
+            "handling command"
+        );
 
-    for command in handler.commands.values() {
-        let guilds = handler
-            .validator
-            .get(&command.name())
-            .cloned()
-            .unwrap_or_default();
-        if guilds.is_empty() {
-            tracing::debug!(
-                command_name = command.name().as_str(),
-                "no guilds to register command in, skipping"
-            );
-            continue;
-        }
+        let command = match self.commands.get(&params.name) {
+            Some(cmd) => cmd.clone(),
+            None => {
+                return Err(anyhow::format_err!("command not found: {}", params.name));
+            }
+        };
 
-        for guild_id in guilds {
-            let id = Id::<GuildMarker>::new(guild_id);
-            let options = command.options().unwrap_or_default();
-            client
-                .interaction(current_application.id)
-                .create_guild_command(id)
-                .chat_input(command.name().as_str(), command.description().as_str())
-                .command_options(&options)
-                .await?;
-        }
+        Ok((command, params))
     }
-    Ok(())
-}
 
-fn build_commands(
-    config: &config::Config,
-    handler: &mut Handler,
-    guild_ids: Vec<Id<GuildMarker>>,
-) -> Result<(), anyhow::Error> {
-    let mut built_commands: HashMap<String, Arc<dyn CommandTrait>> = HashMap::new();
+    fn enabled(&self, command: &str, guild_id: Id<GuildMarker>) -> bool {
+        self.validator.read().is_ok_and(|validator| {
+            validator
+                .get(command)
+                .is_some_and(|guilds| guilds.contains(&guild_id.get()))
+        })
+    }
 
-    let command_list: Vec<Arc<dyn CommandTrait>> = vec![
-        Arc::new(filter_add_command::FilterAddCmd::new()),
-        Arc::new(filter_list_command::FilterListCmd::new()),
-        Arc::new(filter_remove_command::FilterRemoveCmd::new()),
-        Arc::new(filter_clear_command::FilterClearCmd::new()),
-    ];
+    fn allowed_roles(&self, command: &str, guild_id: Id<GuildMarker>) -> Vec<u64> {
+        self.roles
+            .read()
+            .ok()
+            .and_then(|roles| roles.get(command)?.get(&guild_id.get()).cloned())
+            .unwrap_or_default()
+    }
 
-    for cmd in command_list {
-        for guild_id in guild_ids.clone() {
-            match config.guild_commands(guild_id.get()) {
+    // Commands enabled in the guild
+    fn guild_commands(&self, guild_id: Id<GuildMarker>) -> Vec<Arc<dyn CommandTrait>> {
+        let mut commands = self
+            .commands
+            .values()
+            .filter(|cmd| self.enabled(&cmd.name(), guild_id))
+            .cloned()
+            .collect::<Vec<Arc<dyn CommandTrait>>>();
+        commands.sort_by_key(|cmd| cmd.name());
+        commands
+    }
+
+    // Enables the commands the config allows in the guild, returns the names
+    // of the commands that weren't enabled yet
+    pub fn add_guild(&self, guild_id: Id<GuildMarker>) -> Vec<String> {
+        let mut added = vec![];
+
+        for cmd in self.commands.values() {
+            if self.enabled(&cmd.name(), guild_id) {
+                continue;
+            }
+
+            match self.config.guild_commands(guild_id.get()) {
                 config::CommandsEnabled::None => {
                     tracing::debug!(
                         guild_id = guild_id.get(),
@@ -316,61 +665,139 @@ fn build_commands(
 
             tracing::trace!(
                 guild_id = guild_id.get(),
-                guilds_enabled = ?handler.validator.get(&cmd.name()),
                 command_name = cmd.name().as_str(),
                 "building command for guild"
             );
-            match build_command(cmd.as_ref(), guild_id) {
-                Err(e) => {
-                    if e.to_string().contains("not enabled for guild") {
-                        tracing::trace!(
-                            guild_id = guild_id.get(),
-                            command_name = cmd.name().as_str(),
-                            "command not enabled for guild, skipping"
-                        );
-                        continue;
-                    }
-                    tracing::warn!(
-                        error = e.to_string(),
-                        guild_id = guild_id.get(),
-                        command_name = cmd.name().as_str(),
-                        "failed to build command for guild"
-                    );
-                    continue;
-                }
-                Ok(command) => {
-                    tracing::trace!(
-                        guild_id = guild_id.get(),
-                        command_name = command.name.as_str(),
-                        "created command in guild"
-                    );
-                    built_commands.insert(command.name.clone(), cmd.clone());
-                    handler
-                        .validator
-                        .entry(cmd.name())
-                        .or_default()
-                        .push(guild_id.get());
+            if let Err(e) = build_command(cmd.as_ref(), guild_id) {
+                tracing::warn!(
+                    error = e.to_string(),
+                    guild_id = guild_id.get(),
+                    command_name = cmd.name().as_str(),
+                    "failed to build command for guild"
+                );
+                continue;
+            }
+
+            if let Ok(mut validator) = self.validator.write() {
+                validator
+                    .entry(cmd.name())
+                    .or_default()
+                    .push(guild_id.get());
+            }
+
+            let roles = self.config.command_roles(guild_id.get(), &cmd.name());
+            if !roles.is_empty()
+                && let Ok(mut allowed) = self.roles.write()
+            {
+                allowed
+                    .entry(cmd.name())
+                    .or_default()
+                    .insert(guild_id.get(), roles);
+            }
+
+            added.push(cmd.name());
+        }
+
+        added.sort();
+        added
+    }
+
+    // Disables all commands in the guild, returns the names of the commands
+    // that were enabled
+    pub fn remove_guild(&self, guild_id: Id<GuildMarker>) -> Vec<String> {
+        let mut removed = vec![];
+
+        if let Ok(mut validator) = self.validator.write() {
+            for (name, guilds) in validator.iter_mut() {
+                if guilds.contains(&guild_id.get()) {
+                    guilds.retain(|g| *g != guild_id.get());
+                    removed.push(name.clone());
                 }
             }
+            validator.retain(|_, guilds| !guilds.is_empty());
         }
+
+        if let Ok(mut roles) = self.roles.write() {
+            for guilds in roles.values_mut() {
+                guilds.remove(&guild_id.get());
+            }
+            roles.retain(|_, guilds| !guilds.is_empty());
+        }
+
+        removed.sort();
+        removed
     }
+}
 
-    handler.commands = Arc::new(built_commands.clone());
+fn command_list() -> HashMap<String, Arc<dyn CommandTrait>> {
+    let command_list: Vec<Arc<dyn CommandTrait>> = vec![
+        Arc::new(filter_add_command::FilterAddCmd::new()),
+        Arc::new(filter_builder_command::FilterBuilderCmd::new()),
+        Arc::new(filter_list_command::FilterListCmd::new()),
+        Arc::new(filter_overview_command::FilterOverviewCmd::new()),
+        Arc::new(filter_history_command::FilterHistoryCmd::new()),
+        Arc::new(filter_remove_command::FilterRemoveCmd::new()),
+        Arc::new(filter_edit_command::FilterEditCmd::new()),
+        Arc::new(filter_move_command::FilterMoveCmd::new()),
+        Arc::new(filter_clear_command::FilterClearCmd::new()),
+        Arc::new(filter_undo_command::FilterUndoCmd::new()),
+        Arc::new(filter_copy_command::FilterCopyCmd::new()),
+        Arc::new(filter_preset_command::FilterPresetCmd::new()),
+        Arc::new(filter_export_command::FilterExportCmd::new()),
+        Arc::new(filter_import_command::FilterImportCmd::new()),
+        Arc::new(filter_template_command::FilterTemplateCmd::new()),
+        Arc::new(filter_webhook_command::FilterWebhookCmd::new()),
+    ];
 
-    Ok(())
+    command_list
+        .into_iter()
+        .map(|cmd| (cmd.name(), cmd))
+        .collect()
 }
 
+#[async_trait::async_trait]
 pub trait CommandTrait: Send + Sync {
     fn name(&self) -> String;
     fn description(&self) -> String;
     fn kind(&self) -> CommandType;
     fn options(&self) -> Option<Vec<CommandOption>>;
     fn permissions(&self) -> Option<Permissions>;
-    fn callback(
+    async fn callback(
         &self,
-        store: &dyn crate::persistence::Store,
+        ctx: &Context,
         interaction: &CommandParams,
     ) -> Result<String, anyhow::Error>;
+
+    // Suggestions for the option being typed, for options built with autocomplete
+    async fn autocomplete(
+        &self,
+        _ctx: &Context,
+        _interaction: &CommandParams,
+    ) -> Vec<CommandOptionChoice> {
+        vec![]
+    }
+
+    // Full response to the command, an ephemeral message with the callback's
+    // text unless the command needs components
+    async fn response(
+        &self,
+        ctx: &Context,
+        interaction: &CommandParams,
+    ) -> Result<InteractionResponse, anyhow::Error> {
+        Ok(message(self.callback(ctx, interaction).await?))
+    }
+
+    // Handles the components sent with a response
+    async fn component(
+        &self,
+        _ctx: &Context,
+        interaction: &ComponentParams,
+    ) -> Result<InteractionResponse, anyhow::Error> {
+        Err(anyhow::format_err!(
+            "command {} has no components",
+            interaction.name
+        ))
+    }
 }
 
 pub fn build_command(
@@ -380,17 +807,16 @@ pub fn build_command(
     let mut builder =
         CommandBuilder::new(cmd.name().as_str(), cmd.description().as_str(), cmd.kind());
 
+    // Commands without permissions are available to everyone
     if let Some(permissions) = cmd.permissions() {
         builder = builder.default_member_permissions(permissions);
     }
 
-    let built_cmd = builder
-        .default_member_permissions(
-            Permissions::ADMINISTRATOR | Permissions::MANAGE_GUILD | Permissions::MANAGE_CHANNELS,
-        )
-        .guild_id(guild_id)
-        .validate()?
-        .build();
+    for option in cmd.options().unwrap_or_default() {
+        builder = builder.option(option);
+    }
+
+    let built_cmd = builder.guild_id(guild_id).validate()?.build();
 
     Ok(built_cmd)
 }
@@ -0,0 +1,193 @@
+use twilight_http::client::InteractionClient;
+use twilight_model::{
+    application::command::{Command, CommandOption},
+    id::{Id, marker::GuildMarker},
+};
+
+use super::{Handler, build_command};
+
+// Diff lists the commands that differ between Discord and the handler, by name
+#[derive(Debug, Default, PartialEq)]
+pub struct Diff {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+pub fn diff(registered: &[Command], wanted: &[Command]) -> Diff {
+    let mut diff = Diff::default();
+
+    for command in wanted {
+        match registered.iter().find(|r| r.name == command.name) {
+            None => diff.added.push(command.name.clone()),
+            Some(r) if !same(r, command) => diff.changed.push(command.name.clone()),
+            Some(_) => {}
+        }
+    }
+
+    diff.removed = registered
+        .iter()
+        .filter(|r| !wanted.iter().any(|c| c.name == r.name))
+        .map(|r| r.name.clone())
+        .collect();
+
+    diff
+}
+
+// Syncs the commands of every guild, guilds without enabled commands have
+// theirs removed. A guild that fails is logged and doesn't hold up the others.
+pub async fn register_commands(
+    handler: &Handler,
+    guild_ids: &[Id<GuildMarker>],
+) -> Result<(), anyhow::Error> {
+    let client = &handler.context.discord;
+    let current_application = client.current_user_application().await?.model().await?;
+    let interaction = client.interaction(current_application.id);
+
+    for guild_id in guild_ids {
+        if let Err(e) = sync_guild(handler, &interaction, *guild_id).await {
+            tracing::error!(
+                guild_id = guild_id.get(),
+                error = e.to_string(),
+                "failed to register commands in guild"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn register_guild_commands(
+    handler: &Handler,
+    guild_id: Id<GuildMarker>,
+) -> Result<(), anyhow::Error> {
+    let client = &handler.context.discord;
+    let current_application = client.current_user_application().await?.model().await?;
+
+    sync_guild(
+        handler,
+        &client.interaction(current_application.id),
+        guild_id,
+    )
+    .await
+}
+
+// Overwrites the guild's commands in a single request, and only when they
+// differ from what Discord has, so restarts don't touch commands at all
+async fn sync_guild(
+    handler: &Handler,
+    interaction: &InteractionClient<'_>,
+    guild_id: Id<GuildMarker>,
+) -> Result<(), anyhow::Error> {
+    let wanted = handler
+        .guild_commands(guild_id)
+        .iter()
+        .map(|cmd| build_command(cmd.as_ref(), guild_id))
+        .collect::<Result<Vec<Command>, anyhow::Error>>()?;
+
+    let registered = interaction.guild_commands(guild_id).await?.models().await?;
+
+    let diff = diff(&registered, &wanted);
+    if diff.is_empty() {
+        tracing::debug!(guild_id = guild_id.get(), "guild commands up to date");
+        return Ok(());
+    }
+
+    interaction.set_guild_commands(guild_id, &wanted).await?;
+
+    tracing::info!(
+        guild_id = guild_id.get(),
+        added = ?diff.added,
+        changed = ?diff.changed,
+        removed = ?diff.removed,
+        "registered commands in guild"
+    );
+
+    Ok(())
+}
+
+fn same(registered: &Command, wanted: &Command) -> bool {
+    registered.kind == wanted.kind
+        && registered.description == wanted.description
+        && registered.default_member_permissions == wanted.default_member_permissions
+        && normalize(&registered.options) == normalize(&wanted.options)
+}
+
+// Discord leaves out fields set to their defaults and adds localizations we
+// never send, so both sides are compared without them
+fn normalize(options: &[CommandOption]) -> Vec<CommandOption> {
+    options
+        .iter()
+        .cloned()
+        .map(|mut option| {
+            option.autocomplete = option.autocomplete.filter(|a| *a);
+            option.required = option.required.filter(|r| *r);
+            option.channel_types = option.channel_types.filter(|c| !c.is_empty());
+            option.choices = option.choices.filter(|c| !c.is_empty());
+            option.description_localizations = None;
+            option.name_localizations = None;
+            option.options = option
+                .options
+                .map(|o| normalize(&o))
+                .filter(|o| !o.is_empty());
+            option
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::{application::command::CommandType, guild::Permissions};
+    use twilight_util::builder::command::{CommandBuilder, StringBuilder};
+
+    use super::*;
+
+    fn command(name: &str, description: &str, required: bool) -> Command {
+        CommandBuilder::new(name, description, CommandType::ChatInput)
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .option(StringBuilder::new("filter", "Filter to add").required(required))
+            .build()
+    }
+
+    #[test]
+    fn test_diff_unchanged() {
+        let wanted = vec![command("filter-add", "Add a new filter", false)];
+
+        // As returned by Discord, with ids and without defaults
+        let mut registered = wanted.clone();
+        registered[0].id = Some(Id::new(1));
+        registered[0].options[0].required = None;
+        registered[0].options[0].description_localizations = Some(Default::default());
+
+        assert!(diff(&registered, &wanted).is_empty());
+    }
+
+    #[test]
+    fn test_diff_changes() {
+        let registered = vec![
+            command("filter-add", "Add a new filter", true),
+            command("filter-list", "List filters", true),
+            command("testing-krusty", "this is a test", true),
+        ];
+        let wanted = vec![
+            command("filter-add", "Add a new filter", false),
+            command("filter-list", "List filters", true),
+            command("filter-edit", "Edit a filter", true),
+        ];
+
+        assert_eq!(
+            diff(&registered, &wanted),
+            Diff {
+                added: vec!["filter-edit".to_string()],
+                changed: vec!["filter-add".to_string()],
+                removed: vec!["testing-krusty".to_string()],
+            }
+        );
+    }
+}
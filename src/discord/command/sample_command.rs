@@ -38,7 +38,7 @@ impl CommandTrait for TestCmd {
     ) -> Result<String, anyhow::Error> {
         tracing::info!("testing-krusty command executed");
 
-        store.add_filter_to_set(1, &"test".to_string())?;
+        store.add_filter_to_set(1, &"test".to_string()).await?;
 
         Ok("purr! 🐾".to_string())
     }
@@ -0,0 +1,1010 @@
+use std::{collections::HashMap, sync::Arc};
+
+use twilight_model::{
+    application::interaction::application_command::{CommandDataOption, CommandOptionValue},
+    gateway::payload::incoming::InteractionCreate,
+    guild::Permissions,
+    id::Id,
+};
+
+use crate::{config, esi, names, persistence, template::Template};
+
+use super::{
+    CommandParams, CommandTrait, ComponentParams, Context, Handler, MAX_CONTENT_LENGTH, User,
+    filter_clear_command::FilterClearCmd, filter_copy_command::FilterCopyCmd,
+    filter_export_command::FilterExportCmd, filter_history_command::FilterHistoryCmd,
+    filter_import_command, filter_list_command::FilterListCmd, filter_move_command::FilterMoveCmd,
+    filter_overview_command::FilterOverviewCmd, filter_preset_command::FilterPresetCmd,
+    filter_remove_command::FilterRemoveCmd, filter_template_command::FilterTemplateCmd,
+    filter_undo_command::FilterUndoCmd, filter_webhook_command::FilterWebhookCmd,
+};
+
+// Interactions as Discord sends them, deserialized into twilight models
+fn interaction(json: &str) -> InteractionCreate {
+    let mut bytes = json.as_bytes().to_vec();
+    InteractionCreate(simd_json::from_slice(&mut bytes).unwrap())
+}
+
+const GUILD_COMMAND: &str = r#"{
+    "id": "1300000000000000001",
+    "application_id": "1200000000000000001",
+    "type": 2,
+    "token": "token",
+    "version": 1,
+    "authorizing_integration_owners": {},
+    "entitlements": [],
+    "guild_id": "1100000000000000001",
+    "channel_id": "1000000000000000001",
+    "channel": {"id": "1000000000000000001", "type": 0, "name": "kills"},
+    "member": {
+        "user": {"id": "900000000000000001", "username": "ava", "discriminator": "0", "avatar": null},
+        "nick": "Ava Tormund",
+        "roles": ["800000000000000001", "800000000000000002"],
+        "joined_at": "2024-01-01T00:00:00.000000+00:00",
+        "deaf": false,
+        "mute": false,
+        "flags": 0,
+        "permissions": "32"
+    },
+    "data": {
+        "id": "1400000000000000001",
+        "name": "filter-add",
+        "type": 1,
+        "options": [
+            {"name": "channel", "type": 7, "value": "1000000000000000002"},
+            {"name": "filter", "type": 3, "value": "region:10000002"}
+        ],
+        "resolved": {
+            "channels": {
+                "1000000000000000002": {"id": "1000000000000000002", "type": 0, "name": "losses", "permissions": "0"}
+            }
+        }
+    }
+}"#;
+
+const COMMAND_WITHOUT_CHANNEL_OPTION: &str = r#"{
+    "id": "1300000000000000002",
+    "application_id": "1200000000000000001",
+    "type": 2,
+    "token": "token",
+    "version": 1,
+    "authorizing_integration_owners": {},
+    "entitlements": [],
+    "guild_id": "1100000000000000001",
+    "channel_id": "1000000000000000001",
+    "channel": {"id": "1000000000000000001", "type": 0, "name": "kills"},
+    "member": {
+        "user": {"id": "900000000000000001", "username": "ava", "discriminator": "0", "avatar": null},
+        "roles": [],
+        "joined_at": "2024-01-01T00:00:00.000000+00:00",
+        "deaf": false,
+        "mute": false,
+        "flags": 0,
+        "permissions": "8"
+    },
+    "data": {
+        "id": "1400000000000000002",
+        "name": "filter-clear",
+        "type": 1
+    }
+}"#;
+
+const DIRECT_MESSAGE_COMMAND: &str = r#"{
+    "id": "1300000000000000003",
+    "application_id": "1200000000000000001",
+    "type": 2,
+    "token": "token",
+    "version": 1,
+    "authorizing_integration_owners": {},
+    "entitlements": [],
+    "channel_id": "1000000000000000003",
+    "user": {"id": "900000000000000001", "username": "ava", "discriminator": "0", "avatar": null},
+    "data": {
+        "id": "1400000000000000001",
+        "name": "filter-add",
+        "type": 1
+    }
+}"#;
+
+const MODAL_SUBMIT: &str = r#"{
+    "id": "1300000000000000004",
+    "application_id": "1200000000000000001",
+    "type": 5,
+    "token": "token",
+    "version": 1,
+    "authorizing_integration_owners": {},
+    "entitlements": [],
+    "guild_id": "1100000000000000001",
+    "channel_id": "1000000000000000001",
+    "data": {
+        "custom_id": "filter-builder:ids:19a2b",
+        "components": [
+            {"type": 18, "id": 1, "component": {"type": 4, "id": 2, "custom_id": "ids", "value": "Jita, 30002187"}}
+        ]
+    }
+}"#;
+
+#[test]
+fn test_parse_command_channel_and_member() {
+    let params = CommandParams::parse_interaction(&interaction(GUILD_COMMAND)).unwrap();
+
+    assert_eq!(params.guild_id, Id::new(1100000000000000001));
+    assert_eq!(params.name, "filter-add");
+
+    // The channel is where the command was used, not the channel option
+    let channel = params.channel.as_ref().unwrap();
+    assert_eq!(channel.id, 1000000000000000001);
+    assert_eq!(channel.name.as_deref(), Some("kills"));
+    assert_eq!(
+        params.get_option_channel_id("channel"),
+        Some(1000000000000000002)
+    );
+    assert_eq!(
+        params.get_option_string("filter").as_deref(),
+        Some("region:10000002")
+    );
+
+    let user = params.user.as_ref().unwrap();
+    assert_eq!(user.id, 900000000000000001);
+    assert_eq!(user.name, "Ava Tormund");
+    assert_eq!(user.roles, vec![800000000000000001, 800000000000000002]);
+    assert_eq!(user.permissions, Some(Permissions::MANAGE_GUILD));
+}
+
+#[test]
+fn test_parse_command_without_channel_option() {
+    let params =
+        CommandParams::parse_interaction(&interaction(COMMAND_WITHOUT_CHANNEL_OPTION)).unwrap();
+
+    assert_eq!(params.channel.as_ref().unwrap().id, 1000000000000000001);
+    assert_eq!(params.get_option_channel_id("channel"), None);
+    assert_eq!(params.user.as_ref().unwrap().name, "ava");
+    assert!(params.options.is_empty());
+}
+
+#[test]
+fn test_parse_command_outside_guild() {
+    let err = CommandParams::parse_interaction(&interaction(DIRECT_MESSAGE_COMMAND)).unwrap_err();
+
+    assert_eq!(err.to_string(), "command not issued in a guild");
+}
+
+#[test]
+fn test_parse_modal_submit() {
+    let params = ComponentParams::parse_interaction(&interaction(MODAL_SUBMIT)).unwrap();
+
+    assert_eq!(params.name, "filter-builder");
+    assert_eq!(params.action, "ids");
+    assert_eq!(params.state, "19a2b");
+    assert_eq!(params.get_field("ids").as_deref(), Some("Jita, 30002187"));
+}
+
+#[test]
+fn test_check_roles() {
+    let params = CommandParams::parse_interaction(&interaction(GUILD_COMMAND)).unwrap();
+    let member = params.user.as_ref();
+
+    assert!(User::check_roles(member, &[]).is_ok());
+    assert!(User::check_roles(member, &[800000000000000002, 800000000000000003]).is_ok());
+
+    let err = User::check_roles(member, &[800000000000000003]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "you need one of these roles to use this command: <@&800000000000000003>"
+    );
+
+    // Administrators don't need the roles
+    let params =
+        CommandParams::parse_interaction(&interaction(COMMAND_WITHOUT_CHANNEL_OPTION)).unwrap();
+    assert!(User::check_roles(params.user.as_ref(), &[800000000000000003]).is_ok());
+    assert!(User::check_roles(None, &[800000000000000003]).is_err());
+}
+
+fn handler(guilds: HashMap<u64, config::GuildConfig>) -> Handler {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let config = config::Config {
+        queue_id: None,
+        redis_url: None,
+        redisq_url: None,
+        zkillboard_url: None,
+        esi_url: None,
+        filters: None,
+        guilds: Some(guilds),
+    };
+    Handler::build(&config, context(), vec![]).unwrap()
+}
+
+fn context() -> Context {
+    let http = reqwest::Client::new();
+    Context {
+        store: Arc::new(persistence::provider::memory::Store::new()),
+        http: http.clone(),
+        discord: Arc::new(twilight_http::Client::new("token".to_string())),
+        names: names::Resolver::new(esi::Client::new(http, "http://localhost"), None),
+    }
+}
+
+// The Discord client needs a runtime to start its rate limiter
+#[tokio::test]
+async fn test_guilds_join_and_leave() {
+    let guild_config = |commands, roles| config::GuildConfig {
+        commands,
+        roles,
+        command_roles: HashMap::new(),
+        log_channel: None,
+    };
+    let handler = handler(HashMap::from([
+        (1, guild_config(config::CommandsEnabled::All, vec![])),
+        (
+            2,
+            guild_config(
+                config::CommandsEnabled::Some(vec!["filter-list".to_string()]),
+                vec![10],
+            ),
+        ),
+        (3, guild_config(config::CommandsEnabled::None, vec![])),
+    ]));
+
+    assert!(handler.guild_commands(Id::new(1)).is_empty());
+
+    let added = handler.add_guild(Id::new(1));
+    assert_eq!(added.len(), handler.commands.len());
+    assert!(added.contains(&"filter-add".to_string()));
+    // Joining again, e.g. after a reconnect, changes nothing
+    assert!(handler.add_guild(Id::new(1)).is_empty());
+
+    assert_eq!(handler.add_guild(Id::new(2)), vec!["filter-list"]);
+    assert_eq!(handler.allowed_roles("filter-list", Id::new(2)), vec![10]);
+    assert!(handler.add_guild(Id::new(3)).is_empty());
+    assert!(handler.add_guild(Id::new(4)).is_empty());
+
+    assert!(handler.enabled("filter-list", Id::new(2)));
+    assert!(!handler.enabled("filter-add", Id::new(2)));
+
+    assert_eq!(handler.remove_guild(Id::new(2)), vec!["filter-list"]);
+    assert!(!handler.enabled("filter-list", Id::new(2)));
+    assert!(handler.allowed_roles("filter-list", Id::new(2)).is_empty());
+    assert!(handler.enabled("filter-list", Id::new(1)));
+    assert_eq!(
+        handler.guild_commands(Id::new(1)).len(),
+        handler.commands.len()
+    );
+}
+
+#[tokio::test]
+async fn test_filter_overview_lists_guild_channels() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let ctx = context();
+    let params = CommandParams::parse_interaction(&interaction(GUILD_COMMAND)).unwrap();
+    let guild_id = params.guild_id.get();
+
+    let overview = FilterOverviewCmd::new();
+    assert_eq!(
+        overview.callback(&ctx, &params).await.unwrap(),
+        "No filters configured in this server"
+    );
+
+    ctx.store
+        .add_filter_to_set(guild_id, 20, "system:30000142")
+        .await
+        .unwrap();
+    ctx.store
+        .add_filter_to_set(guild_id, 10, "system:30002187")
+        .await
+        .unwrap();
+    ctx.store
+        .add_filter_to_set(guild_id, 10, "system:30000142")
+        .await
+        .unwrap();
+    // Other guilds' channels are left out
+    ctx.store
+        .add_filter_to_set(1, 30, "system:30000142")
+        .await
+        .unwrap();
+
+    let output = overview.callback(&ctx, &params).await.unwrap();
+    assert!(output.starts_with("Filters in this server (2 channels):\n**<#10>**\n1. "));
+    assert!(output.contains("Amarr"));
+    assert!(output.contains("**<#20>**\n1. "));
+    assert!(!output.contains("<#30>"));
+}
+
+#[tokio::test]
+async fn test_filter_list_fits_in_a_message() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let ctx = context();
+    let guild_id = 1100000000000000001;
+    ctx.store
+        .set_filter_set(crate::filters::FilterSet {
+            guild_id,
+            channel_id: 10,
+            filters: vec!["system:30000142".to_string(); 200],
+            webhook: None,
+            discord_webhook: None,
+            template: None,
+            disabled: None,
+            config_filters: vec![],
+        })
+        .await
+        .unwrap();
+
+    let output = FilterListCmd::new()
+        .callback(
+            &ctx,
+            &command("filter-list", vec![("channel", channel(10))]),
+        )
+        .await
+        .unwrap();
+
+    assert!(output.len() <= MAX_CONTENT_LENGTH);
+    assert!(output.starts_with("Filters for <#10>:\n**1.** "));
+    assert!(output.contains("more filters\n-# Use the numbers with /filter-remove"));
+    assert!(!output.contains("**200.**"));
+}
+
+#[tokio::test]
+async fn test_filter_numbers_start_at_one() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let ctx = context();
+    ctx.store
+        .add_filter_to_set(1100000000000000001, 10, "system:30000142")
+        .await
+        .unwrap();
+
+    let output = FilterRemoveCmd::new()
+        .callback(
+            &ctx,
+            &command(
+                "filter-remove",
+                vec![
+                    ("channel", channel(10)),
+                    ("index", CommandOptionValue::Integer(0)),
+                ],
+            ),
+        )
+        .await
+        .unwrap();
+    assert_eq!(output, "Invalid filter number 0, filters start at 1");
+
+    let output = FilterMoveCmd::new()
+        .callback(
+            &ctx,
+            &command(
+                "filter-move",
+                vec![
+                    ("channel", channel(10)),
+                    ("from", CommandOptionValue::Integer(1)),
+                    ("to", CommandOptionValue::Integer(-1)),
+                ],
+            ),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        output,
+        "Invalid filter numbers 1 and -1, filters start at 1"
+    );
+
+    assert_eq!(
+        ctx.store.get_channel_filter_set(10).await.unwrap().filters,
+        vec!["system:30000142"]
+    );
+}
+
+// A command used in channel 1000000000000000001 with the given options
+fn command(name: &str, options: Vec<(&str, CommandOptionValue)>) -> CommandParams {
+    let mut params = CommandParams::parse_interaction(&interaction(GUILD_COMMAND)).unwrap();
+    params.name = name.to_string();
+    params.options = options
+        .into_iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                CommandDataOption {
+                    name: name.to_string(),
+                    value,
+                },
+            )
+        })
+        .collect();
+    params
+}
+
+fn channel(id: u64) -> CommandOptionValue {
+    CommandOptionValue::Channel(Id::new(id))
+}
+
+fn string(value: &str) -> CommandOptionValue {
+    CommandOptionValue::String(value.to_string())
+}
+
+#[tokio::test]
+async fn test_filter_copy_and_presets() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let ctx = context();
+    let guild_id = 1100000000000000001;
+    let here: u64 = 1000000000000000001;
+    ctx.store
+        .add_filter_to_set(guild_id, 10, "system:30000142")
+        .await
+        .unwrap();
+    ctx.store
+        .add_filter_to_set(guild_id, 10, "corp:98000001")
+        .await
+        .unwrap();
+    ctx.store
+        .add_filter_to_set(guild_id, 20, "corp:98000001")
+        .await
+        .unwrap();
+
+    // Copies into the channel the command is used in by default
+    let copy = FilterCopyCmd::new();
+    let output = copy
+        .callback(&ctx, &command("filter-copy", vec![("from", channel(10))]))
+        .await
+        .unwrap();
+    assert_eq!(output, format!("Copied 2 filters from <#10> to <#{here}>"));
+
+    // Filters the channel has are skipped
+    let output = copy
+        .callback(
+            &ctx,
+            &command(
+                "filter-copy",
+                vec![("from", channel(10)), ("to", channel(20))],
+            ),
+        )
+        .await
+        .unwrap();
+    assert_eq!(output, "Copied 1 filters from <#10> to <#20>");
+    assert_eq!(
+        ctx.store.get_channel_filter_set(20).await.unwrap().filters,
+        vec!["corp:98000001", "system:30000142"]
+    );
+
+    let output = copy
+        .callback(&ctx, &command("filter-copy", vec![("from", channel(30))]))
+        .await
+        .unwrap();
+    assert_eq!(output, "No filters configured for <#30>");
+
+    let preset = FilterPresetCmd::new();
+    let output = preset
+        .callback(
+            &ctx,
+            &command(
+                "filter-preset",
+                vec![
+                    ("action", string("save")),
+                    ("name", string("Home-Defence")),
+                    ("channel", channel(10)),
+                ],
+            ),
+        )
+        .await
+        .unwrap();
+    assert!(output.starts_with("Saved the 2 filters of <#10> as `home-defence`"));
+
+    let output = preset
+        .callback(
+            &ctx,
+            &command(
+                "filter-preset",
+                vec![
+                    ("action", string("apply")),
+                    ("name", string("home-defence")),
+                    ("channel", channel(30)),
+                    ("replace", CommandOptionValue::Boolean(true)),
+                ],
+            ),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        output,
+        "Filters of <#30> replaced with the 2 filters of `home-defence`"
+    );
+    assert_eq!(
+        ctx.store.get_channel_filter_set(30).await.unwrap().filters,
+        vec!["system:30000142", "corp:98000001"]
+    );
+
+    let output = preset
+        .callback(
+            &ctx,
+            &command(
+                "filter-preset",
+                vec![("action", string("save")), ("name", string("home defence"))],
+            ),
+        )
+        .await
+        .unwrap();
+    assert!(output.starts_with("Invalid preset name"));
+
+    let output = preset
+        .callback(
+            &ctx,
+            &command("filter-preset", vec![("action", string("list"))]),
+        )
+        .await
+        .unwrap();
+    assert!(output.contains("**home-defence** (2 filters)"));
+
+    let output = preset
+        .callback(
+            &ctx,
+            &command(
+                "filter-preset",
+                vec![
+                    ("action", string("delete")),
+                    ("name", string("home-defence")),
+                ],
+            ),
+        )
+        .await
+        .unwrap();
+    assert_eq!(output, "Preset `home-defence` deleted");
+    assert!(
+        ctx.store
+            .list_filter_presets(guild_id)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_filter_export_and_import() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let ctx = context();
+    let guild_id = 1100000000000000001;
+    ctx.store
+        .add_filter_to_set(guild_id, 10, "system:30000142")
+        .await
+        .unwrap();
+    ctx.store
+        .add_filter_to_set(guild_id, 20, "corp:98000001")
+        .await
+        .unwrap();
+    let webhook =
+        crate::filters::DiscordWebhook::from_url("https://discord.com/api/webhooks/30/token").ok();
+    let set_webhook = webhook.clone();
+    ctx.store
+        .update_filter_sets(
+            &[20],
+            Box::new(move |current| {
+                current[0].as_mut().unwrap().discord_webhook = set_webhook;
+                Ok(())
+            }),
+        )
+        .await
+        .unwrap();
+
+    let export = FilterExportCmd::new();
+    let response = export
+        .response(
+            &ctx,
+            &command("filter-export", vec![("format", string("json"))]),
+        )
+        .await
+        .unwrap();
+    let data = response.data.unwrap();
+    assert_eq!(
+        data.content.unwrap(),
+        "Filters of 2 channels, restore them with /filter-import\n-# Webhooks aren't in the file, channels keep theirs when it's imported"
+    );
+    let attachments = data.attachments.unwrap();
+    assert_eq!(
+        attachments[0].filename,
+        format!("krusty-filters-{guild_id}.json")
+    );
+    let file = attachments[0].file.clone();
+
+    // The channel's webhook isn't in the file and stays on import
+    assert!(!String::from_utf8_lossy(&file).contains("token"));
+
+    // Restoring after the filters were changed
+    ctx.store.clear_filter_set(10).await.unwrap();
+    ctx.store
+        .add_filter_to_set(guild_id, 20, "corp:98000002")
+        .await
+        .unwrap();
+
+    let channel_ids = std::collections::HashSet::from([10, 20]);
+    let output = filter_import_command::import(
+        &ctx,
+        guild_id,
+        &file,
+        crate::filters::export::Format::Json,
+        &channel_ids,
+    )
+    .await
+    .unwrap();
+    assert_eq!(output, "Imported the filters of 2 channels: <#10>, <#20>");
+    assert_eq!(
+        ctx.store.get_channel_filter_set(10).await.unwrap().filters,
+        vec!["system:30000142"]
+    );
+    let filter_set = ctx.store.get_channel_filter_set(20).await.unwrap();
+    assert_eq!(filter_set.filters, vec!["corp:98000001"]);
+    assert_eq!(filter_set.discord_webhook, webhook);
+
+    // Nothing changes when a single filter is invalid
+    let yaml = "filter_sets:
+- {guild_id: 1, channel_id: 10, filters: [region:10000002]}
+- {guild_id: 1, channel_id: 20, filters: [nope:1]}
+";
+    let output = filter_import_command::import(
+        &ctx,
+        guild_id,
+        yaml.as_bytes(),
+        crate::filters::export::Format::Yaml,
+        &channel_ids,
+    )
+    .await
+    .unwrap();
+    assert!(output.starts_with("Nothing was imported, fix these problems first:\nchannel 20"));
+    assert_eq!(
+        ctx.store.get_channel_filter_set(10).await.unwrap().filters,
+        vec!["system:30000142"]
+    );
+
+    // Webhooks in the file don't replace the channel's
+    let yaml = "filter_sets:
+- {guild_id: 1, channel_id: 10, filters: [region:10000002], webhook: {url: 'http://127.0.0.1:6379'}}
+";
+    let output = filter_import_command::import(
+        &ctx,
+        guild_id,
+        yaml.as_bytes(),
+        crate::filters::export::Format::Yaml,
+        &channel_ids,
+    )
+    .await
+    .unwrap();
+    assert!(output.ends_with("Webhooks in the file were ignored, set them with /filter-webhook"));
+    let filter_set = ctx.store.get_channel_filter_set(10).await.unwrap();
+    assert_eq!(filter_set.filters, vec!["region:10000002"]);
+    assert_eq!(filter_set.webhook, None);
+}
+
+// A button of the command's response in channel 1000000000000000001
+fn button(name: &str, action: &str, state: &str) -> ComponentParams {
+    ComponentParams {
+        guild_id: Id::new(1100000000000000001),
+        user: None,
+        name: name.to_string(),
+        action: action.to_string(),
+        state: state.to_string(),
+        values: vec![],
+        fields: HashMap::new(),
+    }
+}
+
+#[tokio::test]
+async fn test_filter_clear_keeps_config_filters() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let ctx = context();
+    let guild_id = 1100000000000000001;
+    let filter_set = crate::filters::FilterSet {
+        guild_id,
+        channel_id: 10,
+        filters: vec!["system:30000142".to_string(), "corp:98000001".to_string()],
+        webhook: None,
+        discord_webhook: None,
+        template: None,
+        disabled: None,
+        config_filters: vec!["system:30000142".to_string()],
+    };
+    ctx.store.set_filter_set(filter_set).await.unwrap();
+
+    let output = FilterClearCmd::new()
+        .component(&ctx, &button("filter-clear", "confirm", "10"))
+        .await
+        .unwrap();
+    assert_eq!(
+        output.data.unwrap().content.unwrap(),
+        "All filters in <#10> removed successfully, 1 filters from the bot config were kept, use /filter-undo within 24 hours to restore them"
+    );
+    assert_eq!(
+        ctx.store.get_channel_filter_set(10).await.unwrap().filters,
+        vec!["system:30000142"]
+    );
+
+    // Only the config filters are left, so there's nothing to clear
+    let output = FilterClearCmd::new()
+        .callback(
+            &ctx,
+            &command("filter-clear", vec![("channel", channel(10))]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(output, "No filters to remove from <#10>");
+}
+
+#[tokio::test]
+async fn test_filter_clear_asks_first_and_can_be_undone() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let ctx = context();
+    let guild_id = 1100000000000000001;
+    for filter in ["system:30000142", "corp:98000001"] {
+        ctx.store
+            .add_filter_to_set(guild_id, 10, filter)
+            .await
+            .unwrap();
+    }
+    let clear = FilterClearCmd::new();
+    let undo = FilterUndoCmd::new();
+    let params = command("filter-clear", vec![("channel", channel(10))]);
+
+    let response = clear.response(&ctx, &params).await.unwrap();
+    let data = response.data.unwrap();
+    assert_eq!(
+        data.content.unwrap(),
+        "Remove the 2 filters of <#10>? They can be restored with /filter-undo for 24 hours"
+    );
+    assert_eq!(data.components.unwrap().len(), 1);
+    // Nothing is removed before the confirmation
+    assert_eq!(
+        ctx.store
+            .get_channel_filter_set(10)
+            .await
+            .unwrap()
+            .filters
+            .len(),
+        2
+    );
+
+    let response = clear
+        .component(&ctx, &button("filter-clear", "cancel", "10"))
+        .await
+        .unwrap();
+    assert_eq!(
+        response.data.unwrap().content.unwrap(),
+        "Nothing was removed"
+    );
+    assert_eq!(
+        ctx.store
+            .get_channel_filter_set(10)
+            .await
+            .unwrap()
+            .filters
+            .len(),
+        2
+    );
+
+    clear
+        .component(&ctx, &button("filter-clear", "confirm", "10"))
+        .await
+        .unwrap();
+    assert!(ctx.store.get_channel_filter_set(10).await.is_err());
+
+    let params = command("filter-undo", vec![("channel", channel(10))]);
+    assert_eq!(
+        undo.callback(&ctx, &params).await.unwrap(),
+        "Restored the 2 filters of <#10>"
+    );
+    assert_eq!(
+        ctx.store.get_channel_filter_set(10).await.unwrap().filters,
+        vec!["system:30000142", "corp:98000001"]
+    );
+
+    // A snapshot is only restored once
+    assert_eq!(
+        undo.callback(&ctx, &params).await.unwrap(),
+        "Nothing to restore for <#10>, cleared filters are kept for 24 hours"
+    );
+
+    // Filters and the template set after the clear are kept
+    clear
+        .component(&ctx, &button("filter-clear", "confirm", "10"))
+        .await
+        .unwrap();
+    ctx.store
+        .add_filter_to_set(guild_id, 10, "alliance:99000001")
+        .await
+        .unwrap();
+    FilterTemplateCmd::new()
+        .callback(
+            &ctx,
+            &command(
+                "filter-template",
+                vec![("channel", channel(10)), ("preset", string("compact"))],
+            ),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        undo.callback(&ctx, &params).await.unwrap(),
+        "Restored the 3 filters of <#10>, 1 filters added since the clear were kept"
+    );
+    let filter_set = ctx.store.get_channel_filter_set(10).await.unwrap();
+    assert_eq!(
+        filter_set.filters,
+        vec!["system:30000142", "corp:98000001", "alliance:99000001"]
+    );
+    assert_eq!(filter_set.template, Some(Template::Compact));
+}
+
+#[tokio::test]
+async fn test_filter_changes_are_recorded() {
+    let handler = handler(HashMap::new());
+    let guild_id = 1100000000000000001;
+    let user = User {
+        id: 42,
+        name: "pilot".to_string(),
+        roles: vec![],
+        permissions: None,
+    };
+
+    let (ctx, audited) = handler.audited("filter-add", Some(&user));
+    ctx.store
+        .add_filter_to_set(guild_id, 10, "system:30000142")
+        .await
+        .unwrap();
+    ctx.store
+        .add_filter_to_set(guild_id, 10, "corp:98000001")
+        .await
+        .unwrap();
+    // Writes that leave the filters as they were aren't recorded
+    ctx.store.move_filter(10, 1, 1).await.unwrap();
+
+    let changes = audited.changes();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[1].user_id, 42);
+    assert_eq!(changes[1].command, "filter-add");
+    assert_eq!(changes[1].before, vec!["system:30000142"]);
+    assert_eq!(changes[1].describe(), "+ `corp:98000001`");
+
+    let (ctx, audited) = handler.audited("filter-clear", None);
+    ctx.store.clear_filter_set(10).await.unwrap();
+    assert_eq!(audited.changes()[0].user_name, "unknown");
+    assert_eq!(
+        audited.changes()[0].describe(),
+        "- `system:30000142`\n- `corp:98000001`"
+    );
+
+    // Settings besides the filters are recorded too
+    handler
+        .context
+        .store
+        .add_filter_to_set(guild_id, 30, "system:30000142")
+        .await
+        .unwrap();
+    let (paused, audited) = handler.audited("filter-add", None);
+    paused
+        .store
+        .disable_filter_set(
+            30,
+            crate::filters::Disabled {
+                reason: "webhook gone".to_string(),
+                at: chrono::Utc::now(),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        audited.changes()[0].describe(),
+        "~ deliveries paused: webhook gone"
+    );
+
+    let history = FilterHistoryCmd::new();
+    let output = history
+        .callback(
+            &ctx,
+            &command("filter-history", vec![("channel", channel(10))]),
+        )
+        .await
+        .unwrap();
+    // Newest first
+    assert!(output.starts_with("Latest filter changes of <#10>:\n<t:"));
+    let clear = output.find("used /filter-clear").unwrap();
+    let add = output
+        .find("<@42> used /filter-add\n+ `corp:98000001`")
+        .unwrap();
+    assert!(clear < add);
+
+    let output = history
+        .callback(
+            &ctx,
+            &command("filter-history", vec![("channel", channel(20))]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(output, "No filter changes recorded for <#20>");
+}
+
+#[tokio::test]
+async fn test_filter_webhook_only_changes_the_webhook() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let ctx = context();
+    let guild_id = 1100000000000000001;
+    ctx.store
+        .add_filter_to_set(guild_id, 10, "system:30000142")
+        .await
+        .unwrap();
+    let disabled = crate::filters::Disabled {
+        reason: "channel gone".to_string(),
+        at: chrono::Utc::now(),
+    };
+    ctx.store
+        .disable_filter_set(10, disabled.clone())
+        .await
+        .unwrap();
+
+    let webhook = FilterWebhookCmd::new();
+    let output = webhook
+        .callback(
+            &ctx,
+            &command(
+                "filter-webhook",
+                vec![
+                    ("channel", channel(10)),
+                    ("url", string("https://discord.com/api/webhooks/20/token")),
+                ],
+            ),
+        )
+        .await
+        .unwrap();
+    assert_eq!(output, "Kills for <#10> will be posted through the webhook");
+
+    let filter_set = ctx.store.get_channel_filter_set(10).await.unwrap();
+    assert_eq!(filter_set.filters, vec!["system:30000142"]);
+    assert_eq!(filter_set.discord_webhook.unwrap().id, 20);
+    assert_eq!(filter_set.disabled, Some(disabled));
+
+    webhook
+        .callback(
+            &ctx,
+            &command("filter-webhook", vec![("channel", channel(10))]),
+        )
+        .await
+        .unwrap();
+    let filter_set = ctx.store.get_channel_filter_set(10).await.unwrap();
+    assert_eq!(filter_set.filters, vec!["system:30000142"]);
+    assert_eq!(filter_set.discord_webhook, None);
+}
+
+#[tokio::test]
+async fn test_filter_template_only_changes_the_template() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let ctx = context();
+    let guild_id = 1100000000000000001;
+    ctx.store
+        .add_filter_to_set(guild_id, 10, "system:30000142")
+        .await
+        .unwrap();
+
+    let template = FilterTemplateCmd::new();
+    let output = template
+        .callback(
+            &ctx,
+            &command(
+                "filter-template",
+                vec![("channel", channel(10)), ("preset", string("compact"))],
+            ),
+        )
+        .await
+        .unwrap();
+    assert_eq!(output, "Kills for <#10> will use the compact template");
+
+    let filter_set = ctx.store.get_channel_filter_set(10).await.unwrap();
+    assert_eq!(filter_set.filters, vec!["system:30000142"]);
+    assert_eq!(filter_set.template, Some(Template::Compact));
+
+    // A channel without filters gets a filter set for its template
+    template
+        .callback(
+            &ctx,
+            &command(
+                "filter-template",
+                vec![("channel", channel(20)), ("preset", string("compact"))],
+            ),
+        )
+        .await
+        .unwrap();
+    let filter_set = ctx.store.get_channel_filter_set(20).await.unwrap();
+    assert!(filter_set.filters.is_empty());
+    assert_eq!(filter_set.guild_id, guild_id);
+}
@@ -0,0 +1,597 @@
+use std::collections::HashMap;
+
+use twilight_model::{
+    channel::message::{
+        Embed,
+        embed::{EmbedAuthor, EmbedField, EmbedFooter, EmbedThumbnail},
+    },
+    util::Timestamp,
+};
+
+use crate::{
+    esi, filters, names, static_data,
+    template::{self, Template},
+    zkb,
+};
+
+const IMAGE_SERVER_URL: &str = "https://images.evetech.net";
+const MAX_ALLIANCES: usize = 10;
+
+// Builder turns a killmail into the embed posted for it, using the killmail
+// data itself plus universe data and names from ESI
+#[derive(Clone)]
+pub struct Builder {
+    esi: esi::Client,
+    names: names::Resolver,
+    zkillboard_url: String,
+}
+
+// Location is where a kill happened, with whatever could be resolved about it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Location {
+    pub system_id: u64,
+    pub system_name: Option<String>,
+    pub security: Option<f64>,
+    pub region_id: Option<u64>,
+    pub region_name: Option<String>,
+}
+
+impl Builder {
+    pub fn new(esi: esi::Client, names: names::Resolver, zkillboard_url: &str) -> Self {
+        Self {
+            esi,
+            names,
+            zkillboard_url: zkillboard_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub async fn build(
+        &self,
+        killmail: &zkb::Killmail,
+        kind: Option<filters::KillmailSide>,
+        template: Option<&Template>,
+    ) -> Result<Embed, anyhow::Error> {
+        let Some(data) = &killmail.killmail else {
+            return Err(anyhow::anyhow!("killmail has no data to embed"));
+        };
+
+        let location = self.location(data.system_id).await;
+        let names = Names(self.names.resolve(&entity_ids(data)).await);
+
+        tracing::debug!(
+            kill_id = killmail.kill_id,
+            victim = participant_name(&names, Some(&data.victim)),
+            ship = ship_name(&names, data.victim.ship_type_id),
+            system = location.system_name,
+            "building embed"
+        );
+
+        match template.and_then(|t| t.layout()) {
+            Some(layout) => {
+                Ok(self.templated_embed(killmail, data, &location, &names, kind, layout))
+            }
+            None => Ok(self.new_embed(killmail, data, &location, &names, kind)),
+        }
+    }
+
+    // Resolves the location of a kill, missing ESI data only degrades the embed
+    async fn location(&self, system_id: u64) -> Location {
+        let mut location = Location {
+            system_id,
+            ..Default::default()
+        };
+
+        if let Some(system) = static_data::get_system(system_id) {
+            location.system_name = Some(system.name.clone());
+            location.region_id = Some(system.region_id);
+        }
+
+        match self.esi.system(system_id).await {
+            Ok(system) => {
+                location.system_name = Some(system.name);
+                location.security = Some(system.security_status);
+            }
+            Err(e) => {
+                tracing::warn!(system_id, error = e.to_string(), "failed to resolve system");
+            }
+        }
+
+        if let Some(region_id) = location.region_id {
+            match self.esi.region(region_id).await {
+                Ok(region) => location.region_name = Some(region.name),
+                Err(e) => {
+                    tracing::warn!(region_id, error = e.to_string(), "failed to resolve region");
+                }
+            }
+        }
+
+        location
+    }
+
+    fn new_embed(
+        &self,
+        killmail: &zkb::Killmail,
+        data: &zkb::KillmailData,
+        location: &Location,
+        names: &Names,
+        kind: Option<filters::KillmailSide>,
+    ) -> Embed {
+        let victim = &data.victim;
+        let system_name = location
+            .system_name
+            .clone()
+            .unwrap_or_else(|| format!("System {}", location.system_id));
+
+        let mut fields = vec![
+            field("Victim", self.participant(names, victim)),
+            field("Ship", self.ship(names, victim.ship_type_id)),
+            field("Location", self.location_text(location, &system_name)),
+            field(
+                "Value",
+                format!("{} ISK", format_isk(killmail.zkb.total_value)),
+            ),
+            field("Attackers", data.attackers.len().to_string()),
+        ];
+
+        if let Some(final_blow) = data.attackers.iter().find(|a| a.final_blow) {
+            fields.push(field("Final blow", self.attacker(names, final_blow)));
+        }
+
+        if let Some(top_damage) = data.attackers.iter().max_by_key(|a| a.damage_done) {
+            fields.push(field(
+                "Top damage",
+                format!(
+                    "{} ({} damage)",
+                    self.attacker(names, top_damage),
+                    top_damage.damage_done
+                ),
+            ));
+        }
+
+        let alliances = self.alliances(names, data);
+        if !alliances.is_empty() {
+            fields.push(EmbedField {
+                inline: false,
+                name: "Alliances".to_string(),
+                value: alliances,
+            });
+        }
+
+        Embed {
+            author: victim.character_id.map(|id| EmbedAuthor {
+                icon_url: Some(format!(
+                    "{IMAGE_SERVER_URL}/characters/{id}/portrait?size=64"
+                )),
+                name: names.get("Character", id),
+                proxy_icon_url: None,
+                url: Some(self.link_url("character", id)),
+            }),
+            color: color(kind),
+            description: None,
+            fields,
+            footer: Some(EmbedFooter {
+                icon_url: None,
+                proxy_icon_url: None,
+                text: format!("Kill {}", killmail.kill_id),
+            }),
+            image: None,
+            kind: "rich".to_owned(),
+            provider: None,
+            thumbnail: victim.ship_type_id.map(|id| EmbedThumbnail {
+                height: None,
+                proxy_url: None,
+                url: format!("{IMAGE_SERVER_URL}/types/{id}/render?size=128"),
+                width: None,
+            }),
+            timestamp: Timestamp::from_secs(data.timestamp.timestamp()).ok(),
+            title: Some(format!(
+                "{} destroyed in {system_name}",
+                ship_name(names, victim.ship_type_id)
+            )),
+            url: Some(format!(
+                "{}/kill/{}/",
+                self.zkillboard_url, killmail.kill_id
+            )),
+            video: None,
+        }
+    }
+
+    fn templated_embed(
+        &self,
+        killmail: &zkb::Killmail,
+        data: &zkb::KillmailData,
+        location: &Location,
+        names: &Names,
+        kind: Option<filters::KillmailSide>,
+        (title, description): (&str, Option<&str>),
+    ) -> Embed {
+        let context = self.context(killmail, data, location, names);
+
+        Embed {
+            author: None,
+            color: color(kind),
+            description: description.map(|d| {
+                template::truncate(
+                    template::render(d, &context),
+                    template::MAX_DESCRIPTION_LENGTH,
+                )
+            }),
+            fields: vec![],
+            footer: None,
+            image: None,
+            kind: "rich".to_owned(),
+            provider: None,
+            thumbnail: None,
+            timestamp: Timestamp::from_secs(data.timestamp.timestamp()).ok(),
+            title: Some(template::truncate(
+                template::render(title, &context),
+                template::MAX_TITLE_LENGTH,
+            )),
+            url: Some(format!(
+                "{}/kill/{}/",
+                self.zkillboard_url, killmail.kill_id
+            )),
+            video: None,
+        }
+    }
+
+    // Values for every placeholder in template::PLACEHOLDERS
+    fn context(
+        &self,
+        killmail: &zkb::Killmail,
+        data: &zkb::KillmailData,
+        location: &Location,
+        names: &Names,
+    ) -> HashMap<&'static str, String> {
+        let victim = &data.victim;
+        let final_blow = data.attackers.iter().find(|a| a.final_blow);
+        let top_damage = data.attackers.iter().max_by_key(|a| a.damage_done);
+
+        HashMap::from([
+            ("kill.id", killmail.kill_id.to_string()),
+            (
+                "kill.url",
+                format!("{}/kill/{}/", self.zkillboard_url, killmail.kill_id),
+            ),
+            ("time", data.timestamp.format("%Y-%m-%d %H:%M").to_string()),
+            ("value", format_isk(killmail.zkb.total_value)),
+            (
+                "system",
+                location
+                    .system_name
+                    .clone()
+                    .unwrap_or_else(|| format!("System {}", location.system_id)),
+            ),
+            (
+                "system.security",
+                location.security.map(format_security).unwrap_or_default(),
+            ),
+            (
+                "region",
+                match (&location.region_name, location.region_id) {
+                    (Some(name), _) => name.clone(),
+                    (None, Some(id)) => format!("Region {id}"),
+                    (None, None) => String::new(),
+                },
+            ),
+            ("victim.name", participant_name(names, Some(victim))),
+            (
+                "victim.corp",
+                label(names, "Corporation", victim.corporation_id),
+            ),
+            (
+                "victim.alliance",
+                label(names, "Alliance", victim.alliance_id),
+            ),
+            ("victim.ship", ship_name(names, victim.ship_type_id)),
+            ("attackers", data.attackers.len().to_string()),
+            ("final_blow.name", participant_name(names, final_blow)),
+            (
+                "final_blow.ship",
+                ship_name(names, final_blow.and_then(|a| a.ship_type_id)),
+            ),
+            ("top_damage.name", participant_name(names, top_damage)),
+            (
+                "top_damage.ship",
+                ship_name(names, top_damage.and_then(|a| a.ship_type_id)),
+            ),
+        ])
+    }
+
+    fn link_url(&self, kind: &str, id: u64) -> String {
+        format!("{}/{kind}/{id}/", self.zkillboard_url)
+    }
+
+    fn link(&self, names: &Names, kind: &str, label: &str, id: u64) -> String {
+        format!("[{}]({})", names.get(label, id), self.link_url(kind, id))
+    }
+
+    fn participant(&self, names: &Names, participant: &zkb::Participant) -> String {
+        let mut lines = vec![];
+        if let Some(id) = participant.character_id {
+            lines.push(self.link(names, "character", "Character", id));
+        }
+        if let Some(id) = participant.corporation_id {
+            lines.push(self.link(names, "corporation", "Corporation", id));
+        }
+        if let Some(id) = participant.alliance_id {
+            lines.push(self.link(names, "alliance", "Alliance", id));
+        }
+
+        if lines.is_empty() {
+            return "Unknown".to_string();
+        }
+
+        lines.join("\n")
+    }
+
+    fn attacker(&self, names: &Names, attacker: &zkb::Participant) -> String {
+        let who = match (attacker.character_id, attacker.corporation_id) {
+            (Some(id), _) => self.link(names, "character", "Character", id),
+            (None, Some(id)) => self.link(names, "corporation", "Corporation", id),
+            (None, None) => "NPC".to_string(),
+        };
+
+        match attacker.ship_type_id {
+            Some(_) => format!("{who} in {}", self.ship(names, attacker.ship_type_id)),
+            None => who,
+        }
+    }
+
+    fn ship(&self, names: &Names, ship_type_id: Option<u64>) -> String {
+        match ship_type_id {
+            Some(id) => self.link(names, "ship", "Ship", id),
+            None => "Unknown".to_string(),
+        }
+    }
+
+    fn location_text(&self, location: &Location, system_name: &str) -> String {
+        let mut text = format!(
+            "[{system_name}]({})",
+            self.link_url("system", location.system_id)
+        );
+
+        if let Some(security) = location.security {
+            text.push_str(&format!(" ({})", format_security(security)));
+        }
+
+        if let Some(region_id) = location.region_id {
+            let region_name = location
+                .region_name
+                .clone()
+                .unwrap_or_else(|| format!("Region {region_id}"));
+            text.push_str(&format!(
+                "\n[{region_name}]({})",
+                self.link_url("region", region_id)
+            ));
+        }
+
+        text
+    }
+
+    // Alliances involved in the kill, victim first, then attackers by headcount
+    fn alliances(&self, names: &Names, data: &zkb::KillmailData) -> String {
+        let mut counts: Vec<(u64, usize)> = vec![];
+        for attacker in &data.attackers {
+            let Some(id) = attacker.alliance_id else {
+                continue;
+            };
+            match counts.iter_mut().find(|(a, _)| *a == id) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((id, 1)),
+            }
+        }
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+        let mut alliances = vec![];
+        if let Some(id) = data.victim.alliance_id {
+            alliances.push(format!(
+                "{} (victim)",
+                self.link(names, "alliance", "Alliance", id)
+            ));
+        }
+        for (id, count) in counts.iter().take(MAX_ALLIANCES) {
+            alliances.push(format!(
+                "{} ({count})",
+                self.link(names, "alliance", "Alliance", *id)
+            ));
+        }
+        if counts.len() > MAX_ALLIANCES {
+            alliances.push(format!("and {} more", counts.len() - MAX_ALLIANCES));
+        }
+
+        alliances.join("\n")
+    }
+}
+
+fn field(name: &str, value: String) -> EmbedField {
+    EmbedField {
+        inline: true,
+        name: name.to_string(),
+        value,
+    }
+}
+
+// Names resolved for a killmail, ids that couldn't be resolved are shown as
+// their kind and id
+struct Names(HashMap<u64, names::Name>);
+
+impl Names {
+    fn get(&self, kind: &str, id: u64) -> String {
+        match self.0.get(&id) {
+            Some(name) => name.name.clone(),
+            None => format!("{kind} {id}"),
+        }
+    }
+}
+
+// Every id on the killmail that has a name worth showing
+fn entity_ids(data: &zkb::KillmailData) -> Vec<u64> {
+    std::iter::once(&data.victim)
+        .chain(data.attackers.iter())
+        .flat_map(|p| {
+            [
+                p.character_id,
+                p.corporation_id,
+                p.alliance_id,
+                p.ship_type_id,
+            ]
+        })
+        .flatten()
+        .collect()
+}
+
+fn label(names: &Names, kind: &str, id: Option<u64>) -> String {
+    id.map(|id| names.get(kind, id)).unwrap_or_default()
+}
+
+fn participant_name(names: &Names, participant: Option<&zkb::Participant>) -> String {
+    match participant.map(|p| (p.character_id, p.corporation_id)) {
+        Some((Some(id), _)) => names.get("Character", id),
+        Some((None, Some(id))) => names.get("Corporation", id),
+        Some((None, None)) => "NPC".to_string(),
+        None => "Unknown".to_string(),
+    }
+}
+
+fn ship_name(names: &Names, ship_type_id: Option<u64>) -> String {
+    match ship_type_id {
+        Some(id) => names.get("Ship", id),
+        None => "Unknown ship".to_string(),
+    }
+}
+
+fn color(kind: Option<filters::KillmailSide>) -> Option<u32> {
+    match kind {
+        Some(filters::KillmailSide::Attackers) => Some(0x93c47d),
+        Some(filters::KillmailSide::Victim) => Some(0x990000),
+        None => Some(0xd3d3d3),
+    }
+}
+
+// Formats ISK values the way zKillboard does, e.g. 1.25b or 350.20m
+pub fn format_isk(value: f64) -> String {
+    let units = [(1e12, "t"), (1e9, "b"), (1e6, "m"), (1e3, "k")];
+    for (size, suffix) in units {
+        if value >= size {
+            return format!("{:.2}{suffix}", value / size);
+        }
+    }
+
+    format!("{value:.2}")
+}
+
+// Formats security status the way the game client does, where anything
+// above 0.0 is rounded up to at least 0.1
+pub fn format_security(security: f64) -> String {
+    if security > 0.0 && security < 0.05 {
+        return "0.1".to_string();
+    }
+
+    format!("{:.1}", (security * 10.0).round() / 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_isk() {
+        assert_eq!(format_isk(0.0), "0.00");
+        assert_eq!(format_isk(10_000.0), "10.00k");
+        assert_eq!(format_isk(350_200_000.0), "350.20m");
+        assert_eq!(format_isk(1_250_000_000.0), "1.25b");
+        assert_eq!(format_isk(2_100_000_000_000.0), "2.10t");
+    }
+
+    #[test]
+    fn test_template_context() {
+        let esi = esi::Client::new(reqwest::Client::new(), "http://localhost");
+        let builder = Builder::new(
+            esi.clone(),
+            names::Resolver::new(esi, None),
+            "https://zkillboard.com/",
+        );
+        let killmail = zkb::Killmail {
+            kill_id: 42,
+            zkb: zkb::Zkb {
+                total_value: 350_200_000.0,
+                ..Default::default()
+            },
+            killmail: Some(zkb::KillmailData {
+                system_id: 30000142,
+                victim: zkb::Participant {
+                    corporation_id: Some(98000001),
+                    ship_type_id: Some(587),
+                    ..Default::default()
+                },
+                attackers: vec![zkb::Participant {
+                    character_id: Some(90000001),
+                    ship_type_id: Some(11198),
+                    final_blow: true,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+        };
+        let location = Location {
+            system_id: 30000142,
+            system_name: Some("Jita".to_string()),
+            security: Some(0.9459),
+            ..Default::default()
+        };
+        let names = Names(HashMap::from([(
+            587,
+            names::Name {
+                id: 587,
+                name: "Rifter".to_string(),
+                category: "inventory_type".to_string(),
+            },
+        )]));
+
+        let embed = builder.templated_embed(
+            &killmail,
+            killmail.killmail.as_ref().unwrap(),
+            &location,
+            &names,
+            None,
+            (
+                "{victim.ship} destroyed in {system} ({system.security})",
+                Some("{victim.name} lost {value} ISK to {final_blow.name} in {final_blow.ship}"),
+            ),
+        );
+
+        assert_eq!(
+            embed.title.as_deref(),
+            Some("Rifter destroyed in Jita (0.9)")
+        );
+        assert_eq!(
+            embed.description.as_deref(),
+            Some("Corporation 98000001 lost 350.20m ISK to Character 90000001 in Ship 11198")
+        );
+        assert_eq!(
+            embed.url.as_deref(),
+            Some("https://zkillboard.com/kill/42/")
+        );
+        assert!(embed.fields.is_empty());
+
+        let context = builder.context(
+            &killmail,
+            killmail.killmail.as_ref().unwrap(),
+            &location,
+            &names,
+        );
+        for placeholder in template::PLACEHOLDERS {
+            assert!(context.contains_key(placeholder), "{placeholder} missing");
+        }
+    }
+
+    #[test]
+    fn test_format_security() {
+        assert_eq!(format_security(0.9459), "0.9");
+        assert_eq!(format_security(0.45), "0.5");
+        assert_eq!(format_security(0.01), "0.1");
+        assert_eq!(format_security(0.0), "0.0");
+        assert_eq!(format_security(-0.47), "-0.5");
+    }
+}
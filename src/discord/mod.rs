@@ -1,26 +1,36 @@
 use opentelemetry::trace::Status;
-use std::sync::{
-    Arc,
-    atomic::{AtomicBool, Ordering},
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
 };
 use tokio::task::JoinHandle;
 use twilight_gateway::{Config, Event, EventTypeFlags, Intents, MessageSender, Shard, StreamExt};
 
-use tracing::{Level, Span};
+use tracing::{Instrument, Level, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 use twilight_http::Client;
 use twilight_model::{
-    channel::message::{Embed, MessageFlags, embed::EmbedThumbnail},
+    application::interaction::InteractionType,
+    channel::message::MessageFlags,
+    gateway::payload::incoming::InteractionCreate,
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
     id::{Id, marker::GuildMarker},
     oauth::Application,
     user::CurrentUser,
 };
 
 mod command;
-use crate::{config, filters, zkb};
+pub mod embed;
+use crate::{config, persistence};
 
 static SHUTDOWN: AtomicBool = AtomicBool::new(false);
 
+// Leaves time for the deferral to reach Discord within its 3 second window
+const DEFER_AFTER: Duration = Duration::from_secs(2);
+
 struct Listener {
     _senders: Vec<MessageSender>,
     _tasks: Vec<JoinHandle<()>>,
@@ -39,8 +49,91 @@ impl Listener {
         Ok(current_application)
     }
 
-    async fn listener(mut shard: Shard, client: Arc<Client>, handler: command::Handler) {
-        let wanted_events = EventTypeFlags::INTERACTION_CREATE;
+    async fn respond(
+        client: &Client,
+        application: &Application,
+        interaction: &InteractionCreate,
+        response: &InteractionResponse,
+    ) {
+        if let Err(e) = client
+            .interaction(application.id)
+            .create_response(interaction.id, &interaction.token, response)
+            .await
+        {
+            tracing::error!(
+                error = e.to_string(),
+                interaction_type = ?interaction.kind,
+                "failed to send interaction response"
+            );
+        }
+    }
+
+    // Discord drops interactions that aren't answered within 3 seconds, so
+    // slower responses are deferred and sent as an edit once they're ready
+    async fn respond_in_time(
+        client: &Client,
+        application: &Application,
+        interaction: &InteractionCreate,
+        deferred: InteractionResponseType,
+        response: impl Future<Output = InteractionResponse>,
+    ) {
+        tokio::pin!(response);
+
+        if let Ok(response) = tokio::time::timeout(DEFER_AFTER, &mut response).await {
+            Listener::respond(client, application, interaction, &response).await;
+            return;
+        }
+
+        tracing::debug!(
+            interaction_id = interaction.id.get(),
+            interaction_type = ?interaction.kind,
+            "deferring interaction response"
+        );
+
+        let flags = match deferred {
+            InteractionResponseType::DeferredChannelMessageWithSource => {
+                Some(MessageFlags::EPHEMERAL)
+            }
+            _ => None,
+        };
+        let defer = InteractionResponse {
+            kind: deferred,
+            data: Some(InteractionResponseData {
+                flags,
+                ..Default::default()
+            }),
+        };
+        Listener::respond(client, application, interaction, &defer).await;
+
+        let data = response.await.data.unwrap_or_default();
+
+        if let Err(e) = client
+            .interaction(application.id)
+            .update_response(&interaction.token)
+            .content(data.content.as_deref())
+            .components(data.components.as_deref())
+            .embeds(data.embeds.as_deref())
+            .attachments(data.attachments.as_deref().unwrap_or_default())
+            .await
+        {
+            tracing::error!(
+                error = e.to_string(),
+                interaction_type = ?interaction.kind,
+                "failed to send deferred interaction response"
+            );
+        }
+    }
+
+    async fn listener(
+        mut shard: Shard,
+        client: Arc<Client>,
+        handler: command::Handler,
+        store: Arc<dyn persistence::Store>,
+    ) {
+        let wanted_events = EventTypeFlags::INTERACTION_CREATE
+            | EventTypeFlags::GUILD_CREATE
+            | EventTypeFlags::GUILD_DELETE
+            | EventTypeFlags::CHANNEL_DELETE;
 
         let current_user: CurrentUser = match Listener::get_current_user(&client).await {
             Ok(user) => user,
@@ -71,6 +164,8 @@ impl Listener {
 
             let handler = handler.clone();
             let client = client.clone();
+            let store = store.clone();
+            let current_application = current_application.clone();
             // You'd normally want to spawn a new tokio task for each event and
             // handle the event there to not block the shard.
             tokio::spawn(async move {
@@ -86,6 +181,121 @@ impl Listener {
 
                         tracing::info!(author = msg.author.name, "received message");
                     }
+                    Event::GuildCreate(guild) => {
+                        // Also sent for every guild when the shard connects,
+                        // only guilds without commands yet are registered
+                        let added = handler.add_guild(guild.id());
+                        if added.is_empty() {
+                            return;
+                        }
+
+                        tracing::info!(guild_id = guild.id().get(), commands = ?added, "joined guild");
+
+                        if let Err(e) = command::register_guild_commands(&handler, guild.id()).await
+                        {
+                            tracing::error!(
+                                guild_id = guild.id().get(),
+                                error = e.to_string(),
+                                "failed to register commands in guild"
+                            );
+                        }
+                    }
+                    // Unavailable guilds are outages, not the bot being removed
+                    Event::GuildDelete(guild) if guild.unavailable != Some(true) => {
+                        // Discord drops the guild's commands along with the bot,
+                        // so only the handler has to forget about the guild
+                        let removed = handler.remove_guild(guild.id);
+                        tracing::info!(guild_id = guild.id.get(), commands = ?removed, "left guild");
+
+                        match persistence::remove_guild_filter_sets(store.as_ref(), guild.id.get())
+                            .await
+                        {
+                            Ok(channel_ids) if channel_ids.is_empty() => {}
+                            Ok(channel_ids) => tracing::info!(
+                                guild_id = guild.id.get(),
+                                ?channel_ids,
+                                "removed filter sets of guild"
+                            ),
+                            Err(e) => tracing::error!(
+                                guild_id = guild.id.get(),
+                                error = e.to_string(),
+                                "failed to remove filter sets of guild"
+                            ),
+                        }
+                    }
+                    Event::ChannelDelete(channel) => {
+                        let channel_id = channel.id.get();
+                        // Most channels never had filters
+                        if store.get_channel_filter_set(channel_id).await.is_err() {
+                            return;
+                        }
+
+                        match store.clear_filter_set(channel_id).await {
+                            Ok(_) => {
+                                tracing::info!(channel_id, "removed filter set of deleted channel")
+                            }
+                            Err(e) => tracing::error!(
+                                channel_id,
+                                error = e.to_string(),
+                                "failed to remove filter set of deleted channel"
+                            ),
+                        }
+                    }
+                    Event::InteractionCreate(msg)
+                        if msg.kind == InteractionType::ApplicationCommandAutocomplete =>
+                    {
+                        let choices = match handler.autocomplete(&msg).await {
+                            Ok(choices) => choices,
+                            Err(e) => {
+                                tracing::warn!(
+                                    error = e.to_string(),
+                                    "failed to autocomplete command"
+                                );
+                                vec![]
+                            }
+                        };
+
+                        let response = InteractionResponse {
+                            kind: InteractionResponseType::ApplicationCommandAutocompleteResult,
+                            data: Some(InteractionResponseData {
+                                choices: Some(choices),
+                                ..Default::default()
+                            }),
+                        };
+
+                        Listener::respond(&client, &current_application, &msg, &response).await;
+                    }
+                    Event::InteractionCreate(msg)
+                        if matches!(
+                            msg.kind,
+                            InteractionType::MessageComponent | InteractionType::ModalSubmit
+                        ) =>
+                    {
+                        tracing::trace!(
+                            interaction_id = msg.id.get(),
+                            interaction_type = ?msg.kind,
+                            data = ?msg.data,
+                            "received component"
+                        );
+
+                        let response = async {
+                            match handler.component(&msg).await {
+                                Ok(response) => response,
+                                Err(e) => {
+                                    command::message(format!("Error handling interaction: {e}"))
+                                }
+                            }
+                        };
+
+                        Listener::respond_in_time(
+                            &client,
+                            &current_application,
+                            &msg,
+                            InteractionResponseType::DeferredUpdateMessage,
+                            response,
+                        )
+                        .await;
+                    }
                     Event::InteractionCreate(msg) => {
                         tracing::trace!(
                             interaction_id = msg.id.get(),
@@ -94,30 +304,21 @@ impl Listener {
                             "received command"
                         );
 
-                        let result = handler.handle(&msg).await;
-
-                        let response_text = match result {
-                            Ok(response) => response,
-                            Err(e) => format!("Error handling command: {}", e),
+                        let response = async {
+                            match handler.handle(&msg).await {
+                                Ok(response) => response,
+                                Err(e) => command::message(format!("Error handling command: {e}")),
+                            }
                         };
 
-                        if let Err(e) = client
-                            .interaction(current_application.id)
-                            .create_response(
-                                msg.id,
-                                &msg.token,
-                                &twilight_model::http::interaction::InteractionResponse {
-                                    kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
-                                    data: Some(twilight_model::http::interaction::InteractionResponseData {
-                                        content: Some(response_text),
-                                        flags: Some(MessageFlags::EPHEMERAL),
-                                        ..Default::default()
-                                    }),
-                                }
-                            )
-                            .await {
-                            tracing::error!(error = e.to_string(), "failed to send interaction response");
-                            }
+                        Listener::respond_in_time(
+                            &client,
+                            &current_application,
+                            &msg,
+                            InteractionResponseType::DeferredChannelMessageWithSource,
+                            response,
+                        )
+                        .await;
                     }
                     _ => {
                         tracing::trace!(?event, "received unhandled event");
@@ -131,7 +332,7 @@ impl Listener {
 #[derive(Clone)]
 pub struct Gateway {
     client: Arc<Client>,
-    command_handler: command::Handler,
+    embeds: embed::Builder,
     _listener: Arc<Listener>,
 }
 
@@ -139,12 +340,18 @@ impl Gateway {
     pub async fn build(
         app_config: &config::Config,
         store: Arc<dyn crate::persistence::Store>,
+        embeds: embed::Builder,
+        names: crate::names::Resolver,
+        http: reqwest::Client,
         token: String,
     ) -> Result<Self, anyhow::Error> {
         let client = Arc::new(Client::new(token.clone()));
         let config = Config::new(
             token.clone(),
-            Intents::GUILD_MESSAGES | Intents::MESSAGE_CONTENT | Intents::DIRECT_MESSAGES,
+            Intents::GUILDS
+                | Intents::GUILD_MESSAGES
+                | Intents::MESSAGE_CONTENT
+                | Intents::DIRECT_MESSAGES,
         );
 
         let guild_ids = client
@@ -156,8 +363,14 @@ impl Gateway {
             .map(|g| g.id)
             .collect::<Vec<Id<GuildMarker>>>();
 
-        let command_handler = command::Handler::build(app_config, store, guild_ids)?;
-        command::register_commands(&command_handler, &client).await?;
+        let context = command::Context {
+            store: store.clone(),
+            http,
+            discord: client.clone(),
+            names,
+        };
+        let command_handler = command::Handler::build(app_config, context, guild_ids.clone())?;
+        command::register_commands(&command_handler, &guild_ids).await?;
 
         let shards =
             twilight_gateway::create_recommended(&client, config, |_, builder| builder.build())
@@ -172,6 +385,7 @@ impl Gateway {
                 shard,
                 client.clone(),
                 command_handler.clone(),
+                store.clone(),
             )));
         }
 
@@ -182,111 +396,89 @@ impl Gateway {
 
         Ok(Self {
             client,
-            command_handler,
+            embeds,
             _listener: Arc::new(listener),
         })
     }
 
+    // Commands stay registered, so they keep working while a new instance
+    // starts during a deploy
     pub async fn shutdown(&self) {
-        let _ = self.command_handler.shutdown(&self.client).await;
         SHUTDOWN.store(true, Ordering::Relaxed);
     }
+}
 
-    // #[tracing::instrument(skip(self, parent), parent = parent)]
-    pub async fn embed(
+#[async_trait::async_trait]
+impl crate::notifier::Notifier for Gateway {
+    async fn notify(
         &self,
         parent: &Span,
-        killmail: &zkb::Killmail,
-        channel_id: u64,
-        kind: Option<filters::KillmailSide>,
+        delivery: &crate::pipeline::Delivery,
     ) -> Result<(), anyhow::Error> {
         let span = tracing::span!(Level::INFO, "embedding killmail");
         let _ = span.set_parent(parent.context());
-        let _enter = span.enter();
 
-        let url = format!("https://zkillboard.com/kill/{}/", killmail.kill_id);
-        let meta = Meta::from_url(url)?;
+        let embed = self
+            .embeds
+            .build(
+                &delivery.killmail,
+                delivery.side.clone(),
+                delivery.template.as_ref(),
+            )
+            .instrument(span.clone())
+            .await?;
+        let url = embed.url.clone().unwrap_or_default();
+        let embeds = [embed];
 
         let client = Arc::clone(&self.client);
 
-        let channel_id = Id::new(channel_id);
-        let embed = Self::new_embed(&meta, kind);
+        let result = match &delivery.discord_webhook {
+            Some(webhook) => {
+                let mut request = client
+                    .execute_webhook(Id::new(webhook.id), &webhook.token)
+                    .embeds(&embeds);
+
+                if let Some(username) = &webhook.username {
+                    request = request.username(username);
+                }
+
+                if let Some(avatar_url) = &webhook.avatar_url {
+                    request = request.avatar_url(avatar_url);
+                }
+
+                request.await.map(|_| ())
+            }
+            None => client
+                .create_message(Id::new(delivery.channel_id))
+                .embeds(&embeds)
+                .await
+                .map(|_| ()),
+        };
 
-        match client.create_message(channel_id).embeds(&[embed]).await {
+        match result {
             Ok(_) => {
-                tracing::info!(url = meta.url, "embedded killmail");
+                tracing::info!(
+                    parent: &span,
+                    url,
+                    webhook = delivery.discord_webhook.is_some(),
+                    "embedded killmail"
+                );
+                Ok(())
             }
             Err(e) => {
                 span.set_status(Status::error(format!("failed to send message: {e}")));
-                tracing::error!(error = e.to_string(), "failed to send message");
-            }
-        }
-        Ok(())
-    }
 
-    fn color(kind: Option<filters::KillmailSide>) -> Option<u32> {
-        match kind {
-            Some(filters::KillmailSide::Attackers) => Some(0x93c47d),
-            Some(filters::KillmailSide::Victim) => Some(0x990000),
-            None => Some(0xd3d3d3),
-        }
-    }
+                if let twilight_http::error::ErrorType::Response { status, .. } = e.kind()
+                    && let Some(unreachable) = crate::notifier::Unreachable::from_status(
+                        status.get(),
+                        format!("failed to send message: {e}"),
+                    )
+                {
+                    return Err(unreachable.into());
+                }
 
-    fn new_embed(meta: &Meta, kind: Option<filters::KillmailSide>) -> Embed {
-        Embed {
-            author: None,
-            color: Self::color(kind),
-            description: Some(meta.description.clone()),
-            fields: vec![],
-            footer: None,
-            image: None,
-            kind: "link".to_owned(),
-            provider: None,
-            thumbnail: Some(EmbedThumbnail {
-                height: Some(meta.thumbnail.height as u64),
-                proxy_url: None,
-                url: meta.thumbnail.url.clone(),
-                width: Some(meta.thumbnail.width as u64),
-            }),
-            timestamp: None,
-            title: Some(meta.title.clone()),
-            url: Some(meta.url.clone()),
-            video: None,
+                Err(anyhow::anyhow!("failed to send message: {e}"))
+            }
         }
     }
 }
-
-struct Thumbnail {
-    url: String,
-    width: u32,
-    height: u32,
-}
-
-struct Meta {
-    url: String,
-    title: String,
-    description: String,
-    thumbnail: Thumbnail,
-}
-
-impl Meta {
-    fn from_url(value: String) -> Result<Self, anyhow::Error> {
-        let Ok(meta) = opengraph::scrape(value.clone().as_str(), Default::default()) else {
-            return Err(anyhow::anyhow!("error occured"));
-        };
-
-        Ok(Self {
-            url: value,
-            title: meta.title,
-            description: meta.description.unwrap_or("".to_string()),
-            thumbnail: Thumbnail {
-                url: meta
-                    .images
-                    .first()
-                    .map_or("".to_string(), |img| img.url.clone()),
-                width: 64,
-                height: 64,
-            },
-        })
-    }
-}
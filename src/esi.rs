@@ -0,0 +1,118 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use serde::de::DeserializeOwned;
+
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct SystemInfo {
+    pub system_id: u64,
+    pub name: String,
+    pub security_status: f64,
+    pub constellation_id: u64,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct RegionInfo {
+    pub region_id: u64,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Name {
+    pub id: u64,
+    pub name: String,
+    pub category: String,
+}
+
+// Client is a small ESI client for universe data. Systems and regions never
+// change at runtime, so they are kept in memory once fetched.
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    systems: Arc<RwLock<HashMap<u64, SystemInfo>>>,
+    regions: Arc<RwLock<HashMap<u64, RegionInfo>>>,
+}
+
+impl Client {
+    pub fn new(http: reqwest::Client, base_url: &str) -> Self {
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            systems: Arc::new(RwLock::new(HashMap::new())),
+            regions: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub async fn system(&self, system_id: u64) -> Result<SystemInfo, anyhow::Error> {
+        if let Ok(systems) = self.systems.read()
+            && let Some(system) = systems.get(&system_id)
+        {
+            return Ok(system.clone());
+        }
+
+        let system: SystemInfo = self.get(&format!("/universe/systems/{system_id}/")).await?;
+
+        if let Ok(mut systems) = self.systems.write() {
+            systems.insert(system_id, system.clone());
+        }
+
+        Ok(system)
+    }
+
+    pub async fn region(&self, region_id: u64) -> Result<RegionInfo, anyhow::Error> {
+        if let Ok(regions) = self.regions.read()
+            && let Some(region) = regions.get(&region_id)
+        {
+            return Ok(region.clone());
+        }
+
+        let region: RegionInfo = self.get(&format!("/universe/regions/{region_id}/")).await?;
+
+        if let Ok(mut regions) = self.regions.write() {
+            regions.insert(region_id, region.clone());
+        }
+
+        Ok(region)
+    }
+
+    // Resolves ids of any kind to names, the whole request fails with a 404 if
+    // one of the ids is unknown
+    pub async fn names(&self, ids: &[u64]) -> Result<Vec<Name>, anyhow::Error> {
+        let url = format!("{}/universe/names/", self.base_url);
+        tracing::debug!(url, ids = ids.len(), "resolving names from ESI");
+
+        let response = self
+            .http
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(simd_json::to_string(ids)?)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        match response.json::<Vec<Name>>().await {
+            Ok(names) => Ok(names),
+            Err(e) => {
+                tracing::error!(url, error = e.to_string(), "failed to parse ESI response");
+                Err(anyhow::anyhow!("failed to parse ESI response: {e}"))
+            }
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, anyhow::Error> {
+        let url = format!("{}{path}", self.base_url);
+        tracing::debug!(url, "fetching from ESI");
+
+        let response = self.http.get(&url).send().await?.error_for_status()?;
+        match response.json::<T>().await {
+            Ok(data) => Ok(data),
+            Err(e) => {
+                tracing::error!(url, error = e.to_string(), "failed to parse ESI response");
+                Err(anyhow::anyhow!("failed to parse ESI response: {e}"))
+            }
+        }
+    }
+}
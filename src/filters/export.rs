@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+
+use crate::template::Template;
+
+use super::{Filter, FilterSet};
+
+// Exported filter sets, laid out like the `filters` section of the config so
+// an export can also be pasted there
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct Document {
+    pub filter_sets: Vec<FilterSet>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Yaml,
+    Json,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Self, anyhow::Error> {
+        match name {
+            "yaml" => Ok(Format::Yaml),
+            "json" => Ok(Format::Json),
+            _ => Err(anyhow::anyhow!("unknown export format: {name}")),
+        }
+    }
+
+    // Files are read as YAML unless they're named .json, JSON is valid YAML
+    // anyway
+    pub fn from_filename(filename: &str) -> Self {
+        if filename.to_lowercase().ends_with(".json") {
+            Format::Json
+        } else {
+            Format::Yaml
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Yaml => "yaml",
+            Format::Json => "json",
+        }
+    }
+}
+
+impl Document {
+    // Exports only what a guild admin set up and can import again: webhook
+    // urls, secrets and tokens stay out of the file, and so do webhook targets
+    // from the bot config, which aren't channels of the guild
+    pub fn new(filter_sets: Vec<FilterSet>) -> Self {
+        let mut filter_sets = filter_sets
+            .into_iter()
+            .filter(|filter_set| !filter_set.is_webhook_target())
+            .map(|filter_set| FilterSet {
+                webhook: None,
+                discord_webhook: None,
+                disabled: None,
+                ..filter_set
+            })
+            .collect::<Vec<FilterSet>>();
+        filter_sets.sort_by_key(|filter_set| filter_set.channel_id);
+        Self { filter_sets }
+    }
+
+    // Files from earlier exports can still have webhooks in them
+    pub fn has_webhooks(&self) -> bool {
+        self.filter_sets
+            .iter()
+            .any(|set| set.webhook.is_some() || set.discord_webhook.is_some())
+    }
+
+    pub fn to_string(&self, format: Format) -> Result<String, anyhow::Error> {
+        Ok(match format {
+            Format::Yaml => serde_yaml::to_string(self)?,
+            Format::Json => simd_json::to_string_pretty(self)?,
+        })
+    }
+
+    pub fn parse(data: &[u8], format: Format) -> Result<Self, anyhow::Error> {
+        match format {
+            Format::Yaml => {
+                serde_yaml::from_slice(data).map_err(|e| anyhow::anyhow!("invalid YAML: {e}"))
+            }
+            Format::Json => simd_json::from_slice(&mut data.to_vec())
+                .map_err(|e| anyhow::anyhow!("invalid JSON: {e}")),
+        }
+    }
+
+    // Checks every filter set before anything is imported, so a file with a
+    // single mistake changes nothing. Filter sets are moved to the guild and
+    // have to target one of its channels. Webhooks are never imported: generic
+    // ones only come from the bot config and Discord ones from /filter-webhook.
+    pub fn validate(
+        self,
+        guild_id: u64,
+        channel_ids: &HashSet<u64>,
+    ) -> Result<Vec<FilterSet>, anyhow::Error> {
+        let mut errors = vec![];
+        let mut seen = HashSet::new();
+
+        for filter_set in &self.filter_sets {
+            let channel_id = filter_set.channel_id;
+
+            if !channel_ids.contains(&channel_id) {
+                errors.push(format!("channel {channel_id} is not in this server"));
+            }
+            if !seen.insert(channel_id) {
+                errors.push(format!("channel {channel_id} is listed more than once"));
+            }
+
+            for filter in &filter_set.filters {
+                if let Err(e) = Filter::parse(filter.clone()) {
+                    errors.push(format!(
+                        "channel {channel_id}: invalid filter `{filter}`: {e}"
+                    ));
+                }
+            }
+
+            if let Some(Template::Custom { title, description }) = &filter_set.template
+                && let Err(e) = Template::custom(title.clone(), description.clone())
+            {
+                errors.push(format!("channel {channel_id}: invalid template: {e}"));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(anyhow::anyhow!(errors.join("\n")));
+        }
+
+        Ok(self
+            .filter_sets
+            .into_iter()
+            .map(|filter_set| FilterSet {
+                guild_id,
+                webhook: None,
+                discord_webhook: None,
+                disabled: None,
+                // Only the bot config decides which filters are its own
+                config_filters: vec![],
+                ..filter_set
+            })
+            .collect())
+    }
+}
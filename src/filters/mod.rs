@@ -1,18 +1,98 @@
 use sha2::Digest;
 
-use crate::static_data;
+use crate::{static_data, template::Template};
 
+pub mod export;
 #[cfg(test)]
 pub mod tests;
 
-#[derive(Debug, Default, serde::Deserialize)]
+// Generic webhook targets aren't Discord channels, their filter sets are kept
+// under ids from here on. Discord snowflakes stay below it, so a target never
+// shares a channel's filter set, dedup or unreachable count.
+pub const WEBHOOK_TARGET_IDS: u64 = 1 << 63;
+
+#[derive(Debug, Default, Clone, serde::Deserialize)]
 pub struct Config {
+    #[serde(deserialize_with = "config_filter_sets")]
     pub filter_sets: Vec<FilterSet>,
 
+    // How the filter sets are stored when channels already have filters
+    #[serde(default)]
+    pub import: ImportStrategy,
+
     #[serde(skip)]
     pub compiled_filters: Vec<CompiledFilters>,
 }
 
+// A filter set as written in the bot config. Sets that post to a generic
+// webhook are numbered with webhook_target instead of naming a channel.
+#[derive(serde::Deserialize)]
+struct ConfigFilterSet {
+    guild_id: u64,
+    #[serde(default)]
+    channel_id: Option<u64>,
+    #[serde(default)]
+    webhook_target: Option<u64>,
+    filters: Vec<String>,
+    #[serde(default)]
+    webhook: Option<Webhook>,
+    #[serde(default)]
+    discord_webhook: Option<DiscordWebhook>,
+    #[serde(default)]
+    template: Option<Template>,
+}
+
+impl TryFrom<ConfigFilterSet> for FilterSet {
+    type Error = String;
+
+    fn try_from(set: ConfigFilterSet) -> Result<Self, Self::Error> {
+        let channel_id = match (set.channel_id, set.webhook_target) {
+            (Some(channel_id), None) if channel_id >= WEBHOOK_TARGET_IDS => {
+                return Err(format!("channel {channel_id} is not a Discord channel id"));
+            }
+            (Some(channel_id), None) if set.webhook.is_some() => {
+                return Err(format!(
+                    "filter set {channel_id} posts to a webhook, number it with webhook_target instead of channel_id"
+                ));
+            }
+            (Some(channel_id), None) => channel_id,
+            (None, Some(target)) if target >= WEBHOOK_TARGET_IDS => {
+                return Err(format!("webhook target {target} is too large"));
+            }
+            (None, Some(target)) if set.webhook.is_none() || set.discord_webhook.is_some() => {
+                return Err(format!(
+                    "webhook target {target} needs a webhook and no discord_webhook"
+                ));
+            }
+            (None, Some(target)) => WEBHOOK_TARGET_IDS | target,
+            _ => {
+                return Err("filter sets have either a channel_id or a webhook_target".to_string());
+            }
+        };
+
+        Ok(FilterSet {
+            guild_id: set.guild_id,
+            channel_id,
+            filters: set.filters,
+            webhook: set.webhook,
+            discord_webhook: set.discord_webhook,
+            template: set.template,
+            disabled: None,
+            config_filters: vec![],
+        })
+    }
+}
+
+fn config_filter_sets<'de, D>(deserializer: D) -> Result<Vec<FilterSet>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    <Vec<ConfigFilterSet> as serde::Deserialize>::deserialize(deserializer)?
+        .into_iter()
+        .map(|set| FilterSet::try_from(set).map_err(serde::de::Error::custom))
+        .collect()
+}
+
 #[derive(Clone, Debug)]
 pub struct CompiledFilters {
     pub channel_id: u64,
@@ -20,7 +100,8 @@ pub struct CompiledFilters {
     pub filters: Vec<Filter>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum KillmailSide {
     Victim,
     Attackers,
@@ -114,9 +195,224 @@ pub struct FilterSet {
     pub guild_id: u64,
     pub channel_id: u64,
     pub filters: Vec<String>,
+    // When set, matched killmails are posted to this webhook instead of the channel
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<Webhook>,
+    // When set, matched killmails are posted to the channel through this Discord
+    // webhook instead of as a bot message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discord_webhook: Option<DiscordWebhook>,
+    // Layout of the embeds posted for this channel, the full embed when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<Template>,
+    // Set when deliveries kept failing because the channel is gone or the bot
+    // lost access to it, matched killmails aren't sent while it is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<Disabled>,
+    // Filters imported from the bot config, they can't be changed from
+    // Discord. Every other filter was added with a command.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub config_filters: Vec<String>,
+}
+
+// ImportStrategy decides what happens to the filters of a channel when the
+// config has a filter set for it too
+#[derive(Debug, Default, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportStrategy {
+    // The config's filter set replaces the channel's, filters added with
+    // commands are dropped
+    Overwrite,
+    // Filters added with commands are kept next to the config's, filters
+    // dropped from the config are removed
+    #[default]
+    Merge,
+    // The config's filter set is only stored for channels without filters
+    OnlyIfMissing,
+}
+
+impl ImportStrategy {
+    // The filter set to store for a config filter set, None when the
+    // channel's filter set stays as it is
+    pub fn apply(&self, existing: Option<FilterSet>, imported: &FilterSet) -> Option<FilterSet> {
+        let from_config = FilterSet {
+            config_filters: imported.filters.clone(),
+            disabled: None,
+            ..imported.clone()
+        };
+
+        let existing = match (self, existing) {
+            (_, None) | (ImportStrategy::Overwrite, Some(_)) => return Some(from_config),
+            (ImportStrategy::OnlyIfMissing, Some(_)) => return None,
+            (ImportStrategy::Merge, Some(existing)) => existing,
+        };
+
+        // Config filters come first, in the config's order
+        let mut filters = from_config.filters.clone();
+        for filter in &existing.filters {
+            if !existing.config_filters.contains(filter) && !filters.contains(filter) {
+                filters.push(filter.clone());
+            }
+        }
+
+        // A paused channel stays paused unless its filters changed
+        let disabled = match filters == existing.filters {
+            true => existing.disabled,
+            false => None,
+        };
+
+        // Generic webhooks only come from the config, so one removed there
+        // stops getting killmails. Discord webhooks and templates can also be
+        // set with commands and are kept unless the config sets its own.
+        Some(FilterSet {
+            filters,
+            webhook: from_config.webhook,
+            discord_webhook: from_config.discord_webhook.or(existing.discord_webhook),
+            template: from_config.template.or(existing.template),
+            disabled,
+            ..from_config
+        })
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+pub struct Disabled {
+    pub reason: String,
+    pub at: chrono::DateTime<chrono::Utc>,
+}
+
+// A named list of filters saved in a guild, so the same filters can be
+// applied to several channels
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+pub struct FilterPreset {
+    pub guild_id: u64,
+    pub name: String,
+    pub filters: Vec<String>,
+}
+
+impl FilterPreset {
+    pub const MAX_NAME_LENGTH: usize = 32;
+
+    // Names are typed in commands, so they're kept short and simple
+    pub fn validate_name(name: &str) -> Result<(), anyhow::Error> {
+        if name.is_empty() || name.len() > Self::MAX_NAME_LENGTH {
+            return Err(anyhow::anyhow!(
+                "preset names have 1 to {} characters",
+                Self::MAX_NAME_LENGTH
+            ));
+        }
+
+        if !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            return Err(anyhow::anyhow!(
+                "preset names only have lowercase letters, digits, - and _"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// A channel's filter set from before it was cleared, kept for a while so the
+// clear can be undone
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+pub struct FilterSnapshot {
+    pub filter_set: FilterSet,
+    pub taken_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl FilterSnapshot {
+    pub const RETENTION: chrono::TimeDelta = chrono::TimeDelta::hours(24);
+
+    pub fn new(filter_set: FilterSet) -> Self {
+        Self {
+            filter_set,
+            taken_at: chrono::Utc::now(),
+        }
+    }
+
+    pub fn expires_at(&self) -> chrono::DateTime<chrono::Utc> {
+        self.taken_at + Self::RETENTION
+    }
+
+    pub fn expired(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        now >= self.expires_at()
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+pub struct Webhook {
+    pub url: String,
+    // Secret used to sign the payload, sent as an HMAC-SHA256 signature header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+pub struct DiscordWebhook {
+    pub id: u64,
+    pub token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+}
+
+impl DiscordWebhook {
+    // Parses a webhook URL as copied from Discord's channel settings,
+    // e.g. https://discord.com/api/webhooks/{id}/{token}
+    pub fn from_url(url: &str) -> Result<Self, anyhow::Error> {
+        let path = url
+            .trim()
+            .trim_end_matches('/')
+            .split_once("/api/webhooks/")
+            .map(|(_, path)| path)
+            .ok_or_else(|| anyhow::anyhow!("not a Discord webhook URL: {url}"))?;
+
+        let (id, token) = path
+            .split_once('/')
+            .ok_or_else(|| anyhow::anyhow!("missing token in Discord webhook URL"))?;
+
+        let id = id
+            .parse::<u64>()
+            .map_err(|e| anyhow::anyhow!("failed to parse webhook id: {e}"))?;
+
+        if token.is_empty() || token.contains('/') {
+            return Err(anyhow::anyhow!("invalid token in Discord webhook URL"));
+        }
+
+        Ok(Self {
+            id,
+            token: token.to_string(),
+            username: None,
+            avatar_url: None,
+        })
+    }
 }
 
 impl FilterSet {
+    // Filter sets of generic webhook targets from the bot config, they don't
+    // belong to a channel of any guild
+    pub fn is_webhook_target(&self) -> bool {
+        self.channel_id >= WEBHOOK_TARGET_IDS
+    }
+
+    // Carries the config filters of the channel's current filter set over,
+    // for changes from Discord that replace all of a channel's filters
+    pub fn keep_config_filters(&mut self, current: &FilterSet) {
+        let mut filters = current.config_filters.clone();
+        filters.extend(
+            self.filters
+                .drain(..)
+                .filter(|filter| !current.config_filters.contains(filter)),
+        );
+
+        self.filters = filters;
+        self.config_filters = current.config_filters.clone();
+    }
+
     pub fn hash(&self) -> String {
         let mut hasher = sha2::Sha512::new();
 
@@ -153,6 +449,24 @@ pub enum FilterKind {
 }
 
 impl FilterKind {
+    // Noun used when describing filters of this kind
+    pub fn noun(&self, plural: bool) -> &'static str {
+        match (self, plural) {
+            (FilterKind::Region, false) => "region",
+            (FilterKind::Region, true) => "regions",
+            (FilterKind::System, false) => "system",
+            (FilterKind::System, true) => "systems",
+            (FilterKind::Ship, false) => "ship",
+            (FilterKind::Ship, true) => "ships",
+            (FilterKind::Character, false) => "character",
+            (FilterKind::Character, true) => "characters",
+            (FilterKind::Corporation, false) => "corporation",
+            (FilterKind::Corporation, true) => "corporations",
+            (FilterKind::Alliance, false) => "alliance",
+            (FilterKind::Alliance, true) => "alliances",
+        }
+    }
+
     fn parse(s: &str) -> Result<Self, anyhow::Error> {
         let val = match s {
             "region" => FilterKind::Region,
@@ -196,8 +510,14 @@ pub struct Filter {
 }
 
 impl Filter {
-    fn parse(s: String) -> Result<Self, anyhow::Error> {
+    pub fn parse(s: String) -> Result<Self, anyhow::Error> {
         let parts: Vec<&str> = s.split(':').collect();
+        if parts.len() < 2 || parts.len() > 3 {
+            return Err(anyhow::anyhow!(
+                "filter must look like kind:ids or kind:ids:properties"
+            ));
+        }
+
         let kind = match FilterKind::parse(parts[0]) {
             Ok(k) => k,
             Err(e) => {
@@ -242,6 +562,50 @@ impl Filter {
             properties,
         })
     }
+
+    pub fn kind(&self) -> &FilterKind {
+        &self.kind
+    }
+
+    pub fn ids(&self) -> &[u64] {
+        &self.ids
+    }
+
+    // Plain-language meaning of the filter, e.g. "Exclude losses of corporation
+    // Krusty Industries", with names looked up through `name`
+    pub fn describe(&self, name: impl Fn(&FilterKind, u64) -> String) -> String {
+        let noun = self.kind.noun(self.ids.len() > 1);
+        let names = self
+            .ids
+            .iter()
+            .map(|id| name(&self.kind, *id))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        let losses = self.properties.contains(&FilterProperty::Losses);
+        let kills = self.properties.contains(&FilterProperty::Kills);
+
+        let subject = match self.kind {
+            FilterKind::Region | FilterKind::System => format!("kills in {noun} {names}"),
+            _ => match (losses, kills) {
+                (true, false) => format!("losses of {noun} {names}"),
+                (false, true) => format!("kills by {noun} {names}"),
+                (true, true) => format!("nothing, loss and kill cancel out for {noun} {names}"),
+                (false, false) => format!("kills and losses of {noun} {names}"),
+            },
+        };
+
+        let mut description = match self.properties.contains(&FilterProperty::Exclude) {
+            true => format!("Exclude {subject}"),
+            false => format!("Show {subject}"),
+        };
+
+        if self.properties.contains(&FilterProperty::WithNPC) {
+            description.push_str(", including NPC kills");
+        }
+
+        description
+    }
 }
 
 impl Filter {
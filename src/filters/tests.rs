@@ -34,6 +34,341 @@ mod parser_tests {
         assert!(filter.properties.contains(&FilterProperty::Losses));
         assert!(filter.properties.contains(&FilterProperty::Exclude));
     }
+
+    #[test]
+    fn test_invalid_filters() {
+        assert!(Filter::parse(String::from("region")).is_err());
+        assert!(Filter::parse(String::from("planet:40009077")).is_err());
+        assert!(Filter::parse(String::from("corp:jita")).is_err());
+        assert!(Filter::parse(String::from("corp:98000001:loss:exclude")).is_err());
+        assert!(Filter::parse(String::from("corp:98000001:sometimes")).is_err());
+    }
+
+    #[test]
+    fn test_describe_filter() {
+        let name = |_: &FilterKind, id: u64| {
+            match id {
+                10000002 => "The Forge",
+                30000142 => "Jita",
+                30002187 => "Amarr",
+                98000001 => "Krusty Industries",
+                99000001 => "Krusty Alliance",
+                _ => "Unknown",
+            }
+            .to_string()
+        };
+        let describe = |filter: &str| {
+            Filter::parse(filter.to_string())
+                .expect("expected to parse filter")
+                .describe(name)
+        };
+
+        assert_eq!(
+            describe("region:10000002"),
+            "Show kills in region The Forge"
+        );
+        assert_eq!(
+            describe("system:30000142,30002187:exclude"),
+            "Exclude kills in systems Jita, Amarr"
+        );
+        assert_eq!(
+            describe("corp:98000001:loss,exclude"),
+            "Exclude losses of corporation Krusty Industries"
+        );
+        assert_eq!(
+            describe("alliance:99000001:kills"),
+            "Show kills by alliance Krusty Alliance"
+        );
+        assert_eq!(
+            describe("character:90000001:with_npc"),
+            "Show kills and losses of character Unknown, including NPC kills"
+        );
+    }
+}
+
+#[cfg(test)]
+mod discord_webhook_tests {
+    use crate::filters::*;
+
+    #[test]
+    fn test_discord_webhook_from_url() {
+        let webhook =
+            DiscordWebhook::from_url("https://discord.com/api/webhooks/1234567890/abc-DEF_123")
+                .expect("expected to parse webhook url");
+        assert_eq!(webhook.id, 1234567890);
+        assert_eq!(webhook.token, "abc-DEF_123");
+        assert_eq!(webhook.username, None);
+
+        let webhook =
+            DiscordWebhook::from_url("https://canary.discordapp.com/api/webhooks/42/token/")
+                .expect("expected to parse webhook url");
+        assert_eq!(webhook.id, 42);
+        assert_eq!(webhook.token, "token");
+    }
+
+    #[test]
+    fn test_discord_webhook_from_invalid_url() {
+        assert!(DiscordWebhook::from_url("https://example.com/hooks/1/token").is_err());
+        assert!(DiscordWebhook::from_url("https://discord.com/api/webhooks/abc/token").is_err());
+        assert!(DiscordWebhook::from_url("https://discord.com/api/webhooks/42").is_err());
+        assert!(DiscordWebhook::from_url("https://discord.com/api/webhooks/42/a/b").is_err());
+    }
+}
+
+#[cfg(test)]
+mod preset_tests {
+    use crate::filters::*;
+
+    #[test]
+    fn test_preset_names() {
+        assert!(FilterPreset::validate_name("home-defence").is_ok());
+        assert!(FilterPreset::validate_name("jita_4_4").is_ok());
+        assert!(FilterPreset::validate_name("").is_err());
+        assert!(FilterPreset::validate_name("Home Defence").is_err());
+        assert!(FilterPreset::validate_name(&"a".repeat(33)).is_err());
+    }
+}
+
+#[cfg(test)]
+mod export_tests {
+    use std::collections::HashSet;
+
+    use crate::filters::export::*;
+    use crate::filters::*;
+
+    fn filter_set(guild_id: u64, channel_id: u64, filters: &[&str]) -> FilterSet {
+        FilterSet {
+            guild_id,
+            channel_id,
+            filters: filters.iter().map(|f| f.to_string()).collect(),
+            webhook: None,
+            discord_webhook: None,
+            template: None,
+            disabled: None,
+            config_filters: vec![],
+        }
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let mut with_template = filter_set(1, 10, &["system:30000142"]);
+        with_template.template = Some(Template::Compact);
+        let document = Document::new(vec![filter_set(1, 20, &["corp:98000001"]), with_template]);
+
+        for format in [Format::Yaml, Format::Json] {
+            let exported = document.to_string(format).unwrap();
+            let parsed = Document::parse(exported.as_bytes(), format).unwrap();
+            assert_eq!(parsed, document);
+            // Sorted by channel
+            assert_eq!(parsed.filter_sets[0].channel_id, 10);
+        }
+
+        let yaml = document.to_string(Format::Yaml).unwrap();
+        assert!(yaml.starts_with("filter_sets:\n- guild_id: 1\n  channel_id: 10\n"));
+        assert_eq!(Format::from_filename("krusty-filters.JSON"), Format::Json);
+        assert_eq!(Format::from_filename("krusty-filters.yml"), Format::Yaml);
+
+        // Webhook secrets and tokens stay out of the file, and webhook targets
+        // aren't channels to import to
+        let mut with_webhooks = filter_set(1, 30, &["corp:98000001"]);
+        with_webhooks.webhook = Some(Webhook {
+            url: "https://tools.example.com/hooks/kills".to_string(),
+            secret: Some("secret".to_string()),
+        });
+        with_webhooks.discord_webhook =
+            DiscordWebhook::from_url("https://discord.com/api/webhooks/42/token").ok();
+        let target = filter_set(1, WEBHOOK_TARGET_IDS | 1, &["corp:98000001"]);
+        let document = Document::new(vec![with_webhooks, target]);
+        assert_eq!(
+            document.filter_sets,
+            vec![filter_set(1, 30, &["corp:98000001"])]
+        );
+        assert!(!document.to_string(Format::Yaml).unwrap().contains("secret"));
+    }
+
+    #[test]
+    fn test_import_validation() {
+        let channel_ids = HashSet::from([10, 20]);
+
+        // Imported sets are moved to the guild and resumed
+        let mut disabled = filter_set(99, 10, &["system:30000142"]);
+        disabled.disabled = Some(Disabled {
+            reason: "gone".to_string(),
+            at: chrono::Utc::now(),
+        });
+        let imported = Document {
+            filter_sets: vec![disabled],
+        }
+        .validate(1, &channel_ids)
+        .unwrap();
+        assert_eq!(imported, vec![filter_set(1, 10, &["system:30000142"])]);
+
+        // Webhooks only come from the bot config and /filter-webhook, files
+        // from earlier exports can still have them
+        let mut with_webhooks = filter_set(1, 20, &["corp:98000001"]);
+        with_webhooks.webhook = Some(Webhook {
+            url: "http://169.254.169.254/latest/meta-data".to_string(),
+            secret: None,
+        });
+        with_webhooks.discord_webhook =
+            DiscordWebhook::from_url("https://discord.com/api/webhooks/42/token").ok();
+        let document = Document {
+            filter_sets: vec![with_webhooks],
+        };
+        assert!(document.has_webhooks());
+        let imported = document.validate(1, &channel_ids).unwrap();
+        assert_eq!(imported, vec![filter_set(1, 20, &["corp:98000001"])]);
+
+        let err = Document::new(vec![
+            filter_set(1, 10, &["system:30000142", "nope:1"]),
+            filter_set(1, 10, &[]),
+            filter_set(1, 30, &["corp:98000001"]),
+        ])
+        .validate(1, &channel_ids)
+        .unwrap_err()
+        .to_string();
+        let errors = err.lines().collect::<Vec<_>>();
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("channel 10: invalid filter `nope:1`"));
+        assert_eq!(errors[1], "channel 10 is listed more than once");
+        assert_eq!(errors[2], "channel 30 is not in this server");
+
+        assert!(Document::parse(b"filter_sets: nope", Format::Yaml).is_err());
+    }
+}
+
+#[cfg(test)]
+mod import_tests {
+    use crate::filters::*;
+
+    fn filter_set(filters: &[&str], config_filters: &[&str]) -> FilterSet {
+        FilterSet {
+            guild_id: 1,
+            channel_id: 10,
+            filters: filters.iter().map(|f| f.to_string()).collect(),
+            webhook: None,
+            discord_webhook: None,
+            template: None,
+            disabled: None,
+            config_filters: config_filters.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_import_strategies() {
+        let config = filter_set(&["system:30000142", "corp:98000002"], &[]);
+        // Imported before with corp:98000001, which was dropped from the
+        // config since, and region:10000002 added with /filter-add
+        let existing = filter_set(
+            &["system:30000142", "corp:98000001", "region:10000002"],
+            &["system:30000142", "corp:98000001"],
+        );
+        let imported = filter_set(
+            &["system:30000142", "corp:98000002"],
+            &["system:30000142", "corp:98000002"],
+        );
+
+        for strategy in [
+            ImportStrategy::Overwrite,
+            ImportStrategy::Merge,
+            ImportStrategy::OnlyIfMissing,
+        ] {
+            assert_eq!(strategy.apply(None, &config), Some(imported.clone()));
+        }
+
+        assert_eq!(
+            ImportStrategy::Overwrite.apply(Some(existing.clone()), &config),
+            Some(imported)
+        );
+        assert_eq!(
+            ImportStrategy::Merge.apply(Some(existing.clone()), &config),
+            Some(filter_set(
+                &["system:30000142", "corp:98000002", "region:10000002"],
+                &["system:30000142", "corp:98000002"],
+            ))
+        );
+        assert_eq!(
+            ImportStrategy::OnlyIfMissing.apply(Some(existing), &config),
+            None
+        );
+    }
+
+    #[test]
+    fn test_merge_takes_the_webhook_from_the_config() {
+        let config = filter_set(&["system:30000142"], &[]);
+        let mut existing = filter_set(&["system:30000142"], &["system:30000142"]);
+        existing.webhook = Some(Webhook {
+            url: "https://tools.example.com/hooks/kills".to_string(),
+            secret: None,
+        });
+        existing.template = Some(Template::Compact);
+
+        let merged = ImportStrategy::Merge
+            .apply(Some(existing), &config)
+            .unwrap();
+        assert_eq!(merged.webhook, None);
+        // Templates can be set with /filter-template too
+        assert_eq!(merged.template, Some(Template::Compact));
+    }
+
+    #[test]
+    fn test_import_strategy_from_config() {
+        let config: Config =
+            serde_yaml::from_str("import: only-if-missing\nfilter_sets: []").unwrap();
+        assert_eq!(config.import, ImportStrategy::OnlyIfMissing);
+
+        let config: Config = serde_yaml::from_str("filter_sets: []").unwrap();
+        assert_eq!(config.import, ImportStrategy::Merge);
+    }
+
+    #[test]
+    fn test_webhook_targets_from_config() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+filter_sets:
+  - guild_id: 1
+    channel_id: 10
+    filters: ["region:10000002"]
+  - guild_id: 1
+    webhook_target: 10
+    webhook:
+      url: "https://tools.example.com/hooks/kills"
+    filters: ["region:10000002"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.filter_sets[0].channel_id, 10);
+        assert!(!config.filter_sets[0].is_webhook_target());
+        // Same number, but not the channel's filter set
+        assert_eq!(config.filter_sets[1].channel_id, WEBHOOK_TARGET_IDS | 10);
+        assert!(config.filter_sets[1].is_webhook_target());
+
+        for yaml in [
+            // Webhook sets don't name a channel
+            "{guild_id: 1, channel_id: 10, webhook: {url: x}, filters: []}",
+            "{guild_id: 1, webhook_target: 10, filters: []}",
+            "{guild_id: 1, channel_id: 10, webhook_target: 10, webhook: {url: x}, filters: []}",
+            "{guild_id: 1, filters: []}",
+        ] {
+            let yaml = format!("filter_sets: [{yaml}]");
+            assert!(serde_yaml::from_str::<Config>(&yaml).is_err(), "{yaml}");
+        }
+    }
+
+    #[test]
+    fn test_keep_config_filters() {
+        let current = filter_set(&["system:30000142", "corp:98000001"], &["system:30000142"]);
+        let mut replacement = filter_set(&["region:10000002", "system:30000142"], &[]);
+
+        replacement.keep_config_filters(&current);
+        assert_eq!(
+            replacement,
+            filter_set(
+                &["system:30000142", "region:10000002"],
+                &["system:30000142"]
+            )
+        );
+    }
 }
 
 #[cfg(test)]
@@ -702,10 +1037,16 @@ mod config_tests {
                 String::from("character:600000:exclude"),
                 String::from("ship:12747"),
             ],
+            webhook: None,
+            discord_webhook: None,
+            template: None,
+            disabled: None,
+            config_filters: vec![],
         };
 
         let mut config = Config {
             filter_sets: vec![filter_set],
+            import: ImportStrategy::default(),
             compiled_filters: vec![],
         };
 
@@ -713,6 +1054,7 @@ mod config_tests {
             kill_id: 1,
             zkb: Zkb {
                 href: "".to_string(),
+                ..Default::default()
             },
             killmail: Some(KillmailData {
                 system_id: 30000142, // system in region 10000002
@@ -744,6 +1086,11 @@ mod config_tests {
                     String::from("ship:12747"),
                     String::from("corp:600000"),
                 ],
+                webhook: None,
+                discord_webhook: None,
+                template: None,
+                disabled: None,
+                config_filters: vec![],
             }
         }
 
@@ -753,6 +1100,7 @@ mod config_tests {
                 create_filter_set(2),
                 create_filter_set(3),
             ],
+            import: ImportStrategy::default(),
             compiled_filters: vec![],
         };
 
@@ -760,6 +1108,7 @@ mod config_tests {
             kill_id: 1,
             zkb: Zkb {
                 href: "".to_string(),
+                ..Default::default()
             },
             killmail: Some(KillmailData {
                 system_id: 30000142, // system in region 10000002
@@ -783,26 +1132,47 @@ mod config_tests {
                 guild_id: 100,
                 channel_id: 10,
                 filters: vec![String::from("corp:100000")],
+                webhook: None,
+                discord_webhook: None,
+                template: None,
+                disabled: None,
+                config_filters: vec![],
             },
             FilterSet {
                 guild_id: 100,
                 channel_id: 20,
                 filters: vec![String::from("ship:20002:losses")], // Titan losses
+                webhook: None,
+                discord_webhook: None,
+                template: None,
+                disabled: None,
+                config_filters: vec![],
             },
             FilterSet {
                 guild_id: 100,
                 channel_id: 30,
                 filters: vec![String::from("system:30000142")], // Jita kills
+                webhook: None,
+                discord_webhook: None,
+                template: None,
+                disabled: None,
+                config_filters: vec![],
             },
             FilterSet {
                 guild_id: 100,
                 channel_id: 40,
                 filters: vec![String::from("ship:670"), String::from("system:30000142")], // Pods in The Forge
+                webhook: None,
+                discord_webhook: None,
+                template: None,
+                disabled: None,
+                config_filters: vec![],
             },
         ];
 
         Config {
             filter_sets,
+            import: ImportStrategy::default(),
             compiled_filters: vec![],
         }
     }
@@ -815,6 +1185,7 @@ mod config_tests {
             kill_id: 1,
             zkb: Zkb {
                 href: "".to_string(),
+                ..Default::default()
             },
             killmail: Some(KillmailData {
                 attackers: vec![crate::zkb::Participant {
@@ -837,6 +1208,7 @@ mod config_tests {
             kill_id: 2,
             zkb: Zkb {
                 href: "".to_string(),
+                ..Default::default()
             },
             killmail: Some(KillmailData {
                 victim: crate::zkb::Participant {
@@ -859,6 +1231,7 @@ mod config_tests {
             kill_id: 3,
             zkb: Zkb {
                 href: "".to_string(),
+                ..Default::default()
             },
             killmail: Some(KillmailData {
                 system_id: 30000142,
@@ -878,6 +1251,7 @@ mod config_tests {
             kill_id: 4,
             zkb: Zkb {
                 href: "".to_string(),
+                ..Default::default()
             },
             killmail: Some(KillmailData {
                 system_id: 30000142,
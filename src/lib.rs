@@ -1,7 +1,13 @@
 pub mod config;
 pub mod discord;
+pub mod esi;
 pub mod filters;
+pub mod names;
+pub mod notifier;
 pub mod otel;
 pub mod persistence;
+pub mod pipeline;
 pub mod static_data;
+pub mod template;
+pub mod webhook;
 pub mod zkb;
@@ -0,0 +1,169 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use crate::{esi, persistence::cache::Cache};
+
+pub use esi::Name;
+
+// ESI refuses to resolve more than 1000 ids per request
+const BATCH_SIZE: usize = 1000;
+const DEFAULT_TTL: Duration = Duration::from_secs(86400);
+const KEY_PREFIX: &str = "krusty:name:";
+
+// Resolver turns character, corporation, alliance, type and other ids into
+// names. Results are kept in memory and, when a cache is given, in redis so
+// restarts don't have to resolve everything again.
+#[derive(Clone)]
+pub struct Resolver {
+    esi: esi::Client,
+    cache: Option<Cache>,
+    memory: Arc<RwLock<HashMap<u64, (Name, Instant)>>>,
+    ttl: Duration,
+}
+
+impl Resolver {
+    pub fn new(esi: esi::Client, cache: Option<Cache>) -> Self {
+        Self {
+            esi,
+            cache,
+            memory: Arc::new(RwLock::new(HashMap::new())),
+            ttl: DEFAULT_TTL,
+        }
+    }
+
+    // Overrides how long resolved names are kept
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    // Resolves as many of the ids as possible, ids that can't be resolved are
+    // missing from the result
+    pub async fn resolve(&self, ids: &[u64]) -> HashMap<u64, Name> {
+        let mut names = HashMap::new();
+
+        let mut seen = HashSet::new();
+        let mut missing = ids
+            .iter()
+            .copied()
+            .filter(|id| *id != 0 && seen.insert(*id))
+            .collect::<Vec<u64>>();
+
+        if let Ok(memory) = self.memory.read() {
+            missing.retain(|id| match memory.get(id) {
+                Some((name, expires)) if *expires > Instant::now() => {
+                    names.insert(*id, name.clone());
+                    false
+                }
+                _ => true,
+            });
+        }
+
+        for name in self.cached(&missing).await {
+            missing.retain(|id| *id != name.id);
+            self.remember(&name);
+            names.insert(name.id, name);
+        }
+
+        for batch in missing.chunks(BATCH_SIZE) {
+            let fetched = self.fetch(batch).await;
+            self.cache(&fetched).await;
+            for name in fetched {
+                self.remember(&name);
+                names.insert(name.id, name);
+            }
+        }
+
+        names
+    }
+
+    // Looks the ids up in redis with one MGET, failures only mean the ids are
+    // resolved again
+    async fn cached(&self, ids: &[u64]) -> Vec<Name> {
+        let Some(cache) = &self.cache else {
+            return vec![];
+        };
+
+        let keys = ids
+            .iter()
+            .map(|id| format!("{KEY_PREFIX}{id}"))
+            .collect::<Vec<String>>();
+
+        match cache.get_values(&keys).await {
+            Ok(values) => values
+                .into_iter()
+                .flatten()
+                .filter_map(|v| simd_json::from_slice::<Name>(&mut v.into_bytes()).ok())
+                .collect(),
+            Err(e) => {
+                tracing::warn!(error = e.to_string(), "failed to read cached names");
+                vec![]
+            }
+        }
+    }
+
+    // Writes a resolved batch back to redis in one pipeline
+    async fn cache(&self, names: &[Name]) {
+        let Some(cache) = &self.cache else {
+            return;
+        };
+
+        let values = names
+            .iter()
+            .filter_map(|name| match simd_json::to_string(name) {
+                Ok(value) => Some((format!("{KEY_PREFIX}{}", name.id), value)),
+                Err(e) => {
+                    tracing::warn!(id = name.id, error = e.to_string(), "failed to encode name");
+                    None
+                }
+            })
+            .collect::<Vec<(String, String)>>();
+
+        if let Err(e) = cache.store_values(&values, Some(self.ttl)).await {
+            tracing::warn!(
+                names = values.len(),
+                error = e.to_string(),
+                "failed to cache names"
+            );
+        }
+    }
+
+    fn remember(&self, name: &Name) {
+        if let Ok(mut memory) = self.memory.write() {
+            memory.insert(name.id, (name.clone(), Instant::now() + self.ttl));
+        }
+    }
+
+    // ESI rejects a whole batch when one id in it is invalid, so rejected
+    // batches are split until the invalid ids are isolated
+    fn fetch<'a>(
+        &'a self,
+        ids: &'a [u64],
+    ) -> std::pin::Pin<Box<dyn Future<Output = Vec<Name>> + Send + 'a>> {
+        Box::pin(async move {
+            match self.esi.names(ids).await {
+                Ok(names) => names,
+                Err(e) if is_not_found(&e) && ids.len() > 1 => {
+                    let (left, right) = ids.split_at(ids.len() / 2);
+                    let mut names = self.fetch(left).await;
+                    names.extend(self.fetch(right).await);
+                    names
+                }
+                Err(e) => {
+                    tracing::warn!(ids = ?ids, error = e.to_string(), "failed to resolve names");
+                    vec![]
+                }
+            }
+        })
+    }
+}
+
+fn is_not_found(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(|e| e.status())
+        .is_some_and(|s| s == reqwest::StatusCode::NOT_FOUND)
+}
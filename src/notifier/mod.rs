@@ -0,0 +1,37 @@
+use tracing::Span;
+
+use crate::pipeline::Delivery;
+
+pub mod recorder;
+
+// Notifier delivers a matched killmail to a channel
+#[async_trait::async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, parent: &Span, delivery: &Delivery) -> Result<(), anyhow::Error>;
+}
+
+// Unreachable is returned when a target refused a delivery in a way retrying
+// won't fix, e.g. the channel was deleted or the bot lost access to it
+#[derive(Debug)]
+pub struct Unreachable {
+    pub status: u16,
+    pub reason: String,
+}
+
+impl Unreachable {
+    // Only statuses that mean the target is gone or off limits count
+    pub fn from_status(status: u16, reason: String) -> Option<Self> {
+        match status {
+            403 | 404 | 410 => Some(Self { status, reason }),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Unreachable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (status {})", self.reason, self.status)
+    }
+}
+
+impl std::error::Error for Unreachable {}
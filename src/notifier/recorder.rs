@@ -0,0 +1,68 @@
+use std::sync::{Arc, Mutex};
+
+use tracing::Span;
+use twilight_model::channel::message::Embed;
+
+use crate::{discord::embed, filters, pipeline::Delivery};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification {
+    pub channel_id: u64,
+    pub kill_id: u64,
+    pub side: Option<filters::KillmailSide>,
+    pub discord_webhook: Option<filters::DiscordWebhook>,
+    pub embed: Embed,
+}
+
+// Recorder keeps every notification in memory instead of sending it anywhere,
+// building the same embeds the Discord gateway would post
+#[derive(Clone)]
+pub struct Recorder {
+    embeds: embed::Builder,
+    notifications: Arc<Mutex<Vec<Notification>>>,
+}
+
+impl Recorder {
+    pub fn new(embeds: embed::Builder) -> Self {
+        Self {
+            embeds,
+            notifications: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn notifications(&self) -> Vec<Notification> {
+        self.notifications
+            .lock()
+            .map(|n| n.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait::async_trait]
+impl crate::notifier::Notifier for Recorder {
+    async fn notify(&self, _parent: &Span, delivery: &Delivery) -> Result<(), anyhow::Error> {
+        let embed = self
+            .embeds
+            .build(
+                &delivery.killmail,
+                delivery.side.clone(),
+                delivery.template.as_ref(),
+            )
+            .await?;
+
+        let mut notifications = self
+            .notifications
+            .lock()
+            .map_err(|_| anyhow::anyhow!("failed to acquire notifications lock"))?;
+
+        notifications.push(Notification {
+            channel_id: delivery.channel_id,
+            kill_id: delivery.killmail.kill_id,
+            side: delivery.side.clone(),
+            discord_webhook: delivery.discord_webhook.clone(),
+            embed,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use std::sync::{Arc, Mutex};
+
+use crate::filters::{FilterPreset, FilterSet, FilterSnapshot};
+
+use super::{FilterSetsChange, Store, Update};
+
+// Changes kept per channel, older ones are dropped
+pub const MAX_CHANGES: usize = 100;
+
+// Change records who changed the filters of a channel, and how
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+pub struct Change {
+    pub guild_id: u64,
+    pub channel_id: u64,
+    pub user_id: u64,
+    pub user_name: String,
+    // Command the change was made with, e.g. filter-add
+    pub command: String,
+    pub at: chrono::DateTime<chrono::Utc>,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+    // Other settings of the filter set that changed, e.g. "webhook removed"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub settings: Vec<String>,
+}
+
+impl Change {
+    pub fn added(&self) -> Vec<&String> {
+        self.after
+            .iter()
+            .filter(|filter| !self.before.contains(filter))
+            .collect()
+    }
+
+    pub fn removed(&self) -> Vec<&String> {
+        self.before
+            .iter()
+            .filter(|filter| !self.after.contains(filter))
+            .collect()
+    }
+
+    // Added and removed filters as a diff followed by the changed settings,
+    // or a note when only the order of the filters changed
+    pub fn describe(&self) -> String {
+        let mut lines = self
+            .added()
+            .iter()
+            .map(|filter| format!("+ `{filter}`"))
+            .chain(self.removed().iter().map(|filter| format!("- `{filter}`")))
+            .chain(self.settings.iter().map(|setting| format!("~ {setting}")))
+            .collect::<Vec<String>>();
+
+        if lines.is_empty() {
+            lines.push("filters reordered".to_string());
+        }
+
+        lines.join("\n")
+    }
+}
+
+// Actor is who changes filters through an Audited store
+#[derive(Debug, Clone)]
+pub struct Actor {
+    pub user_id: u64,
+    pub user_name: String,
+    pub command: String,
+}
+
+// Audited records a Change for every write that changes a channel's filter
+// set, and keeps the changes it made so they can be reported afterwards
+pub struct Audited {
+    inner: Arc<dyn Store>,
+    actor: Actor,
+    changes: Mutex<Vec<Change>>,
+}
+
+impl Audited {
+    pub fn new(inner: Arc<dyn Store>, actor: Actor) -> Self {
+        Self {
+            inner,
+            actor,
+            changes: Mutex::new(vec![]),
+        }
+    }
+
+    // Changes made through this store so far
+    pub fn changes(&self) -> Vec<Change> {
+        self.changes
+            .lock()
+            .map(|changes| changes.clone())
+            .unwrap_or_default()
+    }
+
+    // Records how an update changed the channel's filter set, a failed record
+    // is logged but doesn't fail the write
+    async fn record(&self, before: Option<&FilterSet>, after: Option<&FilterSet>) {
+        let Some(filter_set) = after.or(before) else {
+            return;
+        };
+        if before == after {
+            return;
+        }
+
+        let filters = |filter_set: Option<&FilterSet>| {
+            filter_set
+                .map(|filter_set| filter_set.filters.clone())
+                .unwrap_or_default()
+        };
+
+        let change = Change {
+            guild_id: filter_set.guild_id,
+            channel_id: filter_set.channel_id,
+            user_id: self.actor.user_id,
+            user_name: self.actor.user_name.clone(),
+            command: self.actor.command.clone(),
+            at: chrono::Utc::now(),
+            before: filters(before),
+            after: filters(after),
+            settings: settings(before, after),
+        };
+
+        if let Err(e) = self.inner.record_change(change.clone()).await {
+            tracing::error!(
+                channel_id = change.channel_id,
+                error = e.to_string(),
+                "failed to record change"
+            );
+        }
+        if let Ok(mut changes) = self.changes.lock() {
+            changes.push(change);
+        }
+    }
+}
+
+// Describes the settings besides the filters that differ, webhook urls and
+// tokens are left out
+fn settings(before: Option<&FilterSet>, after: Option<&FilterSet>) -> Vec<String> {
+    let mut settings = vec![];
+
+    let webhook = |filter_set: Option<&FilterSet>| filter_set.and_then(|s| s.webhook.clone());
+    if webhook(before) != webhook(after) {
+        settings.push(match webhook(after) {
+            Some(_) => "webhook set".to_string(),
+            None => "webhook removed".to_string(),
+        });
+    }
+
+    let discord_webhook =
+        |filter_set: Option<&FilterSet>| filter_set.and_then(|s| s.discord_webhook.clone());
+    if discord_webhook(before) != discord_webhook(after) {
+        settings.push(match discord_webhook(after) {
+            Some(_) => "Discord webhook set".to_string(),
+            None => "Discord webhook removed".to_string(),
+        });
+    }
+
+    let template = |filter_set: Option<&FilterSet>| filter_set.and_then(|s| s.template.clone());
+    if template(before) != template(after) {
+        settings.push(format!(
+            "template set to {}",
+            template(after).unwrap_or_default().name()
+        ));
+    }
+
+    let disabled = |filter_set: Option<&FilterSet>| filter_set.and_then(|s| s.disabled.clone());
+    if disabled(before) != disabled(after) {
+        settings.push(match disabled(after) {
+            Some(disabled) => format!("deliveries paused: {}", disabled.reason),
+            None => "deliveries resumed".to_string(),
+        });
+    }
+
+    settings
+}
+
+#[async_trait::async_trait]
+impl Store for Audited {
+    async fn get_channel_filter_set(&self, channel_id: u64) -> Result<FilterSet, anyhow::Error> {
+        self.inner.get_channel_filter_set(channel_id).await
+    }
+
+    async fn list_guild_filter_sets(&self, guild_id: u64) -> Result<Vec<FilterSet>, anyhow::Error> {
+        self.inner.list_guild_filter_sets(guild_id).await
+    }
+
+    async fn list_filter_sets(&self) -> Result<Vec<FilterSet>, anyhow::Error> {
+        self.inner.list_filter_sets().await
+    }
+
+    // Every filter set write goes through here, so the store's write lock
+    // covers the filter sets the change is recorded from
+    async fn update_filter_sets(
+        &self,
+        channel_ids: &[u64],
+        change: FilterSetsChange<'_>,
+    ) -> Result<Vec<Update>, anyhow::Error> {
+        let updates = self.inner.update_filter_sets(channel_ids, change).await?;
+        for (before, after) in &updates {
+            self.record(before.as_ref(), after.as_ref()).await;
+        }
+
+        Ok(updates)
+    }
+
+    async fn list_filter_presets(&self, guild_id: u64) -> Result<Vec<FilterPreset>, anyhow::Error> {
+        self.inner.list_filter_presets(guild_id).await
+    }
+
+    async fn get_filter_preset(
+        &self,
+        guild_id: u64,
+        name: &str,
+    ) -> Result<FilterPreset, anyhow::Error> {
+        self.inner.get_filter_preset(guild_id, name).await
+    }
+
+    async fn set_filter_preset(&self, preset: FilterPreset) -> Result<(), anyhow::Error> {
+        self.inner.set_filter_preset(preset).await
+    }
+
+    async fn remove_filter_preset(&self, guild_id: u64, name: &str) -> Result<(), anyhow::Error> {
+        self.inner.remove_filter_preset(guild_id, name).await
+    }
+
+    async fn set_filter_snapshot(&self, snapshot: FilterSnapshot) -> Result<(), anyhow::Error> {
+        self.inner.set_filter_snapshot(snapshot).await
+    }
+
+    async fn get_filter_snapshot(&self, channel_id: u64) -> Result<FilterSnapshot, anyhow::Error> {
+        self.inner.get_filter_snapshot(channel_id).await
+    }
+
+    async fn remove_filter_snapshot(&self, channel_id: u64) -> Result<(), anyhow::Error> {
+        self.inner.remove_filter_snapshot(channel_id).await
+    }
+
+    async fn record_change(&self, change: Change) -> Result<(), anyhow::Error> {
+        self.inner.record_change(change).await
+    }
+
+    async fn list_changes(
+        &self,
+        channel_id: u64,
+        limit: usize,
+    ) -> Result<Vec<Change>, anyhow::Error> {
+        self.inner.list_changes(channel_id, limit).await
+    }
+}
@@ -1,40 +1,19 @@
-use redis::TypedCommands;
+use redis::{AsyncCommands, aio::ConnectionManager};
 
+// Cache shares the store's multiplexed redis connection, clones are cheap
 #[derive(Clone)]
 pub struct Cache {
-    client: redis::Client,
-    url: String,
+    connection: ConnectionManager,
 }
 
 impl Cache {
-    pub fn build(url: String) -> Result<Self, anyhow::Error> {
-        let client = match redis::Client::open(url.clone()) {
-            Ok(c) => c,
-            Err(e) => {
-                tracing::error!(url, error = e.to_string(), "failed to create redis client");
-                return Err(anyhow::format_err!("failed to create redis client: {e}"));
-            }
-        };
-        Ok(Self {
-            client,
-            url: url.clone(),
-        })
+    pub fn new(connection: ConnectionManager) -> Self {
+        Self { connection }
     }
 
-    pub fn check(&self, key: &str) -> Result<bool, anyhow::Error> {
-        let mut conn = match self.client.get_connection() {
-            Ok(c) => c,
-            Err(e) => {
-                tracing::error!(
-                    url = self.url,
-                    error = e.to_string(),
-                    key,
-                    "failed to connect to cache"
-                );
-                return Err(anyhow::format_err!("failed to connect to cache: {e}"));
-            }
-        };
-        match conn.get(key) {
+    pub async fn check(&self, key: &str) -> Result<bool, anyhow::Error> {
+        let mut conn = self.connection.clone();
+        match conn.get::<_, Option<String>>(key).await {
             Ok(Some(_)) => {
                 tracing::debug!(key, "cache hit");
                 Ok(true)
@@ -44,44 +23,69 @@ impl Cache {
                 Ok(false)
             }
             Err(e) => {
-                tracing::error!(
-                    url = self.url,
-                    error = e.to_string(),
-                    key,
-                    "failed to check cache"
-                );
+                tracing::error!(error = e.to_string(), key, "failed to check cache");
                 Err(anyhow::format_err!("failed to retrieve cache item: {e}"))
             }
         }
     }
 
-    pub fn store(&self, key: &str, ttl: Option<std::time::Duration>) -> Result<(), anyhow::Error> {
-        let mut conn = match self.client.get_connection() {
-            Ok(c) => c,
+    pub async fn store(
+        &self,
+        key: &str,
+        ttl: Option<std::time::Duration>,
+    ) -> Result<(), anyhow::Error> {
+        self.store_values(&[(key.to_string(), "1".to_string())], ttl)
+            .await
+    }
+
+    // Fetches the values of several keys with one MGET, in the order of the keys
+    pub async fn get_values(&self, keys: &[String]) -> Result<Vec<Option<String>>, anyhow::Error> {
+        if keys.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut conn = self.connection.clone();
+        match conn.mget(keys).await {
+            Ok(values) => Ok(values),
             Err(e) => {
                 tracing::error!(
-                    url = self.url,
                     error = e.to_string(),
-                    key,
-                    "failed to connect to cache"
+                    keys = keys.len(),
+                    "failed to check cache"
                 );
-                return Err(anyhow::format_err!("failed to connect to cache: {e}"));
-            }
-        };
-        match ttl {
-            None => {
-                conn.set(key, 1)?;
-            }
-            Some(d) => {
-                let seconds = if d.as_secs() == 0 && d.subsec_nanos() > 0 {
-                    1
-                } else {
-                    d.as_secs()
-                };
-                conn.set_ex(key, 1, seconds)?;
+                Err(anyhow::format_err!("failed to retrieve cache items: {e}"))
             }
         }
+    }
+
+    // Stores several values in one pipelined round trip
+    pub async fn store_values(
+        &self,
+        values: &[(String, String)],
+        ttl: Option<std::time::Duration>,
+    ) -> Result<(), anyhow::Error> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        let mut pipe = redis::pipe();
+        for (key, value) in values {
+            match ttl {
+                None => pipe.set(key, value).ignore(),
+                Some(d) => {
+                    let seconds = if d.as_secs() == 0 && d.subsec_nanos() > 0 {
+                        1
+                    } else {
+                        d.as_secs()
+                    };
+                    pipe.set_ex(key, value, seconds).ignore()
+                }
+            };
+        }
 
-        Ok(())
+        let mut conn = self.connection.clone();
+        pipe.query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| anyhow::format_err!("failed to store cache items: {e}"))
     }
 }
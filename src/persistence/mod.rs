@@ -1,26 +1,368 @@
-use crate::filters::FilterSet;
+use crate::filters::{Disabled, FilterPreset, FilterSet, FilterSnapshot};
 
+pub mod audit;
 pub mod cache;
 pub mod provider;
 
+// Changes the filter sets of channels in place, a None is a channel without a
+// filter set or one to remove
+pub type FilterSetsChange<'a> =
+    Box<dyn FnOnce(&mut [Option<FilterSet>]) -> Result<(), anyhow::Error> + Send + 'a>;
+
+// A channel's filter set before and after an update
+pub type Update = (Option<FilterSet>, Option<FilterSet>);
+
+// Store is async so a remote store never blocks the runtime's workers
+#[async_trait::async_trait]
 pub trait Store: Send + Sync {
-    fn get_channel_filter_set(&self, channel_id: u64) -> Result<FilterSet, anyhow::Error>;
+    async fn get_channel_filter_set(&self, channel_id: u64) -> Result<FilterSet, anyhow::Error>;
+
+    // every filter set of the guild's channels, ordered by channel. Webhook
+    // targets from the bot config aren't channels and are left out.
+    async fn list_guild_filter_sets(&self, guild_id: u64) -> Result<Vec<FilterSet>, anyhow::Error>;
+
+    async fn list_filter_sets(&self) -> Result<Vec<FilterSet>, anyhow::Error>;
 
-    fn get_guild_filter_set(&self, guild_id: u64) -> Result<FilterSet, anyhow::Error>;
+    // Applies the change to the filter sets of the channels, None where a
+    // channel has none, while holding the store's write lock for them. Every
+    // filter set is stored or, when anything fails, none of them. Returns the
+    // filter sets before and after the change, in the order of the channels.
+    async fn update_filter_sets(
+        &self,
+        channel_ids: &[u64],
+        change: FilterSetsChange<'_>,
+    ) -> Result<Vec<Update>, anyhow::Error>;
+
+    async fn set_filter_set(&self, filter_set: FilterSet) -> Result<(), anyhow::Error> {
+        tracing::trace!(?filter_set, "setting filter set");
+        self.set_filter_sets(vec![filter_set]).await
+    }
 
-    fn list_filter_sets(&self) -> Result<Vec<FilterSet>, anyhow::Error>;
+    // stores every filter set or, when anything fails, none of them
+    async fn set_filter_sets(&self, filter_sets: Vec<FilterSet>) -> Result<(), anyhow::Error> {
+        tracing::trace!(count = filter_sets.len(), "setting filter sets");
+        let channel_ids = filter_sets
+            .iter()
+            .map(|filter_set| filter_set.channel_id)
+            .collect::<Vec<u64>>();
 
-    fn set_filter_set(&self, filter_set: FilterSet) -> Result<(), anyhow::Error>;
+        self.update_filter_sets(
+            &channel_ids,
+            Box::new(move |current| {
+                for (current, filter_set) in current.iter_mut().zip(filter_sets) {
+                    *current = Some(filter_set);
+                }
+                Ok(())
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
 
     // need guild_id as we might have to create a new FilterSet
-    fn add_filter_to_set(
+    async fn add_filter_to_set(
         &self,
         guild_id: u64,
         channel_id: u64,
         new_filter: &str,
-    ) -> Result<(), anyhow::Error>;
+    ) -> Result<(), anyhow::Error> {
+        tracing::debug!(
+            guild_id,
+            channel_id,
+            filter = new_filter,
+            "adding filter to set"
+        );
+        self.update_filter_sets(
+            &[channel_id],
+            Box::new(move |current| {
+                let filter_set = current[0].get_or_insert_with(|| FilterSet {
+                    channel_id,
+                    guild_id,
+                    filters: Vec::new(),
+                    webhook: None,
+                    discord_webhook: None,
+                    template: None,
+                    disabled: None,
+                    config_filters: vec![],
+                });
+                filter_set.filters.push(new_filter.to_string());
+                filter_set.disabled = None;
+                Ok(())
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn remove_filter_from_set(
+        &self,
+        channel_id: u64,
+        filter: &str,
+    ) -> Result<(), anyhow::Error> {
+        tracing::debug!(channel_id, filter, "removing filter from set");
+        modify(self, channel_id, |filter_set| {
+            check_config_filter(filter_set, filter)?;
+            if !filter_set.filters.iter().any(|f| f == filter) {
+                return Err(anyhow::anyhow!(
+                    "filter `{filter}` not found in channel {channel_id}"
+                ));
+            }
+            filter_set.filters.retain(|f| f != filter);
+            Ok(())
+        })
+        .await
+    }
+
+    // indexes are zero-based, returns the removed filter
+    async fn remove_filter_at(
+        &self,
+        channel_id: u64,
+        index: usize,
+    ) -> Result<String, anyhow::Error> {
+        tracing::debug!(channel_id, index, "removing filter at index from set");
+        modify(self, channel_id, |filter_set| {
+            check_index(filter_set, index)?;
+            check_config_filter(filter_set, &filter_set.filters[index])?;
+            Ok(filter_set.filters.remove(index))
+        })
+        .await
+    }
+
+    // returns the filter that was replaced
+    async fn replace_filter_at(
+        &self,
+        channel_id: u64,
+        index: usize,
+        filter: &str,
+    ) -> Result<String, anyhow::Error> {
+        tracing::debug!(
+            channel_id,
+            index,
+            filter,
+            "replacing filter at index in set"
+        );
+        modify(self, channel_id, |filter_set| {
+            check_index(filter_set, index)?;
+            check_config_filter(filter_set, &filter_set.filters[index])?;
+            Ok(std::mem::replace(
+                &mut filter_set.filters[index],
+                filter.to_string(),
+            ))
+        })
+        .await
+    }
+
+    // moves the filter at `from` to `to`, shifting the filters in between
+    async fn move_filter(
+        &self,
+        channel_id: u64,
+        from: usize,
+        to: usize,
+    ) -> Result<(), anyhow::Error> {
+        tracing::debug!(channel_id, from, to, "moving filter in set");
+        modify(self, channel_id, |filter_set| {
+            check_index(filter_set, from)?;
+            check_index(filter_set, to)?;
+
+            let filter = filter_set.filters.remove(from);
+            filter_set.filters.insert(to, filter);
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn clear_filter_set(&self, channel_id: u64) -> Result<(), anyhow::Error> {
+        tracing::debug!(channel_id, "clearing filter set");
+        self.update_filter_sets(
+            &[channel_id],
+            Box::new(|current| {
+                current[0] = None;
+                Ok(())
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    // pauses deliveries to the channel until its filters change, the rest of
+    // the filter set is left as is
+    async fn disable_filter_set(
+        &self,
+        channel_id: u64,
+        disabled: Disabled,
+    ) -> Result<(), anyhow::Error> {
+        tracing::debug!(channel_id, "disabling filter set");
+        modify(self, channel_id, move |filter_set| {
+            filter_set.disabled = Some(disabled);
+            Ok(())
+        })
+        .await
+    }
+
+    // presets are saved per guild and ordered by name
+    async fn list_filter_presets(&self, guild_id: u64) -> Result<Vec<FilterPreset>, anyhow::Error>;
+
+    async fn get_filter_preset(
+        &self,
+        guild_id: u64,
+        name: &str,
+    ) -> Result<FilterPreset, anyhow::Error>;
+
+    // replaces a preset with the same name
+    async fn set_filter_preset(&self, preset: FilterPreset) -> Result<(), anyhow::Error>;
+
+    async fn remove_filter_preset(&self, guild_id: u64, name: &str) -> Result<(), anyhow::Error>;
+
+    // one snapshot per channel, replacing the previous one. Snapshots are
+    // dropped after FilterSnapshot::RETENTION.
+    async fn set_filter_snapshot(&self, snapshot: FilterSnapshot) -> Result<(), anyhow::Error>;
+
+    // fails when the channel has no snapshot or it expired
+    async fn get_filter_snapshot(&self, channel_id: u64) -> Result<FilterSnapshot, anyhow::Error>;
+
+    async fn remove_filter_snapshot(&self, channel_id: u64) -> Result<(), anyhow::Error>;
+
+    // keeps the latest audit::MAX_CHANGES changes of the channel
+    async fn record_change(&self, change: audit::Change) -> Result<(), anyhow::Error>;
+
+    // newest changes first
+    async fn list_changes(
+        &self,
+        channel_id: u64,
+        limit: usize,
+    ) -> Result<Vec<audit::Change>, anyhow::Error>;
+}
+
+// Clears every filter set of a guild, e.g. once the bot was removed from it,
+// and returns the channels that had one
+pub async fn remove_guild_filter_sets(
+    store: &dyn Store,
+    guild_id: u64,
+) -> Result<Vec<u64>, anyhow::Error> {
+    let channel_ids = store
+        .list_guild_filter_sets(guild_id)
+        .await?
+        .into_iter()
+        .map(|filter_set| filter_set.channel_id)
+        .collect::<Vec<u64>>();
+
+    for channel_id in &channel_ids {
+        store.clear_filter_set(*channel_id).await?;
+    }
+
+    Ok(channel_ids)
+}
+
+// Adds the filters to a channel's filter set, creating it when needed, and
+// returns how many were added. Filters the channel already has are skipped,
+// with `replace` the channel's filters are replaced instead.
+pub async fn apply_filters(
+    store: &dyn Store,
+    guild_id: u64,
+    channel_id: u64,
+    filters: &[String],
+    replace: bool,
+) -> Result<usize, anyhow::Error> {
+    let mut added = 0;
+
+    store
+        .update_filter_sets(
+            &[channel_id],
+            Box::new(|current| {
+                let filter_set = current[0].get_or_insert_with(|| FilterSet {
+                    guild_id,
+                    channel_id,
+                    filters: vec![],
+                    webhook: None,
+                    discord_webhook: None,
+                    template: None,
+                    disabled: None,
+                    config_filters: vec![],
+                });
+
+                // Filters from the bot config are kept
+                if replace {
+                    let config_filters = filter_set.config_filters.clone();
+                    filter_set.filters.retain(|f| config_filters.contains(f));
+                }
+
+                for filter in filters {
+                    if !filter_set.filters.contains(filter) {
+                        filter_set.filters.push(filter.clone());
+                        added += 1;
+                    }
+                }
+
+                // Changing the filters resumes deliveries to a disabled channel
+                filter_set.disabled = None;
+                Ok(())
+            }),
+        )
+        .await?;
+
+    Ok(added)
+}
+
+// Applies a change to the channel's existing filter set. Changing the filters
+// resumes deliveries to a disabled channel, unless the change pauses them
+// again.
+async fn modify<S, T>(
+    store: &S,
+    channel_id: u64,
+    change: impl FnOnce(&mut FilterSet) -> Result<T, anyhow::Error> + Send,
+) -> Result<T, anyhow::Error>
+where
+    S: Store + ?Sized,
+    T: Send,
+{
+    let mut result = None;
+
+    store
+        .update_filter_sets(
+            &[channel_id],
+            Box::new(|current| {
+                let Some(filter_set) = current[0].as_mut() else {
+                    return Err(anyhow::anyhow!(
+                        "filter set not found for channel {}",
+                        channel_id
+                    ));
+                };
+                filter_set.disabled = None;
+                result = Some(change(filter_set)?);
+                Ok(())
+            }),
+        )
+        .await?;
+
+    result.ok_or_else(|| anyhow::anyhow!("filter set of channel {channel_id} was not changed"))
+}
+
+// Checks shared by the filter set edits
+
+fn check_index(filter_set: &FilterSet, index: usize) -> Result<(), anyhow::Error> {
+    if index >= filter_set.filters.len() {
+        return Err(anyhow::anyhow!(
+            "filter index {index} out of range for channel {}, it has {} filters",
+            filter_set.channel_id,
+            filter_set.filters.len()
+        ));
+    }
+
+    Ok(())
+}
 
-    fn remove_filter_from_set(&self, channel_id: u64, filter: &str) -> Result<(), anyhow::Error>;
+// Filters from the bot config can only be changed in the config, otherwise
+// they'd come back on the next start anyway
+fn check_config_filter(filter_set: &FilterSet, filter: &str) -> Result<(), anyhow::Error> {
+    if filter_set.config_filters.iter().any(|f| f == filter) {
+        return Err(anyhow::anyhow!(
+            "filter `{filter}` comes from the bot config and can only be changed there"
+        ));
+    }
 
-    fn clear_filter_set(&self, channel_id: u64) -> Result<(), anyhow::Error>;
+    Ok(())
 }
@@ -1,21 +1,38 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, VecDeque},
     sync::{Arc, RwLock},
 };
 
-use crate::filters::FilterSet;
+use crate::{
+    filters::{FilterPreset, FilterSet, FilterSnapshot},
+    persistence::{
+        FilterSetsChange, Update,
+        audit::{self, Change},
+    },
+};
 
 type FilterSetMap = Arc<RwLock<HashMap<u64, FilterSet>>>;
+// presets by guild, then by name
+type FilterPresetMap = Arc<RwLock<HashMap<u64, BTreeMap<String, FilterPreset>>>>;
+type FilterSnapshotMap = Arc<RwLock<HashMap<u64, FilterSnapshot>>>;
+// changes by channel, newest first
+type ChangeMap = Arc<RwLock<HashMap<u64, VecDeque<Change>>>>;
 
 #[derive(Clone, Debug)]
 pub struct Store {
     filter_sets: FilterSetMap,
+    filter_presets: FilterPresetMap,
+    filter_snapshots: FilterSnapshotMap,
+    changes: ChangeMap,
 }
 
 impl Store {
     pub fn new() -> Self {
         Self {
             filter_sets: Arc::new(RwLock::new(HashMap::new())),
+            filter_presets: Arc::new(RwLock::new(HashMap::new())),
+            filter_snapshots: Arc::new(RwLock::new(HashMap::new())),
+            changes: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 }
@@ -26,8 +43,9 @@ impl Default for Store {
     }
 }
 
+#[async_trait::async_trait]
 impl crate::persistence::Store for Store {
-    fn get_channel_filter_set(&self, channel_id: u64) -> Result<FilterSet, anyhow::Error> {
+    async fn get_channel_filter_set(&self, channel_id: u64) -> Result<FilterSet, anyhow::Error> {
         tracing::debug!(channel_id, "getting filter set for channel");
         if let Ok(filters_sets) = self.filter_sets.read() {
             match filters_sets.get(&channel_id).cloned() {
@@ -42,22 +60,24 @@ impl crate::persistence::Store for Store {
         }
     }
 
-    fn get_guild_filter_set(&self, guild_id: u64) -> Result<FilterSet, anyhow::Error> {
-        tracing::debug!(guild_id, "getting filter set for guild");
+    async fn list_guild_filter_sets(&self, guild_id: u64) -> Result<Vec<FilterSet>, anyhow::Error> {
+        tracing::debug!(guild_id, "listing filter sets for guild");
         if let Ok(filter_sets) = self.filter_sets.read() {
-            match filter_sets.get(&guild_id).cloned() {
-                Some(filter_set) => Ok(filter_set),
-                None => Err(anyhow::anyhow!(
-                    "filter set not found for guild {}",
-                    guild_id
-                )),
-            }
+            let mut guild_sets = filter_sets
+                .values()
+                .filter(|filter_set| {
+                    filter_set.guild_id == guild_id && !filter_set.is_webhook_target()
+                })
+                .cloned()
+                .collect::<Vec<FilterSet>>();
+            guild_sets.sort_by_key(|filter_set| filter_set.channel_id);
+            Ok(guild_sets)
         } else {
             Err(anyhow::anyhow!("failed to acquire read lock"))
         }
     }
 
-    fn list_filter_sets(&self) -> Result<Vec<FilterSet>, anyhow::Error> {
+    async fn list_filter_sets(&self) -> Result<Vec<FilterSet>, anyhow::Error> {
         tracing::trace!("listing all filter sets");
         if let Ok(filter_sets) = self.filter_sets.read() {
             Ok(filter_sets.values().cloned().collect())
@@ -66,74 +86,167 @@ impl crate::persistence::Store for Store {
         }
     }
 
-    fn set_filter_set(&self, filter_set: FilterSet) -> Result<(), anyhow::Error> {
-        tracing::trace!(?filter_set, "setting filter set");
+    async fn update_filter_sets(
+        &self,
+        channel_ids: &[u64],
+        change: FilterSetsChange<'_>,
+    ) -> Result<Vec<Update>, anyhow::Error> {
+        tracing::trace!(?channel_ids, "updating filter sets");
+        let Ok(mut filter_sets) = self.filter_sets.write() else {
+            return Err(anyhow::anyhow!("failed to acquire write lock"));
+        };
 
-        if let Ok(mut filters) = self.filter_sets.write() {
-            filters.insert(filter_set.channel_id, filter_set);
+        let before = channel_ids
+            .iter()
+            .map(|channel_id| filter_sets.get(channel_id).cloned())
+            .collect::<Vec<Option<FilterSet>>>();
+        let mut after = before.clone();
+        change(&mut after)?;
 
-            Ok(())
+        for (channel_id, filter_set) in channel_ids.iter().zip(&after) {
+            match filter_set {
+                Some(filter_set) => filter_sets.insert(*channel_id, filter_set.clone()),
+                None => filter_sets.remove(channel_id),
+            };
+        }
+
+        Ok(before.into_iter().zip(after).collect())
+    }
+
+    async fn list_filter_presets(&self, guild_id: u64) -> Result<Vec<FilterPreset>, anyhow::Error> {
+        tracing::debug!(guild_id, "listing filter presets for guild");
+        if let Ok(presets) = self.filter_presets.read() {
+            Ok(presets
+                .get(&guild_id)
+                .map(|presets| presets.values().cloned().collect())
+                .unwrap_or_default())
         } else {
-            Err(anyhow::anyhow!("failed to acquire write lock"))
+            Err(anyhow::anyhow!("failed to acquire read lock"))
         }
     }
 
-    fn add_filter_to_set(
+    async fn get_filter_preset(
         &self,
         guild_id: u64,
-        channel_id: u64,
-        filter: &str,
-    ) -> Result<(), anyhow::Error> {
-        tracing::debug!(guild_id = guild_id, channel_id = channel_id, filters = ?filter, "adding filter to set");
-        if let Ok(mut filters_sets) = self.filter_sets.write() {
-            let filter_set = filters_sets.entry(channel_id).or_insert_with(|| FilterSet {
-                channel_id,
-                guild_id,
-                filters: Vec::new(),
-            });
-            filter_set.filters.push(filter.to_string());
+        name: &str,
+    ) -> Result<FilterPreset, anyhow::Error> {
+        tracing::debug!(guild_id, name, "getting filter preset");
+        if let Ok(presets) = self.filter_presets.read() {
+            match presets.get(&guild_id).and_then(|presets| presets.get(name)) {
+                Some(preset) => Ok(preset.clone()),
+                None => Err(anyhow::anyhow!(
+                    "filter preset {name} not found for guild {guild_id}"
+                )),
+            }
+        } else {
+            Err(anyhow::anyhow!("failed to acquire read lock"))
+        }
+    }
+
+    async fn set_filter_preset(&self, preset: FilterPreset) -> Result<(), anyhow::Error> {
+        tracing::trace!(?preset, "setting filter preset");
+        if let Ok(mut presets) = self.filter_presets.write() {
+            presets
+                .entry(preset.guild_id)
+                .or_default()
+                .insert(preset.name.clone(), preset);
             Ok(())
         } else {
             Err(anyhow::anyhow!("failed to acquire write lock"))
         }
     }
 
-    fn remove_filter_from_set(&self, channel_id: u64, filter: &str) -> Result<(), anyhow::Error> {
-        tracing::debug!(channel_id, filter = filter, "removing filter from set");
-        if let Ok(mut filters_sets) = self.filter_sets.write() {
-            if let Some(filter_set) = filters_sets.get_mut(&channel_id) {
-                filter_set.filters.retain(|f| f != filter);
-                Ok(())
-            } else {
-                Err(anyhow::anyhow!(
-                    "filter set not found for channel {}",
-                    channel_id
-                ))
+    async fn remove_filter_preset(&self, guild_id: u64, name: &str) -> Result<(), anyhow::Error> {
+        tracing::debug!(guild_id, name, "removing filter preset");
+        if let Ok(mut presets) = self.filter_presets.write() {
+            match presets
+                .get_mut(&guild_id)
+                .and_then(|presets| presets.remove(name))
+            {
+                Some(_) => Ok(()),
+                None => Err(anyhow::anyhow!(
+                    "filter preset {name} not found for guild {guild_id}"
+                )),
+            }
+        } else {
+            Err(anyhow::anyhow!("failed to acquire write lock"))
+        }
+    }
+
+    async fn set_filter_snapshot(&self, snapshot: FilterSnapshot) -> Result<(), anyhow::Error> {
+        tracing::trace!(?snapshot, "setting filter snapshot");
+        if let Ok(mut snapshots) = self.filter_snapshots.write() {
+            // Nothing else drops expired snapshots
+            let now = chrono::Utc::now();
+            snapshots.retain(|_, snapshot| !snapshot.expired(now));
+            snapshots.insert(snapshot.filter_set.channel_id, snapshot);
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("failed to acquire write lock"))
+        }
+    }
+
+    async fn get_filter_snapshot(&self, channel_id: u64) -> Result<FilterSnapshot, anyhow::Error> {
+        tracing::debug!(channel_id, "getting filter snapshot");
+        if let Ok(snapshots) = self.filter_snapshots.read() {
+            match snapshots.get(&channel_id) {
+                Some(snapshot) if !snapshot.expired(chrono::Utc::now()) => Ok(snapshot.clone()),
+                _ => Err(anyhow::anyhow!(
+                    "filter snapshot not found for channel {channel_id}"
+                )),
             }
+        } else {
+            Err(anyhow::anyhow!("failed to acquire read lock"))
+        }
+    }
+
+    async fn remove_filter_snapshot(&self, channel_id: u64) -> Result<(), anyhow::Error> {
+        tracing::debug!(channel_id, "removing filter snapshot");
+        if let Ok(mut snapshots) = self.filter_snapshots.write() {
+            snapshots.remove(&channel_id);
+            Ok(())
         } else {
             Err(anyhow::anyhow!("failed to acquire write lock"))
         }
     }
 
-    fn clear_filter_set(&self, channel_id: u64) -> Result<(), anyhow::Error> {
-        tracing::debug!(channel_id, "clearing filter set");
-        if let Ok(mut filters_sets) = self.filter_sets.write() {
-            filters_sets.remove(&channel_id);
+    async fn record_change(&self, change: Change) -> Result<(), anyhow::Error> {
+        tracing::trace!(?change, "recording change");
+        if let Ok(mut changes) = self.changes.write() {
+            let channel_changes = changes.entry(change.channel_id).or_default();
+            channel_changes.push_front(change);
+            channel_changes.truncate(audit::MAX_CHANGES);
             Ok(())
         } else {
             Err(anyhow::anyhow!("failed to acquire write lock"))
         }
     }
+
+    async fn list_changes(
+        &self,
+        channel_id: u64,
+        limit: usize,
+    ) -> Result<Vec<Change>, anyhow::Error> {
+        tracing::debug!(channel_id, limit, "listing changes");
+        if let Ok(changes) = self.changes.read() {
+            Ok(changes
+                .get(&channel_id)
+                .map(|changes| changes.iter().take(limit).cloned().collect())
+                .unwrap_or_default())
+        } else {
+            Err(anyhow::anyhow!("failed to acquire read lock"))
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::persistence::Store;
+    use crate::{filters::Disabled, persistence::Store};
 
     use super::*;
 
-    #[test]
-    fn test_store() {
+    #[tokio::test]
+    async fn test_store() {
         let store = super::Store::new();
 
         // Test setting and getting filter sets
@@ -142,19 +255,23 @@ mod tests {
                 guild_id: 1,
                 channel_id: 20,
                 filters: vec!["filter1".to_string(), "filter2".to_string()],
+                webhook: None,
+                discord_webhook: None,
+                template: None,
+                disabled: None,
+                config_filters: vec![],
             })
+            .await
             .unwrap();
-        let filter_set = store.get_channel_filter_set(20).unwrap();
+        let filter_set = store.get_channel_filter_set(20).await.unwrap();
         assert_eq!(
             filter_set.filters,
             vec!["filter1".to_string(), "filter2".to_string()]
         );
 
         // Test adding a filter to a set
-        store
-            .add_filter_to_set(1, 20, &"filter3".to_string())
-            .unwrap();
-        let filter_set = store.get_channel_filter_set(20).unwrap();
+        store.add_filter_to_set(1, 20, "filter3").await.unwrap();
+        let filter_set = store.get_channel_filter_set(20).await.unwrap();
         assert_eq!(
             filter_set.filters,
             vec![
@@ -165,7 +282,7 @@ mod tests {
         );
 
         // Test listing filter sets
-        let all_filter_sets = store.list_filter_sets().unwrap();
+        let all_filter_sets = store.list_filter_sets().await.unwrap();
         assert_eq!(all_filter_sets.len(), 1);
         assert_eq!(
             all_filter_sets[0],
@@ -176,8 +293,304 @@ mod tests {
                     "filter1".to_string(),
                     "filter2".to_string(),
                     "filter3".to_string()
-                ]
+                ],
+                webhook: None,
+                discord_webhook: None,
+                template: None,
+                disabled: None,
+                config_filters: vec![],
             }
         );
     }
+
+    #[tokio::test]
+    async fn test_filter_indexes() {
+        let store = super::Store::new();
+        for filter in ["filter1", "filter2", "filter3"] {
+            store.add_filter_to_set(1, 20, filter).await.unwrap();
+        }
+
+        // Test replacing a filter
+        let replaced = store.replace_filter_at(20, 1, "filter4").await.unwrap();
+        assert_eq!(replaced, "filter2");
+
+        // Test moving a filter
+        store.move_filter(20, 2, 0).await.unwrap();
+        let filter_set = store.get_channel_filter_set(20).await.unwrap();
+        assert_eq!(filter_set.filters, vec!["filter3", "filter1", "filter4"]);
+
+        // Test removing a filter
+        let removed = store.remove_filter_at(20, 1).await.unwrap();
+        assert_eq!(removed, "filter1");
+        let filter_set = store.get_channel_filter_set(20).await.unwrap();
+        assert_eq!(filter_set.filters, vec!["filter3", "filter4"]);
+
+        // Test out of range indexes and unknown channels
+        assert!(store.remove_filter_at(20, 2).await.is_err());
+        assert!(store.replace_filter_at(20, 5, "filter5").await.is_err());
+        assert!(store.move_filter(20, 0, 2).await.is_err());
+        assert!(store.remove_filter_at(30, 0).await.is_err());
+        // Filters the channel doesn't have aren't reported as removed
+        let err = store
+            .remove_filter_from_set(20, "filter9")
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "filter `filter9` not found in channel 20");
+    }
+
+    #[tokio::test]
+    async fn test_guild_filter_sets() {
+        let store = super::Store::new();
+        store.add_filter_to_set(1, 10, "filter1").await.unwrap();
+        store.add_filter_to_set(1, 20, "filter2").await.unwrap();
+        store.add_filter_to_set(2, 30, "filter3").await.unwrap();
+        // Webhook targets aren't channels of the guild
+        let target = crate::filters::WEBHOOK_TARGET_IDS | 1;
+        store.add_filter_to_set(1, target, "filter4").await.unwrap();
+
+        let guild_sets = store.list_guild_filter_sets(1).await.unwrap();
+        assert_eq!(
+            guild_sets.iter().map(|s| s.channel_id).collect::<Vec<_>>(),
+            vec![10, 20]
+        );
+        assert!(store.list_guild_filter_sets(3).await.unwrap().is_empty());
+
+        let mut removed = crate::persistence::remove_guild_filter_sets(&store, 1)
+            .await
+            .unwrap();
+        removed.sort();
+        assert_eq!(removed, vec![10, 20]);
+
+        let mut remaining = store
+            .list_filter_sets()
+            .await
+            .unwrap()
+            .iter()
+            .map(|s| s.channel_id)
+            .collect::<Vec<_>>();
+        remaining.sort();
+        assert_eq!(remaining, vec![30, target]);
+    }
+
+    #[tokio::test]
+    async fn test_filter_presets() {
+        let store = super::Store::new();
+        let preset = |guild_id, name: &str| FilterPreset {
+            guild_id,
+            name: name.to_string(),
+            filters: vec!["filter1".to_string()],
+        };
+
+        store.set_filter_preset(preset(1, "roams")).await.unwrap();
+        store
+            .set_filter_preset(preset(1, "home-defence"))
+            .await
+            .unwrap();
+        store.set_filter_preset(preset(2, "roams")).await.unwrap();
+
+        let names = store
+            .list_filter_presets(1)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["home-defence", "roams"]);
+        assert_eq!(
+            store.get_filter_preset(2, "roams").await.unwrap(),
+            preset(2, "roams")
+        );
+        assert!(store.get_filter_preset(2, "home-defence").await.is_err());
+
+        store.remove_filter_preset(1, "roams").await.unwrap();
+        assert!(store.remove_filter_preset(1, "roams").await.is_err());
+        assert_eq!(store.list_filter_presets(1).await.unwrap().len(), 1);
+        assert_eq!(store.list_filter_presets(2).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_apply_filters() {
+        let store = super::Store::new();
+        store.add_filter_to_set(1, 20, "filter1").await.unwrap();
+        let filters = vec!["filter1".to_string(), "filter2".to_string()];
+
+        // Filters the channel has are skipped
+        let added = crate::persistence::apply_filters(&store, 1, 20, &filters, false)
+            .await
+            .unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(
+            store.get_channel_filter_set(20).await.unwrap().filters,
+            vec!["filter1", "filter2"]
+        );
+
+        // Channels without filters get a new filter set
+        let added = crate::persistence::apply_filters(&store, 1, 30, &filters, false)
+            .await
+            .unwrap();
+        assert_eq!(added, 2);
+        assert_eq!(store.get_channel_filter_set(30).await.unwrap().guild_id, 1);
+
+        let added = crate::persistence::apply_filters(&store, 1, 20, &filters[1..], true)
+            .await
+            .unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(
+            store.get_channel_filter_set(20).await.unwrap().filters,
+            vec!["filter2"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disabled_filter_sets_resume_on_change() {
+        let store = super::Store::new();
+        store
+            .add_filter_to_set(1, 10, "system:30000142")
+            .await
+            .unwrap();
+
+        store
+            .disable_filter_set(
+                10,
+                Disabled {
+                    reason: "missing access".to_string(),
+                    at: chrono::Utc::now(),
+                },
+            )
+            .await
+            .unwrap();
+        let filter_set = store.get_channel_filter_set(10).await.unwrap();
+        assert_eq!(filter_set.disabled.unwrap().reason, "missing access");
+        assert_eq!(filter_set.filters, vec!["system:30000142"]);
+
+        store
+            .add_filter_to_set(1, 10, "system:30002187")
+            .await
+            .unwrap();
+        let filter_set = store.get_channel_filter_set(10).await.unwrap();
+        assert!(filter_set.disabled.is_none());
+
+        assert!(
+            store
+                .disable_filter_set(
+                    20,
+                    Disabled {
+                        reason: "missing access".to_string(),
+                        at: chrono::Utc::now(),
+                    },
+                )
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_config_filters_are_protected() {
+        let store = super::Store::new();
+        store
+            .set_filter_set(FilterSet {
+                guild_id: 1,
+                channel_id: 20,
+                filters: vec!["filter1".to_string(), "filter2".to_string()],
+                webhook: None,
+                discord_webhook: None,
+                template: None,
+                disabled: None,
+                config_filters: vec!["filter1".to_string()],
+            })
+            .await
+            .unwrap();
+
+        let err = store.remove_filter_at(20, 0).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "filter `filter1` comes from the bot config and can only be changed there"
+        );
+        assert!(store.replace_filter_at(20, 0, "filter3").await.is_err());
+        assert!(store.remove_filter_from_set(20, "filter1").await.is_err());
+
+        // Filters added with commands can still be changed
+        assert_eq!(
+            store.replace_filter_at(20, 1, "filter3").await.unwrap(),
+            "filter2"
+        );
+        store.move_filter(20, 1, 0).await.unwrap();
+
+        let filters = vec!["filter4".to_string()];
+        crate::persistence::apply_filters(&store, 1, 20, &filters, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_channel_filter_set(20).await.unwrap().filters,
+            vec!["filter1", "filter4"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_kept_per_channel() {
+        let store = super::Store::new();
+        let change = |channel_id, after: &str| audit::Change {
+            guild_id: 1,
+            channel_id,
+            user_id: 2,
+            user_name: "pilot".to_string(),
+            command: "filter-add".to_string(),
+            at: chrono::Utc::now(),
+            before: vec![],
+            after: vec![after.to_string()],
+            settings: vec![],
+        };
+
+        for index in 0..audit::MAX_CHANGES + 5 {
+            store
+                .record_change(change(20, &index.to_string()))
+                .await
+                .unwrap();
+        }
+        store.record_change(change(30, "other")).await.unwrap();
+
+        let changes = store.list_changes(20, usize::MAX).await.unwrap();
+        assert_eq!(changes.len(), audit::MAX_CHANGES);
+        // Newest first, the oldest are dropped
+        assert_eq!(changes[0].after, vec![(audit::MAX_CHANGES + 4).to_string()]);
+        assert_eq!(store.list_changes(20, 3).await.unwrap().len(), 3);
+        assert_eq!(
+            store.list_changes(30, 10).await.unwrap()[0].after,
+            vec!["other"]
+        );
+        assert!(store.list_changes(40, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_filter_snapshots_expire() {
+        let store = super::Store::new();
+        let filter_set = FilterSet {
+            guild_id: 1,
+            channel_id: 20,
+            filters: vec!["filter1".to_string()],
+            webhook: None,
+            discord_webhook: None,
+            template: None,
+            disabled: None,
+            config_filters: vec![],
+        };
+
+        store
+            .set_filter_snapshot(FilterSnapshot::new(filter_set.clone()))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_filter_snapshot(20).await.unwrap().filter_set,
+            filter_set
+        );
+        store.remove_filter_snapshot(20).await.unwrap();
+        assert!(store.get_filter_snapshot(20).await.is_err());
+
+        let snapshot = FilterSnapshot {
+            filter_set,
+            taken_at: chrono::Utc::now() - FilterSnapshot::RETENTION,
+        };
+        store.set_filter_snapshot(snapshot).await.unwrap();
+        assert!(store.get_filter_snapshot(20).await.is_err());
+    }
 }
@@ -1,52 +1,133 @@
-use redis::{Client, Commands, Connection};
-use std::sync::{Arc, Mutex};
-
-use crate::filters::FilterSet;
+use redis::{
+    AsyncCommands, Client,
+    aio::{ConnectionManager, ConnectionManagerConfig},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    filters::{FilterPreset, FilterSet, FilterSnapshot},
+    persistence::{
+        FilterSetsChange, Update,
+        audit::{self, Change},
+        cache::Cache,
+    },
+};
 
 const FILTER_SET_PREFIX: &str = "krusty:filter_set:channel:";
 const FILTER_SET_INDEX_KEY: &str = "krusty:filter_set:index";
+// A hash per guild, of preset name to preset
+const FILTER_PRESET_PREFIX: &str = "krusty:filter_preset:guild:";
+// Expires after FilterSnapshot::RETENTION
+const FILTER_SNAPSHOT_PREFIX: &str = "krusty:filter_snapshot:channel:";
+// A list per channel, newest change first
+const CHANGES_PREFIX: &str = "krusty:changes:channel:";
+
+// A lock per channel being written, dropped again once no update holds or
+// waits for it
+type ChannelLocks = Arc<Mutex<HashMap<u64, Arc<tokio::sync::Mutex<()>>>>>;
 
 #[derive(Clone)]
 pub struct Store {
-    #[allow(dead_code)] // Kept for potential reconnection logic
-    client: Arc<Client>,
-    connection: Arc<Mutex<Connection>>,
+    // Multiplexed, so clones share one connection that is re-established
+    // when it drops
+    connection: ConnectionManager,
+    // Held while a filter set is read, changed and written back, so two
+    // changes to a channel don't overwrite each other while changes to other
+    // channels go ahead. The locks only cover this process: the bot runs as a
+    // single instance and a second one writing to the same redis, e.g. while a
+    // rolling deploy overlaps, could still overwrite a change.
+    writes: ChannelLocks,
 }
 
 impl Store {
-    pub fn new(redis_url: &str) -> Result<Self, anyhow::Error> {
+    pub async fn new(redis_url: &str) -> Result<Self, anyhow::Error> {
         let client = Client::open(redis_url)?;
-        let connection = client.get_connection()?;
+        let config = ConnectionManagerConfig::new()
+            .set_connection_timeout(Some(Duration::from_secs(5)))
+            .set_response_timeout(Some(Duration::from_secs(5)))
+            .set_max_delay(Duration::from_secs(10));
+        let connection = client.get_connection_manager_with_config(config).await?;
+
         Ok(Self {
-            client: Arc::new(client),
-            connection: Arc::new(Mutex::new(connection)),
+            connection,
+            writes: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
+    // A cache on the same connection
+    pub fn cache(&self) -> Cache {
+        Cache::new(self.connection.clone())
+    }
+
+    // Takes the locks of the channels in ascending order, so two updates of
+    // overlapping channels can't wait on each other
+    async fn lock_channels(
+        &self,
+        channel_ids: &[u64],
+    ) -> Result<Vec<tokio::sync::OwnedMutexGuard<()>>, anyhow::Error> {
+        let mut channel_ids = channel_ids.to_vec();
+        channel_ids.sort_unstable();
+        channel_ids.dedup();
+
+        let locks = {
+            let mut writes = self
+                .writes
+                .lock()
+                .map_err(|e| anyhow::format_err!("failed to lock channel writes: {e}"))?;
+            // Locks only the map has a handle to are idle, updates that hold
+            // or wait for a lock have one too
+            writes.retain(|_, lock| Arc::strong_count(lock) > 1);
+            channel_ids
+                .iter()
+                .map(|channel_id| writes.entry(*channel_id).or_default().clone())
+                .collect::<Vec<_>>()
+        };
+
+        let mut guards = Vec::with_capacity(locks.len());
+        for lock in locks {
+            guards.push(lock.lock_owned().await);
+        }
+        Ok(guards)
+    }
+
     fn get_key(channel_id: u64) -> String {
         format!("{}{}", FILTER_SET_PREFIX, channel_id)
     }
+
+    fn get_preset_key(guild_id: u64) -> String {
+        format!("{}{}", FILTER_PRESET_PREFIX, guild_id)
+    }
+
+    fn get_snapshot_key(channel_id: u64) -> String {
+        format!("{}{}", FILTER_SNAPSHOT_PREFIX, channel_id)
+    }
+
+    fn get_changes_key(channel_id: u64) -> String {
+        format!("{}{}", CHANGES_PREFIX, channel_id)
+    }
 }
 
 impl std::fmt::Debug for Store {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Store")
-            .field("client", &"<redis::Client>")
+            .field("connection", &"<redis::aio::ConnectionManager>")
             .finish()
     }
 }
 
+#[async_trait::async_trait]
 impl crate::persistence::Store for Store {
-    fn get_channel_filter_set(&self, channel_id: u64) -> Result<FilterSet, anyhow::Error> {
+    async fn get_channel_filter_set(&self, channel_id: u64) -> Result<FilterSet, anyhow::Error> {
         tracing::debug!(channel_id, "getting filter set for channel from redis");
 
-        let mut conn = self
-            .connection
-            .lock()
-            .map_err(|_| anyhow::anyhow!("failed to acquire connection lock"))?;
+        let mut conn = self.connection.clone();
 
         let key = Self::get_key(channel_id);
-        let data: Option<String> = conn.get(&key)?;
+        let data: Option<String> = conn.get(&key).await?;
 
         match data {
             Some(json) => {
@@ -60,154 +141,254 @@ impl crate::persistence::Store for Store {
         }
     }
 
-    fn get_guild_filter_set(&self, guild_id: u64) -> Result<FilterSet, anyhow::Error> {
-        tracing::debug!(guild_id, "getting filter set for guild from redis");
+    async fn list_guild_filter_sets(&self, guild_id: u64) -> Result<Vec<FilterSet>, anyhow::Error> {
+        tracing::debug!(guild_id, "listing filter sets for guild from redis");
 
-        // For guild filter sets, we need to iterate through all filter sets and find the one with the matching guild_id
-        // This is less efficient than the memory implementation, but maintains the same interface
-        let all_sets = self.list_filter_sets()?;
-
-        all_sets
+        // Filter sets are only indexed by channel, so the guild's are picked
+        // out of all of them
+        let mut guild_sets = self
+            .list_filter_sets()
+            .await?
             .into_iter()
-            .find(|fs| fs.guild_id == guild_id)
-            .ok_or_else(|| anyhow::anyhow!("filter set not found for guild {}", guild_id))
+            .filter(|filter_set| filter_set.guild_id == guild_id && !filter_set.is_webhook_target())
+            .collect::<Vec<FilterSet>>();
+        guild_sets.sort_by_key(|filter_set| filter_set.channel_id);
+
+        Ok(guild_sets)
     }
 
-    fn list_filter_sets(&self) -> Result<Vec<FilterSet>, anyhow::Error> {
+    async fn list_filter_sets(&self) -> Result<Vec<FilterSet>, anyhow::Error> {
         tracing::trace!("listing all filter sets from redis");
 
-        let mut conn = self
-            .connection
-            .lock()
-            .map_err(|_| anyhow::anyhow!("failed to acquire connection lock"))?;
+        let mut conn = self.connection.clone();
 
         // Get all channel IDs from the index
-        let channel_ids: Vec<u64> = conn.smembers(FILTER_SET_INDEX_KEY)?;
+        let channel_ids: Vec<u64> = conn.smembers(FILTER_SET_INDEX_KEY).await?;
+
+        if channel_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // Fetched in one round trip, a filter set removed since the index
+        // was read comes back as None
+        let keys = channel_ids
+            .into_iter()
+            .map(Self::get_key)
+            .collect::<Vec<String>>();
+        let data: Vec<Option<String>> = conn.mget(&keys).await?;
 
         let mut filter_sets = Vec::new();
-        for channel_id in channel_ids {
-            let key = Self::get_key(channel_id);
-            let data: Option<String> = conn.get(&key)?;
-            if let Some(json) = data {
-                let filter_set: FilterSet = simd_json::from_slice(&mut json.into_bytes())?;
-                filter_sets.push(filter_set);
-            }
+        for json in data.into_iter().flatten() {
+            let filter_set: FilterSet = simd_json::from_slice(&mut json.into_bytes())?;
+            filter_sets.push(filter_set);
         }
 
         Ok(filter_sets)
     }
 
-    fn set_filter_set(&self, filter_set: FilterSet) -> Result<(), anyhow::Error> {
-        tracing::trace!(?filter_set, "setting filter set in redis");
+    async fn update_filter_sets(
+        &self,
+        channel_ids: &[u64],
+        change: FilterSetsChange<'_>,
+    ) -> Result<Vec<Update>, anyhow::Error> {
+        tracing::trace!(?channel_ids, "updating filter sets in redis");
 
-        let mut conn = self
-            .connection
-            .lock()
-            .map_err(|_| anyhow::anyhow!("failed to acquire connection lock"))?;
+        if channel_ids.is_empty() {
+            return Ok(vec![]);
+        }
 
-        let key = Self::get_key(filter_set.channel_id);
-        let json = simd_json::to_string(&filter_set)?;
+        let _writes = self.lock_channels(channel_ids).await?;
+        let mut conn = self.connection.clone();
 
-        // Store the filter set
-        let _: () = conn.set(&key, &json)?;
+        let keys = channel_ids
+            .iter()
+            .map(|channel_id| Self::get_key(*channel_id))
+            .collect::<Vec<String>>();
+        let data: Vec<Option<String>> = conn.mget(&keys).await?;
 
-        // Add to the index set
-        let _: usize = conn.sadd(FILTER_SET_INDEX_KEY, filter_set.channel_id)?;
+        let before = data
+            .into_iter()
+            .map(|json| {
+                json.map(|json| simd_json::from_slice::<FilterSet>(&mut json.into_bytes()))
+                    .transpose()
+            })
+            .collect::<Result<Vec<Option<FilterSet>>, _>>()?;
+        let mut after = before.clone();
+        change(&mut after)?;
+
+        // Sent as a single MULTI/EXEC transaction
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for ((channel_id, key), filter_set) in channel_ids.iter().zip(&keys).zip(&after) {
+            match filter_set {
+                Some(filter_set) => {
+                    pipe.set(key, simd_json::to_string(filter_set)?)
+                        .ignore()
+                        .sadd(FILTER_SET_INDEX_KEY, channel_id)
+                        .ignore();
+                }
+                None => {
+                    pipe.del(key)
+                        .ignore()
+                        .srem(FILTER_SET_INDEX_KEY, channel_id)
+                        .ignore();
+                }
+            }
+        }
+        let _: () = pipe.query_async(&mut conn).await?;
 
-        Ok(())
+        Ok(before.into_iter().zip(after).collect())
     }
 
-    fn add_filter_to_set(
+    async fn list_filter_presets(&self, guild_id: u64) -> Result<Vec<FilterPreset>, anyhow::Error> {
+        tracing::debug!(guild_id, "listing filter presets for guild from redis");
+
+        let mut conn = self.connection.clone();
+
+        let data: Vec<String> = conn.hvals(Self::get_preset_key(guild_id)).await?;
+
+        let mut presets = data
+            .into_iter()
+            .map(|json| simd_json::from_slice(&mut json.into_bytes()))
+            .collect::<Result<Vec<FilterPreset>, _>>()?;
+        presets.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(presets)
+    }
+
+    async fn get_filter_preset(
         &self,
         guild_id: u64,
-        channel_id: u64,
-        filter: &str,
-    ) -> Result<(), anyhow::Error> {
-        tracing::debug!(
-            guild_id = guild_id,
-            channel_id = channel_id,
-            filters = ?filter,
-            "adding filter to set in redis"
-        );
+        name: &str,
+    ) -> Result<FilterPreset, anyhow::Error> {
+        tracing::debug!(guild_id, name, "getting filter preset from redis");
 
-        let mut conn = self
-            .connection
-            .lock()
-            .map_err(|_| anyhow::anyhow!("failed to acquire connection lock"))?;
+        let mut conn = self.connection.clone();
 
-        let key = Self::get_key(channel_id);
-        let data: Option<String> = conn.get(&key)?;
-
-        let mut filter_set = match data {
-            Some(json) => simd_json::from_slice(&mut json.into_bytes())?,
-            None => FilterSet {
-                channel_id,
-                guild_id,
-                filters: Vec::new(),
-            },
-        };
+        let data: Option<String> = conn.hget(Self::get_preset_key(guild_id), name).await?;
+
+        match data {
+            Some(json) => Ok(simd_json::from_slice(&mut json.into_bytes())?),
+            None => Err(anyhow::anyhow!(
+                "filter preset {name} not found for guild {guild_id}"
+            )),
+        }
+    }
 
-        filter_set.filters.push(filter.to_string());
+    async fn set_filter_preset(&self, preset: FilterPreset) -> Result<(), anyhow::Error> {
+        tracing::trace!(?preset, "setting filter preset in redis");
 
-        let json = simd_json::to_string(&filter_set)?;
-        let _: () = conn.set(&key, &json)?;
+        let mut conn = self.connection.clone();
 
-        // Add to the index set (in case it's a new filter set)
-        let _: usize = conn.sadd(FILTER_SET_INDEX_KEY, channel_id)?;
+        let json = simd_json::to_string(&preset)?;
+        let _: () = conn
+            .hset(Self::get_preset_key(preset.guild_id), &preset.name, &json)
+            .await?;
 
         Ok(())
     }
 
-    fn remove_filter_from_set(&self, channel_id: u64, filter: &str) -> Result<(), anyhow::Error> {
-        tracing::debug!(
-            channel_id,
-            filter = filter,
-            "removing filter from set in redis"
-        );
+    async fn remove_filter_preset(&self, guild_id: u64, name: &str) -> Result<(), anyhow::Error> {
+        tracing::debug!(guild_id, name, "removing filter preset from redis");
 
-        let mut conn = self
-            .connection
-            .lock()
-            .map_err(|_| anyhow::anyhow!("failed to acquire connection lock"))?;
+        let mut conn = self.connection.clone();
 
-        let key = Self::get_key(channel_id);
-        let data: Option<String> = conn.get(&key)?;
+        let removed: usize = conn.hdel(Self::get_preset_key(guild_id), name).await?;
+        if removed == 0 {
+            return Err(anyhow::anyhow!(
+                "filter preset {name} not found for guild {guild_id}"
+            ));
+        }
 
-        match data {
-            Some(json) => {
-                let mut filter_set: FilterSet = simd_json::from_slice(&mut json.into_bytes())?;
-                filter_set.filters.retain(|f| f != filter);
+        Ok(())
+    }
 
-                let json = simd_json::to_string(&filter_set)?;
-                let _: () = conn.set(&key, &json)?;
+    async fn set_filter_snapshot(&self, snapshot: FilterSnapshot) -> Result<(), anyhow::Error> {
+        tracing::trace!(?snapshot, "setting filter snapshot in redis");
 
-                Ok(())
-            }
+        let mut conn = self.connection.clone();
+
+        let key = Self::get_snapshot_key(snapshot.filter_set.channel_id);
+        let json = simd_json::to_string(&snapshot)?;
+        let ttl = (snapshot.expires_at() - chrono::Utc::now()).num_seconds();
+        if ttl <= 0 {
+            return Ok(());
+        }
+
+        let _: () = conn.set_ex(&key, json, ttl as u64).await?;
+
+        Ok(())
+    }
+
+    async fn get_filter_snapshot(&self, channel_id: u64) -> Result<FilterSnapshot, anyhow::Error> {
+        tracing::debug!(channel_id, "getting filter snapshot from redis");
+
+        let mut conn = self.connection.clone();
+
+        let data: Option<String> = conn.get(Self::get_snapshot_key(channel_id)).await?;
+
+        match data {
+            Some(json) => Ok(simd_json::from_slice(&mut json.into_bytes())?),
             None => Err(anyhow::anyhow!(
-                "filter set not found for channel {}",
-                channel_id
+                "filter snapshot not found for channel {channel_id}"
             )),
         }
     }
 
-    fn clear_filter_set(&self, channel_id: u64) -> Result<(), anyhow::Error> {
-        tracing::debug!(channel_id, "clearing filter set from redis");
+    async fn remove_filter_snapshot(&self, channel_id: u64) -> Result<(), anyhow::Error> {
+        tracing::debug!(channel_id, "removing filter snapshot from redis");
 
-        let mut conn = self
-            .connection
-            .lock()
-            .map_err(|_| anyhow::anyhow!("failed to acquire connection lock"))?;
+        let mut conn = self.connection.clone();
 
-        let key = Self::get_key(channel_id);
+        let _: () = conn.del(Self::get_snapshot_key(channel_id)).await?;
 
-        // Remove the filter set
-        let _: usize = conn.del(&key)?;
+        Ok(())
+    }
+
+    async fn record_change(&self, change: Change) -> Result<(), anyhow::Error> {
+        tracing::trace!(?change, "recording change in redis");
+
+        let mut conn = self.connection.clone();
 
-        // Remove from the index set
-        let _: usize = conn.srem(FILTER_SET_INDEX_KEY, channel_id)?;
+        let key = Self::get_changes_key(change.channel_id);
+        let json = simd_json::to_string(&change)?;
+
+        let _: () = redis::pipe()
+            .atomic()
+            .lpush(&key, json)
+            .ignore()
+            .ltrim(&key, 0, audit::MAX_CHANGES as isize - 1)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
 
         Ok(())
     }
+
+    async fn list_changes(
+        &self,
+        channel_id: u64,
+        limit: usize,
+    ) -> Result<Vec<Change>, anyhow::Error> {
+        tracing::debug!(channel_id, limit, "listing changes from redis");
+
+        if limit == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut conn = self.connection.clone();
+
+        // No more than MAX_CHANGES are kept, which also keeps the range in
+        // bounds
+        let last = limit.min(audit::MAX_CHANGES) as isize - 1;
+        let data: Vec<String> = conn
+            .lrange(Self::get_changes_key(channel_id), 0, last)
+            .await?;
+
+        data.into_iter()
+            .map(|json| Ok(simd_json::from_slice(&mut json.into_bytes())?))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -217,13 +398,15 @@ mod tests {
 
     // Note: These tests require a running Redis instance
     // Run with: cargo test --features redis-tests -- --ignored
-    #[test]
+    #[tokio::test]
     #[ignore]
-    fn test_redis_store() {
-        let store = Store::new("redis://127.0.0.1:6379").expect("Failed to connect to Redis");
+    async fn test_redis_store() {
+        let store = Store::new("redis://127.0.0.1:6379")
+            .await
+            .expect("Failed to connect to Redis");
 
         // Clean up any existing test data
-        let _ = store.clear_filter_set(20);
+        let _ = store.clear_filter_set(20).await;
 
         // Test setting and getting filter sets
         store
@@ -231,18 +414,24 @@ mod tests {
                 guild_id: 1,
                 channel_id: 20,
                 filters: vec!["filter1".to_string(), "filter2".to_string()],
+                webhook: None,
+                discord_webhook: None,
+                template: None,
+                disabled: None,
+                config_filters: vec![],
             })
+            .await
             .unwrap();
 
-        let filter_set = store.get_channel_filter_set(20).unwrap();
+        let filter_set = store.get_channel_filter_set(20).await.unwrap();
         assert_eq!(
             filter_set.filters,
             vec!["filter1".to_string(), "filter2".to_string()]
         );
 
         // Test adding a filter to a set
-        store.add_filter_to_set(1, 20, "filter3").unwrap();
-        let filter_set = store.get_channel_filter_set(20).unwrap();
+        store.add_filter_to_set(1, 20, "filter3").await.unwrap();
+        let filter_set = store.get_channel_filter_set(20).await.unwrap();
         assert_eq!(
             filter_set.filters,
             vec![
@@ -253,19 +442,173 @@ mod tests {
         );
 
         // Test removing a filter from a set
-        store.remove_filter_from_set(20, "filter2").unwrap();
-        let filter_set = store.get_channel_filter_set(20).unwrap();
+        store.remove_filter_from_set(20, "filter2").await.unwrap();
+        let filter_set = store.get_channel_filter_set(20).await.unwrap();
         assert_eq!(
             filter_set.filters,
             vec!["filter1".to_string(), "filter3".to_string()]
         );
 
+        // Test editing filters by index
+        store.add_filter_to_set(1, 20, "filter4").await.unwrap();
+        assert_eq!(
+            store.replace_filter_at(20, 0, "filter5").await.unwrap(),
+            "filter1"
+        );
+        store.move_filter(20, 2, 0).await.unwrap();
+        assert_eq!(store.remove_filter_at(20, 1).await.unwrap(), "filter5");
+        let filter_set = store.get_channel_filter_set(20).await.unwrap();
+        assert_eq!(
+            filter_set.filters,
+            vec!["filter4".to_string(), "filter3".to_string()]
+        );
+        assert!(store.remove_filter_at(20, 2).await.is_err());
+
         // Test listing filter sets
-        let all_filter_sets = store.list_filter_sets().unwrap();
+        let all_filter_sets = store.list_filter_sets().await.unwrap();
         assert!(all_filter_sets.iter().any(|fs| fs.channel_id == 20));
+        let guild_sets = store.list_guild_filter_sets(1).await.unwrap();
+        assert!(guild_sets.iter().all(|fs| fs.guild_id == 1));
+        assert!(guild_sets.iter().any(|fs| fs.channel_id == 20));
 
         // Test clearing filter set
-        store.clear_filter_set(20).unwrap();
-        assert!(store.get_channel_filter_set(20).is_err());
+        store.clear_filter_set(20).await.unwrap();
+        assert!(store.get_channel_filter_set(20).await.is_err());
+
+        // Test setting several filter sets at once
+        store
+            .set_filter_sets(vec![
+                FilterSet {
+                    guild_id: 1,
+                    channel_id: 21,
+                    filters: vec!["filter1".to_string()],
+                    webhook: None,
+                    discord_webhook: None,
+                    template: None,
+                    disabled: None,
+                    config_filters: vec![],
+                },
+                FilterSet {
+                    guild_id: 1,
+                    channel_id: 22,
+                    filters: vec!["filter2".to_string()],
+                    webhook: None,
+                    discord_webhook: None,
+                    template: None,
+                    disabled: None,
+                    config_filters: vec![],
+                },
+            ])
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_channel_filter_set(22).await.unwrap().filters,
+            vec!["filter2"]
+        );
+        store.clear_filter_set(21).await.unwrap();
+        store.clear_filter_set(22).await.unwrap();
+
+        // Test saving and removing presets
+        let _ = store.remove_filter_preset(1, "home-defence").await;
+        let preset = FilterPreset {
+            guild_id: 1,
+            name: "home-defence".to_string(),
+            filters: vec!["filter1".to_string()],
+        };
+        store.set_filter_preset(preset.clone()).await.unwrap();
+        assert_eq!(
+            store.get_filter_preset(1, "home-defence").await.unwrap(),
+            preset
+        );
+        assert!(
+            store
+                .list_filter_presets(1)
+                .await
+                .unwrap()
+                .contains(&preset)
+        );
+        store.remove_filter_preset(1, "home-defence").await.unwrap();
+        assert!(store.get_filter_preset(1, "home-defence").await.is_err());
+
+        // Test snapshots
+        let filter_set = FilterSet {
+            guild_id: 1,
+            channel_id: 20,
+            filters: vec!["filter1".to_string()],
+            webhook: None,
+            discord_webhook: None,
+            template: None,
+            disabled: None,
+            config_filters: vec![],
+        };
+        store
+            .set_filter_snapshot(FilterSnapshot::new(filter_set.clone()))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_filter_snapshot(20).await.unwrap().filter_set,
+            filter_set
+        );
+        store.remove_filter_snapshot(20).await.unwrap();
+        assert!(store.get_filter_snapshot(20).await.is_err());
+
+        // Test recording changes
+        let _: () = store
+            .connection
+            .clone()
+            .del(Store::get_changes_key(20))
+            .await
+            .unwrap();
+        for after in ["filter1", "filter2"] {
+            store
+                .record_change(Change {
+                    guild_id: 1,
+                    channel_id: 20,
+                    user_id: 2,
+                    user_name: "pilot".to_string(),
+                    command: "filter-add".to_string(),
+                    at: chrono::Utc::now(),
+                    before: vec![],
+                    after: vec![after.to_string()],
+                    settings: vec![],
+                })
+                .await
+                .unwrap();
+        }
+        let changes = store.list_changes(20, 10).await.unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].after, vec!["filter2"]);
+        assert_eq!(store.list_changes(20, 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_concurrent_writes_keep_every_filter() {
+        let store = Store::new("redis://127.0.0.1:6379")
+            .await
+            .expect("Failed to connect to Redis");
+        let _ = store.clear_filter_set(21).await;
+        let _ = store.clear_filter_set(22).await;
+
+        let writes = (0..20).map(|index| {
+            let store = store.clone();
+            tokio::spawn(async move {
+                let channel_id = 21 + index % 2;
+                store
+                    .add_filter_to_set(1, channel_id, &format!("filter{index}"))
+                    .await
+            })
+        });
+        for write in writes.collect::<Vec<_>>() {
+            write.await.unwrap().unwrap();
+        }
+
+        for channel_id in [21, 22] {
+            let filter_set = store.get_channel_filter_set(channel_id).await.unwrap();
+            assert_eq!(filter_set.filters.len(), 10);
+            store.clear_filter_set(channel_id).await.unwrap();
+        }
+        // Idle locks are dropped by the next update
+        assert_eq!(store.writes.lock().unwrap().len(), 1);
     }
 }
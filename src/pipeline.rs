@@ -0,0 +1,296 @@
+use opentelemetry::trace::Status;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::{
+    filters::{self, FilterSet},
+    notifier::{Notifier, Unreachable},
+    persistence::{self, cache::Cache},
+    template::Template,
+    webhook, zkb,
+};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(2);
+const DEDUPE_TTL: Duration = Duration::from_secs(10800);
+// Channels are disabled after this many deliveries in a row were refused
+const MAX_UNREACHABLE: u32 = 3;
+
+// A killmail that matched a channel's filters and should be sent there
+#[derive(Debug)]
+pub struct Delivery {
+    pub killmail: Arc<zkb::Killmail>,
+    pub channel_id: u64,
+    pub side: Option<filters::KillmailSide>,
+    pub webhook: Option<filters::Webhook>,
+    pub discord_webhook: Option<filters::DiscordWebhook>,
+    pub template: Option<Template>,
+}
+
+pub struct Pipeline {
+    client: reqwest::Client,
+    store: Arc<dyn persistence::Store>,
+    notifier: Arc<dyn Notifier>,
+    webhooks: webhook::Sender,
+    cache: Option<Cache>,
+    listen_url: String,
+    interval: Duration,
+    // deliveries refused in a row, by channel
+    unreachable: Mutex<HashMap<u64, u32>>,
+}
+
+impl Pipeline {
+    pub fn new(
+        client: reqwest::Client,
+        store: Arc<dyn persistence::Store>,
+        notifier: Arc<dyn Notifier>,
+        cache: Option<Cache>,
+        redisq_url: &str,
+        queue_id: &str,
+    ) -> Self {
+        Self {
+            webhooks: webhook::Sender::new(client.clone()),
+            client,
+            store,
+            notifier,
+            cache,
+            listen_url: format!(
+                "{}/listen.php?queueID={queue_id}&",
+                redisq_url.trim_end_matches('/')
+            ),
+            interval: DEFAULT_INTERVAL,
+            unreachable: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Overrides the pause between two RedisQ polls
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    // Polls RedisQ until cancelled, notifying every channel a killmail matched
+    pub async fn run(&self, cancel_token: CancellationToken) {
+        let mut running = false;
+        loop {
+            if running {
+                tokio::select! {
+                    _ = tokio::time::sleep(self.interval) => {}
+                    _ = cancel_token.cancelled() => {
+                        tracing::info!("shutdown signal received, exiting main loop");
+                        break;
+                    }
+                }
+            }
+            running = true;
+
+            if cancel_token.is_cancelled() {
+                break;
+            }
+
+            let request_span: Span = tracing::span!(Level::INFO, "sending request");
+            let deliveries = match self.process_next().instrument(request_span.clone()).await {
+                Ok(deliveries) => deliveries,
+                Err(e) => {
+                    request_span.set_status(Status::error(e.to_string()));
+                    tracing::error!(parent: &request_span, error = e.to_string(), "failed to process killmail");
+                    continue;
+                }
+            };
+
+            let mut status = Status::Ok;
+            for delivery in deliveries {
+                let result = match &delivery.webhook {
+                    Some(webhook) => {
+                        self.webhooks
+                            .send(
+                                &request_span,
+                                webhook,
+                                &delivery.killmail,
+                                delivery.side.clone(),
+                            )
+                            .await
+                    }
+                    None => self.notifier.notify(&request_span, &delivery).await,
+                };
+
+                match result {
+                    Ok(_) => self.reachable(delivery.channel_id),
+                    Err(e) => {
+                        tracing::error!(
+                            parent: &request_span,
+                            channel_id = delivery.channel_id,
+                            error = e.to_string(),
+                            "failed to embed killmail"
+                        );
+                        status = Status::error(format!("failed to embed killmail: {e}"));
+
+                        if let Some(unreachable) = e.downcast_ref::<Unreachable>() {
+                            self.refused(delivery.channel_id, unreachable).await;
+                        }
+                    }
+                }
+            }
+
+            request_span.set_status(status);
+        }
+    }
+
+    fn reachable(&self, channel_id: u64) {
+        if let Ok(mut unreachable) = self.unreachable.lock() {
+            unreachable.remove(&channel_id);
+        }
+    }
+
+    // Counts a refused delivery and disables the channel once too many were
+    // refused in a row, so a deleted channel doesn't fail every matched kill
+    async fn refused(&self, channel_id: u64, error: &Unreachable) {
+        let count = match self.unreachable.lock() {
+            Ok(mut unreachable) => {
+                let count = unreachable.entry(channel_id).or_default();
+                *count += 1;
+                *count
+            }
+            Err(_) => return,
+        };
+
+        if count < MAX_UNREACHABLE {
+            return;
+        }
+
+        let disabled = filters::Disabled {
+            reason: error.to_string(),
+            at: chrono::Utc::now(),
+        };
+
+        match self.store.disable_filter_set(channel_id, disabled).await {
+            Ok(_) => {
+                tracing::warn!(
+                    channel_id,
+                    count,
+                    reason = error.to_string(),
+                    "disabled unreachable channel"
+                );
+                self.reachable(channel_id);
+            }
+            Err(e) => {
+                tracing::error!(
+                    channel_id,
+                    error = e.to_string(),
+                    "failed to disable filter set"
+                );
+            }
+        }
+    }
+
+    // Fetches the next package from RedisQ and returns the channels it has to be sent to
+    pub async fn process_next(&self) -> Result<Vec<Delivery>, anyhow::Error> {
+        let response = self
+            .client
+            .get(&self.listen_url)
+            .send()
+            .await
+            .map_err(|e| anyhow::format_err!("failed to send request: {e}"))?;
+
+        let raw = response
+            .text()
+            .await
+            .map_err(|e| anyhow::format_err!("failed to read response body: {e}"))?;
+
+        let response = match simd_json::from_slice::<zkb::Response>(&mut raw.clone().into_bytes()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                tracing::error!(raw, error = e.to_string(), "failed to parse response JSON");
+                return Err(anyhow::format_err!("failed to parse response JSON: {e}"));
+            }
+        };
+
+        let Some(mut killmail) = response.killmail else {
+            tracing::debug!("dropped empty killmail");
+            return Ok(vec![]);
+        };
+
+        killmail
+            .fetch_data()
+            .await
+            .map_err(|e| anyhow::format_err!("failed to fetch killmail data: {e}"))?;
+
+        if killmail.killmail.is_none() {
+            tracing::debug!("dropped null killmail");
+            return Ok(vec![]);
+        }
+
+        let time_divergence = killmail.skew();
+
+        let filter_sets = self
+            .store
+            .list_filter_sets()
+            .await
+            .map_err(|e| anyhow::format_err!("failed to get filter sets: {e}"))?
+            .into_iter()
+            .filter(|set| set.disabled.is_none())
+            .collect::<Vec<FilterSet>>();
+
+        if filter_sets.is_empty() {
+            tracing::debug!("no filter sets found, skipping killmail");
+            return Ok(vec![]);
+        }
+
+        let targets = filter_sets
+            .iter()
+            .map(|set| (set.channel_id, set.clone()))
+            .collect::<HashMap<u64, FilterSet>>();
+
+        let mut filter_config = filters::Config {
+            filter_sets,
+            ..Default::default()
+        };
+
+        let channels = filter_config
+            .filter(&killmail)
+            .map_err(|e| anyhow::format_err!("failed to filter killmail: {e}"))?;
+
+        tracing::info!(
+            channel_len = channels.len(),
+            time_divergence_s = format!("{}", time_divergence.num_seconds()),
+            time_divergence_ms = format!("{}", time_divergence.num_milliseconds()),
+            time_divergence_m = format!("{}", time_divergence.num_minutes()),
+            "ran killmail through filters"
+        );
+
+        let killmail = Arc::new(killmail);
+        let mut deliveries = vec![];
+        for (channel_id, side) in channels {
+            tracing::info!(channel_id, "matched filter");
+            if let Some(cache) = &self.cache {
+                let cache_key = format!("kill:{channel_id}:{}", killmail.kill_id);
+                if let Ok(hit) = cache.check(&cache_key).await
+                    && hit
+                {
+                    continue;
+                }
+
+                if let Err(e) = cache.store(&cache_key, Some(DEDUPE_TTL)).await {
+                    tracing::error!(error = e.to_string(), "failed to store killmail in cache");
+                }
+            }
+
+            let target = targets.get(&channel_id);
+            deliveries.push(Delivery {
+                killmail: Arc::clone(&killmail),
+                webhook: target.and_then(|t| t.webhook.clone()),
+                discord_webhook: target.and_then(|t| t.discord_webhook.clone()),
+                template: target.and_then(|t| t.template.clone()),
+                channel_id,
+                side,
+            });
+        }
+
+        Ok(deliveries)
+    }
+}
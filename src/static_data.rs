@@ -12,7 +12,7 @@ pub struct System {
     pub region_id: u64,
     pub _constellation_id: u64,
     pub system_id: u64,
-    pub _name: String,
+    pub name: String,
 }
 
 impl From<SystemRow> for System {
@@ -21,7 +21,23 @@ impl From<SystemRow> for System {
             region_id: row.0,
             _constellation_id: row.1,
             system_id: row.2,
-            _name: row.3,
+            name: row.3,
+        }
+    }
+}
+
+type RegionRow = (u64, String);
+
+pub struct Region {
+    pub region_id: u64,
+    pub name: String,
+}
+
+impl From<RegionRow> for Region {
+    fn from(row: RegionRow) -> Self {
+        Region {
+            region_id: row.0,
+            name: row.1,
         }
     }
 }
@@ -47,8 +63,95 @@ lazy_static! {
 
         systems
     };
+    pub static ref REGIONS_DATA: HashMap<u64, Region> = {
+        let region_rows: Vec<RegionRow> = csv::Reader::from_reader(
+            Data::get("mapRegionsTrimmed.csv")
+                .expect("Failed to load regions.csv")
+                .data
+                .as_ref(),
+        )
+        .deserialize()
+        .map(|result| result.expect("Failed to parse CSV row"))
+        .collect();
+
+        region_rows
+            .into_iter()
+            .map(|row| (row.0, Region::from(row)))
+            .collect()
+    };
 }
 
 pub fn get_region_by_system_id(system_id: u64) -> Option<u64> {
     SYSTEMS_DATA.get(&system_id).map(|s| s.region_id)
 }
+
+pub fn get_system(system_id: u64) -> Option<&'static System> {
+    SYSTEMS_DATA.get(&system_id)
+}
+
+// Only known space regions are named, wormhole and abyssal regions are not
+pub fn get_region(region_id: u64) -> Option<&'static Region> {
+    REGIONS_DATA.get(&region_id)
+}
+
+// Systems whose name contains the query, names starting with it first
+pub fn search_systems(query: &str, limit: usize) -> Vec<&'static System> {
+    search(SYSTEMS_DATA.values(), |s| &s.name, query, limit)
+}
+
+// Regions whose name contains the query, names starting with it first
+pub fn search_regions(query: &str, limit: usize) -> Vec<&'static Region> {
+    search(REGIONS_DATA.values(), |r| &r.name, query, limit)
+}
+
+fn search<T>(
+    items: impl Iterator<Item = &'static T>,
+    name: impl Fn(&T) -> &str,
+    query: &str,
+    limit: usize,
+) -> Vec<&'static T> {
+    let query = query.to_lowercase();
+
+    let mut matches = items
+        .filter_map(|item| {
+            let lower = name(item).to_lowercase();
+            lower
+                .find(&query)
+                .map(|position| (position != 0, lower, item))
+        })
+        .collect::<Vec<_>>();
+    matches.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, _, item)| item)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_region() {
+        assert_eq!(
+            get_region(10000002).map(|r| r.name.as_str()),
+            Some("The Forge")
+        );
+        assert!(get_region(11000001).is_none());
+    }
+
+    #[test]
+    fn test_search() {
+        let systems = search_systems("jita", 5);
+        assert_eq!(systems.len(), 1);
+        assert_eq!(systems[0].system_id, 30000142);
+
+        let regions = search_regions("de", 3)
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(regions, vec!["Deklein", "Delve", "Derelik"]);
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+// Placeholders available to templates, see discord::embed::Builder::context
+pub const PLACEHOLDERS: &[&str] = &[
+    "kill.id",
+    "kill.url",
+    "time",
+    "value",
+    "system",
+    "system.security",
+    "region",
+    "victim.name",
+    "victim.corp",
+    "victim.alliance",
+    "victim.ship",
+    "attackers",
+    "final_blow.name",
+    "final_blow.ship",
+    "top_damage.name",
+    "top_damage.ship",
+];
+
+const COMPACT_TITLE: &str = "{victim.ship} destroyed in {system} ({value} ISK)";
+
+pub const MAX_TITLE_LENGTH: usize = 256;
+pub const MAX_DESCRIPTION_LENGTH: usize = 4096;
+
+// Template decides how the embed for a channel is laid out
+#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone, Default)]
+#[serde(rename_all = "lowercase", tag = "kind")]
+pub enum Template {
+    // Every detail we have about the kill, as fields
+    #[default]
+    Full,
+    // A single line title linking to the kill
+    Compact,
+    // User-defined title and description with placeholders
+    Custom {
+        title: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+    },
+}
+
+impl Template {
+    pub fn preset(name: &str) -> Result<Self, anyhow::Error> {
+        match name {
+            "full" => Ok(Template::Full),
+            "compact" => Ok(Template::Compact),
+            _ => Err(anyhow::anyhow!("unknown template preset: {name}")),
+        }
+    }
+
+    pub fn custom(title: String, description: Option<String>) -> Result<Self, anyhow::Error> {
+        validate(&title, MAX_TITLE_LENGTH)?;
+        if let Some(description) = &description {
+            validate(description, MAX_DESCRIPTION_LENGTH)?;
+        }
+
+        Ok(Template::Custom { title, description })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Template::Full => "full",
+            Template::Compact => "compact",
+            Template::Custom { .. } => "custom",
+        }
+    }
+
+    // Title and description to render, None for the full layout
+    pub fn layout(&self) -> Option<(&str, Option<&str>)> {
+        match self {
+            Template::Full => None,
+            Template::Compact => Some((COMPACT_TITLE, None)),
+            Template::Custom { title, description } => Some((title, description.as_deref())),
+        }
+    }
+}
+
+// Replaces every known `{placeholder}` in the template, unknown ones are kept as-is
+pub fn render(template: &str, context: &HashMap<&str, String>) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        let Some(end) = after.find('}') else {
+            output.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+
+        let key = &after[..end];
+        match context.get(key) {
+            Some(value) => output.push_str(value),
+            None => output.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &after[end + 1..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+pub fn truncate(value: String, max: usize) -> String {
+    if value.chars().count() <= max {
+        return value;
+    }
+
+    let mut truncated = value.chars().take(max - 1).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+fn validate(template: &str, max: usize) -> Result<(), anyhow::Error> {
+    if template.trim().is_empty() {
+        return Err(anyhow::anyhow!("template must not be empty"));
+    }
+
+    if template.chars().count() > max {
+        return Err(anyhow::anyhow!(
+            "template must be at most {max} characters long"
+        ));
+    }
+
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            break;
+        };
+
+        let key = &after[..end];
+        if !PLACEHOLDERS.contains(&key) {
+            return Err(anyhow::anyhow!("unknown placeholder: {{{key}}}"));
+        }
+        rest = &after[end + 1..];
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let context = HashMap::from([
+            ("victim.name", "Raven X".to_string()),
+            ("value", "1.25b".to_string()),
+        ]);
+
+        assert_eq!(
+            render("{victim.name} lost {value} ISK", &context),
+            "Raven X lost 1.25b ISK"
+        );
+        assert_eq!(render("{unknown} {value}", &context), "{unknown} 1.25b");
+        assert_eq!(render("dangling {value", &context), "dangling {value");
+        assert_eq!(render("no placeholders", &context), "no placeholders");
+    }
+
+    #[test]
+    fn test_custom_template_validation() {
+        assert!(Template::custom("{victim.name} in {system}".to_string(), None).is_ok());
+        assert!(Template::custom("{victim.nmae}".to_string(), None).is_err());
+        assert!(Template::custom("   ".to_string(), None).is_err());
+        assert!(
+            Template::custom(
+                "{victim.ship}".to_string(),
+                Some("x".repeat(MAX_DESCRIPTION_LENGTH + 1))
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_template_serialization() {
+        let template = Template::custom("{victim.ship}".to_string(), None).unwrap();
+        let json = simd_json::to_string(&template).unwrap();
+        assert_eq!(json, r#"{"kind":"custom","title":"{victim.ship}"}"#);
+
+        let parsed: Template = simd_json::from_slice(&mut json.into_bytes()).unwrap();
+        assert_eq!(parsed, template);
+
+        let parsed: Template =
+            simd_json::from_slice(&mut r#"{"kind":"compact"}"#.to_string().into_bytes()).unwrap();
+        assert_eq!(parsed, Template::Compact);
+    }
+}
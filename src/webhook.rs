@@ -0,0 +1,156 @@
+use hmac::{Hmac, Mac};
+use opentelemetry::trace::Status;
+use tracing::{Instrument, Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::{filters, notifier::Unreachable, static_data, zkb};
+
+pub const SIGNATURE_HEADER: &str = "X-Krusty-Signature";
+
+// Payload is the JSON body posted to webhook targets
+#[derive(Debug, serde::Serialize)]
+pub struct Payload<'a> {
+    pub kill_id: u64,
+    pub side: Option<filters::KillmailSide>,
+    pub value: f64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub system_id: u64,
+    pub region_id: Option<u64>,
+    pub victim: &'a zkb::Participant,
+    pub attackers: &'a [zkb::Participant],
+}
+
+impl<'a> Payload<'a> {
+    pub fn new(
+        killmail: &'a zkb::Killmail,
+        side: Option<filters::KillmailSide>,
+    ) -> Result<Self, anyhow::Error> {
+        let Some(data) = &killmail.killmail else {
+            return Err(anyhow::anyhow!("killmail has no data to send"));
+        };
+
+        Ok(Self {
+            kill_id: killmail.kill_id,
+            side,
+            value: killmail.zkb.total_value,
+            timestamp: data.timestamp,
+            system_id: data.system_id,
+            region_id: static_data::get_region_by_system_id(data.system_id),
+            victim: &data.victim,
+            attackers: &data.attackers,
+        })
+    }
+}
+
+// Sender posts matched killmails to generic HTTP webhooks
+#[derive(Clone)]
+pub struct Sender {
+    client: reqwest::Client,
+}
+
+impl Sender {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn send(
+        &self,
+        parent: &Span,
+        webhook: &filters::Webhook,
+        killmail: &zkb::Killmail,
+        side: Option<filters::KillmailSide>,
+    ) -> Result<(), anyhow::Error> {
+        let span = tracing::span!(Level::INFO, "sending killmail to webhook");
+        let _ = span.set_parent(parent.context());
+
+        let body = simd_json::to_string(&Payload::new(killmail, side)?)?;
+
+        let mut request = self
+            .client
+            .post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+
+        if let Some(secret) = &webhook.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, body.as_bytes())?);
+        }
+
+        match request
+            .body(body)
+            .send()
+            .instrument(span.clone())
+            .await
+            .and_then(|resp| resp.error_for_status())
+        {
+            Ok(_) => {
+                tracing::info!(parent: &span, kill_id = killmail.kill_id, "sent killmail to webhook");
+                Ok(())
+            }
+            Err(e) => {
+                span.set_status(Status::error(format!("failed to send webhook: {e}")));
+
+                if let Some(unreachable) = e.status().and_then(|status| {
+                    Unreachable::from_status(
+                        status.as_u16(),
+                        format!("failed to send webhook: {e}"),
+                    )
+                }) {
+                    return Err(unreachable.into());
+                }
+
+                Err(anyhow::anyhow!("failed to send webhook: {e}"))
+            }
+        }
+    }
+}
+
+// Signs the body with HMAC-SHA256, formatted as `sha256=<hex digest>`
+pub fn sign(secret: &str, body: &[u8]) -> Result<String, anyhow::Error> {
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|e| anyhow::anyhow!("invalid webhook secret: {e}"))?;
+    mac.update(body);
+
+    Ok(format!("sha256={:x}", mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        // Reference value from RFC 4231, test case 2
+        let signature = sign("Jefe", b"what do ya want for nothing?").unwrap();
+        assert_eq!(
+            signature,
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_payload() {
+        let killmail = zkb::Killmail {
+            kill_id: 1,
+            zkb: zkb::Zkb {
+                total_value: 1500000.0,
+                ..Default::default()
+            },
+            killmail: Some(zkb::KillmailData {
+                system_id: 30000142,
+                victim: zkb::Participant {
+                    character_id: Some(2),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+        };
+
+        let payload = Payload::new(&killmail, Some(filters::KillmailSide::Victim)).unwrap();
+        assert_eq!(payload.kill_id, 1);
+        assert_eq!(payload.value, 1500000.0);
+        assert_eq!(payload.region_id, Some(10000002));
+
+        let json = simd_json::to_string(&payload).unwrap();
+        assert!(json.contains(r#""side":"victim""#));
+        assert!(json.contains(r#""character_id":2"#));
+    }
+}
@@ -8,9 +8,16 @@ pub struct Response {
     pub killmail: Option<Killmail>,
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Default, serde::Deserialize)]
 pub struct Zkb {
+    #[serde(default)]
     pub href: String,
+    #[serde(default, rename = "totalValue")]
+    pub total_value: f64,
+    #[serde(default)]
+    pub npc: bool,
+    #[serde(default)]
+    pub solo: bool,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -18,16 +25,25 @@ pub struct Killmail {
     #[serde(rename = "killID")]
     pub kill_id: u64,
     pub zkb: Zkb,
-    #[serde(skip)]
+    // Newer RedisQ/R2Z2 packages embed the ESI killmail, older ones only
+    // provide `zkb.href` and we have to fetch it ourselves
+    #[serde(default, rename = "esi", alias = "killmail")]
     pub killmail: Option<KillmailData>,
 }
 
 impl Killmail {
     pub async fn fetch_data(&mut self) -> Result<(), reqwest::Error> {
         if self.killmail.is_some() {
+            tracing::trace!(kill_id = self.kill_id, "using embedded killmail data");
             return Ok(());
         }
 
+        tracing::debug!(
+            kill_id = self.kill_id,
+            href = self.zkb.href,
+            "killmail data not embedded, fetching from ESI"
+        );
+
         let resp = reqwest::get(&self.zkb.href).await;
         match resp {
             Ok(response) => {
@@ -83,12 +99,18 @@ impl Default for KillmailData {
     }
 }
 
-#[derive(Debug, Default, serde::Deserialize)]
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
 pub struct Participant {
     pub character_id: Option<u64>,
     pub corporation_id: Option<u64>,
     pub alliance_id: Option<u64>,
     pub ship_type_id: Option<u64>,
+    #[serde(default)]
+    pub final_blow: bool,
+    #[serde(default)]
+    pub damage_done: u64,
+    #[serde(default)]
+    pub damage_taken: u64,
 }
 
 impl Participant {
@@ -96,3 +118,76 @@ impl Participant {
         self.character_id.is_none()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_embedded_killmail() {
+        let raw = r#"{
+            "package": {
+                "killID": 130678514,
+                "esi": {
+                    "killmail_id": 130678514,
+                    "killmail_time": "2025-10-12T18:04:11Z",
+                    "solar_system_id": 30000142,
+                    "victim": {
+                        "character_id": 2112625428,
+                        "corporation_id": 98190062,
+                        "ship_type_id": 670
+                    },
+                    "attackers": [
+                        {
+                            "character_id": 95465499,
+                            "corporation_id": 1000169,
+                            "alliance_id": 99003581,
+                            "ship_type_id": 33475
+                        }
+                    ]
+                },
+                "zkb": {
+                    "href": "https://esi.evetech.net/v1/killmails/130678514/145c457c34ce9c9e8d67e942e764d8f439b22271/"
+                }
+            }
+        }"#;
+
+        let response: Response =
+            simd_json::from_slice(&mut raw.to_string().into_bytes()).expect("failed to parse");
+        let killmail = response.killmail.expect("expected a package");
+        assert_eq!(killmail.kill_id, 130678514);
+
+        let data = killmail.killmail.expect("expected embedded killmail data");
+        assert_eq!(data.system_id, 30000142);
+        assert_eq!(data.victim.corporation_id, Some(98190062));
+        assert_eq!(data.attackers.len(), 1);
+        assert_eq!(data.attackers[0].alliance_id, Some(99003581));
+    }
+
+    #[test]
+    fn test_deserialize_href_only_killmail() {
+        let raw = r#"{
+            "package": {
+                "killID": 130678514,
+                "zkb": {
+                    "href": "https://esi.evetech.net/v1/killmails/130678514/145c457c34ce9c9e8d67e942e764d8f439b22271/"
+                }
+            }
+        }"#;
+
+        let response: Response =
+            simd_json::from_slice(&mut raw.to_string().into_bytes()).expect("failed to parse");
+        let killmail = response.killmail.expect("expected a package");
+        assert!(killmail.killmail.is_none());
+        assert!(!killmail.zkb.href.is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_null_package() {
+        let raw = r#"{"package": null}"#;
+
+        let response: Response =
+            simd_json::from_slice(&mut raw.to_string().into_bytes()).expect("failed to parse");
+        assert!(response.killmail.is_none());
+    }
+}
@@ -0,0 +1,110 @@
+use simd_json::json;
+
+// Participant describes a victim or attacker in a fixture killmail
+#[derive(Clone, Copy, Default)]
+pub struct Participant {
+    pub character_id: Option<u64>,
+    pub corporation_id: Option<u64>,
+    pub alliance_id: Option<u64>,
+    pub ship_type_id: Option<u64>,
+    pub damage: u64,
+    pub final_blow: bool,
+}
+
+impl Participant {
+    fn to_json(self) -> simd_json::OwnedValue {
+        json!({
+            "character_id": self.character_id,
+            "corporation_id": self.corporation_id,
+            "alliance_id": self.alliance_id,
+            "ship_type_id": self.ship_type_id,
+            "damage_done": self.damage,
+            "damage_taken": self.damage,
+            "final_blow": self.final_blow,
+        })
+    }
+}
+
+pub fn esi_killmail(
+    kill_id: u64,
+    system_id: u64,
+    victim: Participant,
+    attackers: &[Participant],
+) -> String {
+    let attackers = attackers
+        .iter()
+        .map(|a| a.to_json())
+        .collect::<Vec<simd_json::OwnedValue>>();
+
+    simd_json::to_string(&json!({
+        "killmail_id": kill_id,
+        "killmail_time": "2025-10-12T18:04:11Z",
+        "solar_system_id": system_id,
+        "victim": victim.to_json(),
+        "attackers": attackers,
+    }))
+    .expect("failed to serialize killmail fixture")
+}
+
+// RedisQ package carrying the ESI killmail inline
+pub fn embedded_package(
+    kill_id: u64,
+    system_id: u64,
+    victim: Participant,
+    attackers: &[Participant],
+) -> String {
+    format!(
+        r#"{{"package":{{"killID":{kill_id},"esi":{},"zkb":{{"href":""}}}}}}"#,
+        esi_killmail(kill_id, system_id, victim, attackers)
+    )
+}
+
+// RedisQ package which only references the killmail on ESI
+pub fn href_package(kill_id: u64, href: &str) -> String {
+    format!(r#"{{"package":{{"killID":{kill_id},"zkb":{{"href":"{href}"}}}}}}"#)
+}
+
+// ESI universe data for the systems and regions used in tests
+pub fn universe(path: &str) -> Option<String> {
+    let body = match path {
+        "/universe/systems/30000142/" => {
+            r#"{"system_id":30000142,"name":"Jita","security_status":0.9459131360054016,"constellation_id":20000020}"#
+        }
+        "/universe/systems/30002187/" => {
+            r#"{"system_id":30002187,"name":"Amarr","security_status":1.0,"constellation_id":20000322}"#
+        }
+        "/universe/regions/10000002/" => r#"{"region_id":10000002,"name":"The Forge"}"#,
+        "/universe/regions/10000043/" => r#"{"region_id":10000043,"name":"Domain"}"#,
+        _ => return None,
+    };
+
+    Some(body.to_string())
+}
+
+// Names known to the fake ESI, any other id makes /universe/names/ fail the
+// whole request like the real ESI does
+const NAMES: &[(u64, &str, &str)] = &[
+    (670, "Capsule", "inventory_type"),
+    (12747, "Mastodon", "inventory_type"),
+    (33475, "Mobile Tractor Unit", "inventory_type"),
+    (2112625428, "Ava Tormund", "character"),
+    (95465499, "Jun Okabe", "character"),
+    (98000001, "Krusty Industries", "corporation"),
+    (98000002, "Forge Wardens", "corporation"),
+    (99000001, "Krusty Alliance", "alliance"),
+    (99003581, "Fraternity.", "alliance"),
+];
+
+pub fn names(body: &str) -> Option<String> {
+    let ids: Vec<u64> = simd_json::from_slice(&mut body.as_bytes().to_vec()).ok()?;
+
+    let mut names = vec![];
+    for id in ids {
+        let (id, name, category) = NAMES.iter().find(|(known, _, _)| *known == id)?;
+        names.push(format!(
+            r#"{{"id":{id},"name":"{name}","category":"{category}"}}"#
+        ));
+    }
+
+    Some(format!("[{}]", names.join(",")))
+}
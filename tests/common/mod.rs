@@ -0,0 +1,237 @@
+#![allow(dead_code)]
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+pub mod fixtures;
+
+// Reply is a canned HTTP response served by the fake server
+#[derive(Clone, Debug)]
+pub enum Reply {
+    Json(String),
+    Status(u16, String),
+}
+
+impl Reply {
+    fn null_package() -> Self {
+        Reply::Json(r#"{"package":null}"#.to_string())
+    }
+}
+
+// Request is a webhook call received by the fake server
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+#[derive(Default)]
+struct State {
+    hooks: Vec<Request>,
+    packages: VecDeque<Reply>,
+    esi: HashMap<String, Reply>,
+    listen_calls: usize,
+    killmail_calls: usize,
+    names_calls: usize,
+}
+
+// FakeServer is an in-process stand-in for both RedisQ and ESI. Packages are
+// served in the order they were queued, once the queue is drained every poll
+// receives a null package like the real RedisQ does on timeout.
+#[derive(Clone)]
+pub struct FakeServer {
+    url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind fake server");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let inner = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(Self::serve(stream, inner.clone()));
+            }
+        });
+
+        Self { url, state }
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    pub fn esi_href(&self, kill_id: u64) -> String {
+        format!("{}/killmails/{kill_id}/0123456789abcdef/", self.url)
+    }
+
+    pub fn push(&self, reply: Reply) {
+        self.state.lock().unwrap().packages.push_back(reply);
+    }
+
+    pub fn push_package(&self, package: String) {
+        self.push(Reply::Json(package));
+    }
+
+    pub fn push_null(&self) {
+        self.push(Reply::null_package());
+    }
+
+    pub fn push_malformed(&self) {
+        self.push(Reply::Json(r#"{"package": {"killID": "#.to_string()));
+    }
+
+    pub fn push_error(&self, status: u16) {
+        self.push(Reply::Status(status, "upstream error".to_string()));
+    }
+
+    pub fn set_esi(&self, kill_id: u64, reply: Reply) {
+        let path = format!("/killmails/{kill_id}/0123456789abcdef/");
+        self.state.lock().unwrap().esi.insert(path, reply);
+    }
+
+    pub fn hook_url(&self, name: &str) -> String {
+        format!("{}/hooks/{name}", self.url)
+    }
+
+    pub fn hooks(&self) -> Vec<Request> {
+        self.state.lock().unwrap().hooks.clone()
+    }
+
+    pub fn listen_calls(&self) -> usize {
+        self.state.lock().unwrap().listen_calls
+    }
+
+    pub fn killmail_calls(&self) -> usize {
+        self.state.lock().unwrap().killmail_calls
+    }
+
+    pub fn names_calls(&self) -> usize {
+        self.state.lock().unwrap().names_calls
+    }
+
+    // Waits until every queued package has been served and processed, which
+    // is the case once the pipeline comes back for one more
+    pub async fn drained(&self) {
+        let wanted = {
+            let state = self.state.lock().unwrap();
+            state.packages.len() + state.listen_calls + 1
+        };
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+        while self.listen_calls() < wanted {
+            if tokio::time::Instant::now() > deadline {
+                panic!("timed out waiting for fake server to be drained");
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    async fn serve(mut stream: TcpStream, state: Arc<Mutex<State>>) {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 1024];
+        let header_end = loop {
+            if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            }
+        };
+
+        let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+        let path = head
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .unwrap_or("/")
+            .to_string();
+        let headers = head
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.split_once(':'))
+            .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+            .collect::<HashMap<String, String>>();
+
+        let content_length = headers
+            .get("content-length")
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(0);
+        while buf.len() < header_end + content_length {
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+        let body =
+            String::from_utf8_lossy(&buf[header_end..header_end + content_length]).to_string();
+
+        let reply = {
+            let mut state = state.lock().unwrap();
+            if path.starts_with("/hooks/") {
+                state.hooks.push(Request {
+                    path: path.clone(),
+                    headers,
+                    body,
+                });
+                // Stands in for a target that was deleted
+                if path.starts_with("/hooks/gone") {
+                    Reply::Status(404, r#"{"error":"unknown webhook"}"#.to_string())
+                } else {
+                    Reply::Status(204, String::new())
+                }
+            } else if path == "/universe/names/" {
+                state.names_calls += 1;
+                fixtures::names(&body)
+                    .map(Reply::Json)
+                    .unwrap_or(Reply::Status(
+                        404,
+                        r#"{"error":"Ensure all IDs are valid before resolving."}"#.to_string(),
+                    ))
+            } else if path.starts_with("/universe/") {
+                fixtures::universe(&path)
+                    .map(Reply::Json)
+                    .unwrap_or(Reply::Status(404, r#"{"error":"not found"}"#.to_string()))
+            } else if path.starts_with("/listen.php") {
+                state.listen_calls += 1;
+                state
+                    .packages
+                    .pop_front()
+                    .unwrap_or_else(Reply::null_package)
+            } else {
+                state.killmail_calls += 1;
+                state
+                    .esi
+                    .get(&path)
+                    .cloned()
+                    .unwrap_or(Reply::Status(404, r#"{"error":"not found"}"#.to_string()))
+            }
+        };
+
+        let (status, content_type, body) = match reply {
+            Reply::Json(body) => (200, "application/json", body),
+            Reply::Status(status, body) => (status, "application/json", body),
+        };
+
+        let response = format!(
+            "HTTP/1.1 {status} Fake\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+        let _ = stream.shutdown().await;
+    }
+}
@@ -0,0 +1,58 @@
+use krusty::{esi, names};
+
+mod common;
+
+use common::FakeServer;
+
+fn resolver(server: &FakeServer) -> names::Resolver {
+    names::Resolver::new(
+        esi::Client::new(reqwest::Client::new(), server.url().as_str()),
+        None,
+    )
+}
+
+#[tokio::test]
+async fn test_names_are_resolved_in_one_batch() {
+    let server = FakeServer::start().await;
+    let resolver = resolver(&server);
+
+    let names = resolver.resolve(&[98000001, 99000001, 670, 98000001]).await;
+
+    assert_eq!(names.len(), 3);
+    assert_eq!(names[&98000001].name, "Krusty Industries");
+    assert_eq!(names[&98000001].category, "corporation");
+    assert_eq!(names[&99000001].name, "Krusty Alliance");
+    assert_eq!(names[&670].name, "Capsule");
+    assert_eq!(server.names_calls(), 1);
+}
+
+#[tokio::test]
+async fn test_unknown_ids_do_not_fail_the_batch() {
+    let server = FakeServer::start().await;
+    let resolver = resolver(&server);
+
+    let names = resolver.resolve(&[98000001, 1, 99000001, 2]).await;
+
+    let mut resolved = names.keys().copied().collect::<Vec<u64>>();
+    resolved.sort();
+    assert_eq!(resolved, vec![98000001, 99000001]);
+}
+
+#[tokio::test]
+async fn test_resolved_names_are_kept_in_memory() {
+    let server = FakeServer::start().await;
+    let resolver = resolver(&server);
+
+    resolver.resolve(&[98000001, 670]).await;
+    let calls = server.names_calls();
+
+    let names = resolver.resolve(&[670, 98000001]).await;
+
+    assert_eq!(names.len(), 2);
+    assert_eq!(server.names_calls(), calls);
+
+    // Only ids that weren't resolved before are looked up
+    let names = resolver.resolve(&[670, 99000001]).await;
+    assert_eq!(names.len(), 2);
+    assert_eq!(server.names_calls(), calls + 1);
+}
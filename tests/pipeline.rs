@@ -0,0 +1,579 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio_util::sync::CancellationToken;
+use twilight_model::{
+    channel::message::{
+        Embed,
+        embed::{EmbedAuthor, EmbedField, EmbedFooter, EmbedThumbnail},
+    },
+    util::Timestamp,
+};
+
+use krusty::{
+    discord::embed,
+    esi,
+    filters::{DiscordWebhook, FilterSet, KillmailSide, Webhook},
+    names,
+    notifier::recorder::{Notification, Recorder},
+    persistence::{Store, provider::memory},
+    pipeline::Pipeline,
+    template::Template,
+    webhook,
+};
+
+mod common;
+
+use common::{FakeServer, Reply, fixtures};
+
+const JITA: u64 = 30000142; // The Forge
+const AMARR: u64 = 30002187; // Domain
+
+async fn store(filter_sets: Vec<(u64, &str)>) -> Arc<memory::Store> {
+    let store = Arc::new(memory::Store::new());
+    for (channel_id, filter) in filter_sets {
+        store
+            .add_filter_to_set(1, channel_id, filter)
+            .await
+            .unwrap();
+    }
+    store
+}
+
+fn corp(id: u64) -> fixtures::Participant {
+    fixtures::Participant {
+        character_id: Some(id + 1),
+        corporation_id: Some(id),
+        ship_type_id: Some(670),
+        ..Default::default()
+    }
+}
+
+// Runs the pipeline until everything queued on the server was processed and
+// returns what the recorder was notified of
+async fn notifications(server: &FakeServer, store: Arc<memory::Store>) -> Vec<Notification> {
+    let client = reqwest::Client::new();
+    let esi = esi::Client::new(client.clone(), server.url().as_str());
+    let recorder = Recorder::new(embed::Builder::new(
+        esi.clone(),
+        names::Resolver::new(esi, None),
+        "https://zkillboard.com",
+    ));
+
+    let pipeline = Pipeline::new(
+        client,
+        store,
+        Arc::new(recorder.clone()),
+        None,
+        server.url().as_str(),
+        "krusty-test",
+    )
+    .with_interval(Duration::from_millis(1));
+
+    let cancel_token = CancellationToken::new();
+
+    let inner_token = cancel_token.clone();
+    let task = tokio::spawn(async move { pipeline.run(inner_token).await });
+
+    server.drained().await;
+    cancel_token.cancel();
+    task.await.expect("pipeline task panicked");
+
+    recorder.notifications()
+}
+
+// Same as notifications, reduced to sorted (channel, kill, side) triples
+async fn run(
+    server: &FakeServer,
+    store: Arc<memory::Store>,
+) -> Vec<(u64, u64, Option<KillmailSide>)> {
+    let mut received = notifications(server, store)
+        .await
+        .into_iter()
+        .map(|n| (n.channel_id, n.kill_id, n.side))
+        .collect::<Vec<_>>();
+    received.sort_by_key(|(channel_id, kill_id, _)| (*kill_id, *channel_id));
+    received
+}
+
+#[tokio::test]
+async fn test_embedded_killmails_are_routed_to_matching_channels() {
+    let server = FakeServer::start().await;
+    server.push_package(fixtures::embedded_package(
+        1,
+        JITA,
+        corp(98000001),
+        &[corp(98000002)],
+    ));
+    server.push_package(fixtures::embedded_package(
+        2,
+        AMARR,
+        corp(98000002),
+        &[corp(98000003)],
+    ));
+
+    let store = store(vec![
+        (10, "system:30000142"),
+        (20, "region:10000043"),
+        (30, "corp:98000002"),
+    ])
+    .await;
+
+    let received = run(&server, store).await;
+
+    assert_eq!(
+        received,
+        vec![
+            (10, 1, None),
+            (30, 1, Some(KillmailSide::Attackers)),
+            (20, 2, None),
+            (30, 2, Some(KillmailSide::Victim)),
+        ]
+    );
+    assert_eq!(server.killmail_calls(), 0);
+}
+
+#[tokio::test]
+async fn test_href_killmails_are_fetched_from_esi() {
+    let server = FakeServer::start().await;
+    server.set_esi(
+        3,
+        Reply::Json(fixtures::esi_killmail(3, JITA, corp(98000001), &[])),
+    );
+    server.push_package(fixtures::href_package(3, &server.esi_href(3)));
+
+    let store = store(vec![(10, "corp:98000001:loss")]).await;
+
+    let received = run(&server, store).await;
+
+    assert_eq!(received, vec![(10, 3, Some(KillmailSide::Victim))]);
+    assert_eq!(server.killmail_calls(), 1);
+}
+
+#[tokio::test]
+async fn test_upstream_errors_do_not_stop_the_loop() {
+    let server = FakeServer::start().await;
+    server.push_null();
+    server.push_malformed();
+    server.push_error(502);
+    server.set_esi(4, Reply::Status(500, "{}".to_string()));
+    server.push_package(fixtures::href_package(4, &server.esi_href(4)));
+    server.set_esi(5, Reply::Json("not json".to_string()));
+    server.push_package(fixtures::href_package(5, &server.esi_href(5)));
+    server.push_package(fixtures::embedded_package(6, JITA, corp(98000001), &[]));
+
+    let store = store(vec![(10, "system:30000142")]).await;
+
+    let received = run(&server, store).await;
+
+    assert_eq!(received, vec![(10, 6, None)]);
+    assert_eq!(server.killmail_calls(), 2);
+}
+
+#[tokio::test]
+async fn test_excluded_and_unmatched_killmails_are_dropped() {
+    let server = FakeServer::start().await;
+    server.push_package(fixtures::embedded_package(
+        7,
+        JITA,
+        corp(98000001),
+        &[corp(98000009)],
+    ));
+    server.push_package(fixtures::embedded_package(
+        8,
+        AMARR,
+        corp(98000005),
+        &[corp(98000006)],
+    ));
+
+    let store = store(vec![
+        (10, "region:10000002"),
+        (10, "corp:98000009:exclude"),
+        (20, "corp:98000001:kills"),
+    ])
+    .await;
+
+    let received = run(&server, store).await;
+
+    assert_eq!(received, vec![]);
+}
+
+#[tokio::test]
+async fn test_no_filter_sets_delivers_nothing() {
+    let server = FakeServer::start().await;
+    server.push_package(fixtures::embedded_package(9, JITA, corp(98000001), &[]));
+
+    let received = run(&server, store(vec![]).await).await;
+
+    assert_eq!(received, vec![]);
+}
+
+#[tokio::test]
+async fn test_embed_is_built_for_each_channel() {
+    let server = FakeServer::start().await;
+    server.push_package(
+        fixtures::embedded_package(
+            10,
+            JITA,
+            fixtures::Participant {
+                character_id: Some(2112625428),
+                corporation_id: Some(98000001),
+                alliance_id: Some(99000001),
+                ship_type_id: Some(670),
+                damage: 300,
+                ..Default::default()
+            },
+            &[
+                fixtures::Participant {
+                    character_id: Some(95465499),
+                    corporation_id: Some(98000002),
+                    alliance_id: Some(99003581),
+                    ship_type_id: Some(33475),
+                    damage: 100,
+                    final_blow: true,
+                },
+                fixtures::Participant {
+                    character_id: Some(95465500),
+                    corporation_id: Some(98000002),
+                    alliance_id: Some(99003581),
+                    ship_type_id: Some(12747),
+                    damage: 200,
+                    ..Default::default()
+                },
+            ],
+        )
+        .replace(r#""href":"""#, r#""href":"","totalValue":1250000000.0"#),
+    );
+
+    let store = store(vec![(10, "corp:98000001"), (20, "corp:98000002")]).await;
+
+    let mut received = notifications(&server, store).await;
+    received.sort_by_key(|n| n.channel_id);
+
+    let field = |name: &str, value: &str| EmbedField {
+        inline: true,
+        name: name.to_string(),
+        value: value.to_string(),
+    };
+
+    let expected = |color: u32| Embed {
+        author: Some(EmbedAuthor {
+            icon_url: Some(
+                "https://images.evetech.net/characters/2112625428/portrait?size=64".to_string(),
+            ),
+            name: "Ava Tormund".to_string(),
+            proxy_icon_url: None,
+            url: Some("https://zkillboard.com/character/2112625428/".to_string()),
+        }),
+        color: Some(color),
+        description: None,
+        fields: vec![
+            field(
+                "Victim",
+                "[Ava Tormund](https://zkillboard.com/character/2112625428/)\n\
+                 [Krusty Industries](https://zkillboard.com/corporation/98000001/)\n\
+                 [Krusty Alliance](https://zkillboard.com/alliance/99000001/)",
+            ),
+            field("Ship", "[Capsule](https://zkillboard.com/ship/670/)"),
+            field(
+                "Location",
+                "[Jita](https://zkillboard.com/system/30000142/) (0.9)\n\
+                 [The Forge](https://zkillboard.com/region/10000002/)",
+            ),
+            field("Value", "1.25b ISK"),
+            field("Attackers", "2"),
+            field(
+                "Final blow",
+                "[Jun Okabe](https://zkillboard.com/character/95465499/) in \
+                 [Mobile Tractor Unit](https://zkillboard.com/ship/33475/)",
+            ),
+            field(
+                "Top damage",
+                "[Character 95465500](https://zkillboard.com/character/95465500/) in \
+                 [Mastodon](https://zkillboard.com/ship/12747/) (200 damage)",
+            ),
+            EmbedField {
+                inline: false,
+                name: "Alliances".to_string(),
+                value: "[Krusty Alliance](https://zkillboard.com/alliance/99000001/) (victim)\n\
+                        [Fraternity.](https://zkillboard.com/alliance/99003581/) (2)"
+                    .to_string(),
+            },
+        ],
+        footer: Some(EmbedFooter {
+            icon_url: None,
+            proxy_icon_url: None,
+            text: "Kill 10".to_string(),
+        }),
+        image: None,
+        kind: "rich".to_string(),
+        provider: None,
+        thumbnail: Some(EmbedThumbnail {
+            height: None,
+            proxy_url: None,
+            url: "https://images.evetech.net/types/670/render?size=128".to_string(),
+            width: None,
+        }),
+        timestamp: Some(Timestamp::from_secs(1760292251).unwrap()), // 2025-10-12T18:04:11Z
+        title: Some("Capsule destroyed in Jita".to_string()),
+        url: Some("https://zkillboard.com/kill/10/".to_string()),
+        video: None,
+    };
+
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[0].channel_id, 10);
+    assert_eq!(received[0].embed, expected(0x990000));
+    assert_eq!(received[1].channel_id, 20);
+    assert_eq!(received[1].embed, expected(0x93c47d));
+
+    // Character 95465500 is unknown to ESI, the batch is split so the rest
+    // still resolves
+    assert!(server.names_calls() > 1);
+}
+
+#[tokio::test]
+async fn test_webhook_targets_receive_signed_payloads() {
+    let server = FakeServer::start().await;
+    server.push_package(
+        fixtures::embedded_package(11, JITA, corp(98000001), &[corp(98000002)])
+            .replace(r#""href":"""#, r#""href":"","totalValue":1250000.5"#),
+    );
+
+    let store = store(vec![(10, "system:30000142")]).await;
+    store
+        .set_filter_set(FilterSet {
+            guild_id: 1,
+            channel_id: 20,
+            filters: vec!["corp:98000001".to_string()],
+            webhook: Some(Webhook {
+                url: server.hook_url("signed"),
+                secret: Some("hunter2".to_string()),
+            }),
+            discord_webhook: None,
+            template: None,
+            disabled: None,
+            config_filters: vec![],
+        })
+        .await
+        .unwrap();
+    store
+        .set_filter_set(FilterSet {
+            guild_id: 1,
+            channel_id: 30,
+            filters: vec!["corp:98000002".to_string()],
+            webhook: Some(Webhook {
+                url: server.hook_url("unsigned"),
+                secret: None,
+            }),
+            discord_webhook: None,
+            template: None,
+            disabled: None,
+            config_filters: vec![],
+        })
+        .await
+        .unwrap();
+
+    let received = run(&server, store).await;
+
+    // Webhook targets don't go through the Discord notifier
+    assert_eq!(received, vec![(10, 11, None)]);
+
+    let mut hooks = server.hooks();
+    hooks.sort_by_key(|h| h.path.clone());
+    assert_eq!(hooks.len(), 2);
+
+    let signed = &hooks[0];
+    assert_eq!(signed.path, "/hooks/signed");
+    assert_eq!(
+        signed
+            .headers
+            .get(&webhook::SIGNATURE_HEADER.to_lowercase()),
+        Some(&webhook::sign("hunter2", signed.body.as_bytes()).unwrap())
+    );
+
+    let payload: simd_json::OwnedValue =
+        simd_json::from_slice(&mut signed.body.clone().into_bytes()).unwrap();
+    assert_eq!(payload["kill_id"], 11);
+    assert_eq!(payload["side"], "victim");
+    assert_eq!(payload["value"], 1250000.5);
+    assert_eq!(payload["system_id"], JITA);
+    assert_eq!(payload["region_id"], 10000002);
+    assert_eq!(payload["victim"]["corporation_id"], 98000001);
+    assert_eq!(payload["attackers"][0]["corporation_id"], 98000002);
+
+    let unsigned = &hooks[1];
+    assert_eq!(unsigned.path, "/hooks/unsigned");
+    assert!(
+        !unsigned
+            .headers
+            .contains_key(&webhook::SIGNATURE_HEADER.to_lowercase())
+    );
+    assert!(unsigned.body.contains(r#""side":"attackers""#));
+}
+
+#[tokio::test]
+async fn test_unreachable_targets_are_disabled() {
+    let server = FakeServer::start().await;
+    for kill_id in 1..=4 {
+        server.push_package(fixtures::embedded_package(
+            kill_id,
+            JITA,
+            corp(98000001),
+            &[],
+        ));
+    }
+
+    let store = store(vec![(10, "system:30000142")]).await;
+    store
+        .set_filter_set(FilterSet {
+            guild_id: 1,
+            channel_id: 20,
+            filters: vec!["system:30000142".to_string()],
+            webhook: Some(Webhook {
+                url: server.hook_url("gone"),
+                secret: None,
+            }),
+            discord_webhook: None,
+            template: None,
+            disabled: None,
+            config_filters: vec![],
+        })
+        .await
+        .unwrap();
+
+    let received = run(&server, store.clone()).await;
+
+    // Other channels keep receiving kills
+    assert_eq!(received.len(), 4);
+    // The fourth kill isn't sent once the target refused three in a row
+    assert_eq!(server.hooks().len(), 3);
+
+    let disabled = store
+        .get_channel_filter_set(20)
+        .await
+        .unwrap()
+        .disabled
+        .unwrap();
+    assert!(disabled.reason.starts_with("failed to send webhook"));
+    assert!(disabled.reason.ends_with("(status 404)"));
+    assert!(
+        store
+            .get_channel_filter_set(10)
+            .await
+            .unwrap()
+            .disabled
+            .is_none()
+    );
+
+    // Changing the filters resumes deliveries
+    store
+        .add_filter_to_set(1, 20, "corp:98000001")
+        .await
+        .unwrap();
+    assert!(
+        store
+            .get_channel_filter_set(20)
+            .await
+            .unwrap()
+            .disabled
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn test_discord_webhook_is_passed_to_notifier() {
+    let server = FakeServer::start().await;
+    server.push_package(fixtures::embedded_package(12, JITA, corp(98000001), &[]));
+
+    let discord_webhook = DiscordWebhook {
+        username: Some("Home Defence".to_string()),
+        ..DiscordWebhook::from_url("https://discord.com/api/webhooks/42/token").unwrap()
+    };
+
+    let store = store(vec![(10, "system:30000142")]).await;
+    store
+        .set_filter_set(FilterSet {
+            guild_id: 1,
+            channel_id: 20,
+            filters: vec!["system:30000142".to_string()],
+            webhook: None,
+            discord_webhook: Some(discord_webhook.clone()),
+            template: None,
+            disabled: None,
+            config_filters: vec![],
+        })
+        .await
+        .unwrap();
+
+    let mut received = notifications(&server, store).await;
+    received.sort_by_key(|n| n.channel_id);
+
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[0].discord_webhook, None);
+    assert_eq!(received[1].discord_webhook, Some(discord_webhook));
+}
+
+#[tokio::test]
+async fn test_channel_templates_shape_the_embed() {
+    let server = FakeServer::start().await;
+    server.push_package(fixtures::embedded_package(13, JITA, corp(98000001), &[]));
+
+    let store = store(vec![(10, "system:30000142")]).await;
+    store
+        .set_filter_set(FilterSet {
+            guild_id: 1,
+            channel_id: 20,
+            filters: vec!["system:30000142".to_string()],
+            webhook: None,
+            discord_webhook: None,
+            template: Some(Template::Compact),
+            disabled: None,
+            config_filters: vec![],
+        })
+        .await
+        .unwrap();
+    store
+        .set_filter_set(FilterSet {
+            guild_id: 1,
+            channel_id: 30,
+            filters: vec!["system:30000142".to_string()],
+            webhook: None,
+            discord_webhook: None,
+            template: Some(
+                Template::custom(
+                    "{victim.corp} lost a {victim.ship} in {system} ({system.security}), {region}"
+                        .to_string(),
+                    Some("[zKillboard]({kill.url})".to_string()),
+                )
+                .unwrap(),
+            ),
+            disabled: None,
+            config_filters: vec![],
+        })
+        .await
+        .unwrap();
+
+    let mut received = notifications(&server, store).await;
+    received.sort_by_key(|n| n.channel_id);
+    assert_eq!(received.len(), 3);
+
+    // Channels without a template get the full embed
+    assert!(!received[0].embed.fields.is_empty());
+
+    let compact = &received[1].embed;
+    assert_eq!(
+        compact.title.as_deref(),
+        Some("Capsule destroyed in Jita (0.00 ISK)")
+    );
+    assert!(compact.fields.is_empty());
+    assert_eq!(compact.description, None);
+
+    let custom = &received[2].embed;
+    assert_eq!(
+        custom.title.as_deref(),
+        Some("Krusty Industries lost a Capsule in Jita (0.9), The Forge")
+    );
+    assert_eq!(
+        custom.description.as_deref(),
+        Some("[zKillboard](https://zkillboard.com/kill/13/)")
+    );
+}